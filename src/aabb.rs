@@ -1,5 +1,8 @@
 use nalgebra_glm::{vec3, Vec3};
 
+// Boxes closer than this are touching, not overlapping, so float errors don't get the player stuck
+const EPSILON: f32 = 1e-5;
+
 #[derive(Debug, Copy, Clone)]
 
 pub struct AABB {
@@ -25,6 +28,51 @@ impl AABB {
             && self.mins.z < other.maxs.z
             && self.maxs.z > other.mins.z
     }
+
+    // Box covering everything this box goes through when moved by `offset`
+    pub fn expanded_towards(&self, offset: &Vec3) -> AABB {
+        AABB::new(
+            self.mins + offset.inf(&Vec3::zeros()),
+            self.maxs + offset.sup(&Vec3::zeros()),
+        )
+    }
+
+    // Part of `offset` along `axis` that this box can move before hitting `other`
+    pub fn clip_offset(&self, other: &AABB, axis: usize, offset: f32) -> f32 {
+        let overlaps_across = (0..3).filter(|&other_axis| other_axis != axis).all(|a| {
+            self.mins[a] < other.maxs[a] - EPSILON && self.maxs[a] > other.mins[a] + EPSILON
+        });
+
+        if !overlaps_across {
+            return offset;
+        }
+
+        if offset > 0.0 && self.maxs[axis] <= other.mins[axis] + EPSILON {
+            offset.min(other.mins[axis] - self.maxs[axis])
+        } else if offset < 0.0 && self.mins[axis] >= other.maxs[axis] - EPSILON {
+            offset.max(other.maxs[axis] - self.mins[axis])
+        } else {
+            offset
+        }
+    }
+
+    // Moves the box by `offset`, vertically first, stopping each axis at the first box in the way
+    // Returns how far it actually moved
+    pub fn sweep(&mut self, obstacles: &[AABB], offset: &Vec3) -> Vec3 {
+        let mut moved = *offset;
+
+        for axis in [1, 0, 2] {
+            for obstacle in obstacles {
+                moved[axis] = self.clip_offset(obstacle, axis, moved[axis]);
+            }
+
+            let mut translation = Vec3::zeros();
+            translation[axis] = moved[axis];
+            self.translate(&translation);
+        }
+
+        moved
+    }
 }
 
 pub fn get_block_aabb(mins: &Vec3) -> AABB {
@@ -0,0 +1,431 @@
+use crate::block_shape::BlockShape;
+use crate::block_state::Property;
+use crate::block_texture_sides::BlockFaces;
+use crate::chunk::BlockID;
+use crate::inventory::item::{ToolKind, ToolTier};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/*
+    Blocks are described in a RON file (see assets/blocks.ron) with implicit_some enabled, one entry per block :
+
+    (
+        name: "grass",
+        id: 4,
+        faces: Sides(sides: "textures/blocks/grass_block_side.png", top: "...", bottom: "..."),
+        shape: Cube,                    // optional, see block_shape.rs
+        transparent: false,             // optional, false for cubes and true for the other shapes
+        render_layer: Opaque,           // optional, Opaque by default and Translucent for transparent cubes
+        properties: [Axis],             // optional, see block_state.rs
+        casts_ambient_occlusion: true,  // optional, defaults to the opposite of `transparent`
+        solid: true,                    // optional, true by default
+        fluid: (flow_delay: 5, flow_distance: 7, infinite_sources: true),  // optional, see FluidProperties
+        light_emission: 0,              // optional, from 0 to 15
+        hardness: 0.6,                  // optional, negative for blocks that can't be broken
+        tool: Shovel,                   // optional, the tool that mines it faster, see mining.rs
+        tool_tier: Wooden,              // optional, the weakest tier of `tool` that harvests it
+        in_inventory: true,             // optional, true by default
+    )
+
+    The id is what gets written in the world save, so it must never change once a block is used
+    Air always has the id 0 and no textures
+
+    The blocks of `KnownBlocks` are used by the terrain generator and the fluids, the registry
+    doesn't load without them
+*/
+
+static BLOCK_REGISTRY: OnceLock<BlockRegistry> = OnceLock::new();
+
+pub const MAX_LIGHT_LEVEL: u8 = 15;
+
+// How a fluid flows, see fluids.rs
+#[derive(Deserialize, Copy, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct FluidProperties {
+    // Game ticks between two steps of the flow
+    pub flow_delay: u64,
+    // Blocks it flows sideways from a source, from 1 to 7
+    pub flow_distance: u8,
+    // A flowing block between two sources becomes a source
+    #[serde(default)]
+    pub infinite_sources: bool,
+}
+
+// Render pass a block is drawn in, see `render_loaded_chunks`
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RenderLayer {
+    Opaque,
+    // Pixels are either fully opaque or fully transparent (leaves), no blending needed
+    Cutout,
+    // Blended with what is behind, drawn last and back to front (glass, water)
+    Translucent,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BlockDescription {
+    name: String,
+    id: u8,
+    faces: Option<BlockFaces<String>>,
+    #[serde(default)]
+    shape: BlockShape,
+    transparent: Option<bool>,
+    render_layer: Option<RenderLayer>,
+    #[serde(default)]
+    properties: Vec<Property>,
+    casts_ambient_occlusion: Option<bool>,
+    solid: Option<bool>,
+    fluid: Option<FluidProperties>,
+    #[serde(default)]
+    light_emission: u8,
+    #[serde(default)]
+    hardness: f32,
+    tool: Option<ToolKind>,
+    tool_tier: Option<ToolTier>,
+    in_inventory: Option<bool>,
+}
+
+pub struct BlockDefinition {
+    pub name: String,
+    pub id: BlockID,
+    // Texture of each face, None only for air
+    pub faces: Option<BlockFaces<String>>,
+    pub shape: BlockShape,
+    // Faces next to a transparent block are drawn, light goes through it
+    pub transparent: bool,
+    pub render_layer: RenderLayer,
+    // Stored in the block states, in this order
+    pub properties: Vec<Property>,
+    // Darkens the corners of the faces next to it, leaves are transparent but still cast it
+    pub casts_ambient_occlusion: bool,
+    // Stops the player and the particles
+    pub solid: bool,
+    // Like translucent blocks, faces between two blocks of the same fluid are not drawn
+    pub fluid: Option<FluidProperties>,
+    pub light_emission: u8,
+    pub hardness: f32,
+    pub tool: Option<ToolKind>,
+    // Nothing drops when it is broken without this tier of `tool` or a better one
+    pub tool_tier: Option<ToolTier>,
+    // Whether the player starts with it, debug blocks are left out
+    pub in_inventory: bool,
+}
+
+// Blocks the game needs to know about, looked up by name once when the registry is loaded
+#[derive(Copy, Clone, Debug)]
+pub struct KnownBlocks {
+    pub grass: BlockID,
+    pub dirt: BlockID,
+    pub stone: BlockID,
+    pub bedrock: BlockID,
+    pub sand: BlockID,
+    pub snow: BlockID,
+    pub cactus: BlockID,
+    pub oak_log: BlockID,
+    pub oak_leaves: BlockID,
+    pub coal_ore: BlockID,
+    pub iron_ore: BlockID,
+    pub gold_ore: BlockID,
+    pub diamond_ore: BlockID,
+    pub water: BlockID,
+    pub lava: BlockID,
+    pub obsidian: BlockID,
+    pub cobblestone: BlockID,
+}
+
+impl KnownBlocks {
+    fn resolve(ids_by_name: &HashMap<String, BlockID>) -> Result<KnownBlocks, String> {
+        let id = |name: &str| {
+            ids_by_name.get(name).copied().ok_or_else(|| {
+                format!("The block registry has no block named \"{name}\", the game needs it")
+            })
+        };
+
+        Ok(KnownBlocks {
+            grass: id("grass")?,
+            dirt: id("dirt")?,
+            stone: id("stone")?,
+            bedrock: id("bedrock")?,
+            sand: id("sand")?,
+            snow: id("snow")?,
+            cactus: id("cactus")?,
+            oak_log: id("oak_log")?,
+            oak_leaves: id("oak_leaves")?,
+            coal_ore: id("coal_ore")?,
+            iron_ore: id("iron_ore")?,
+            gold_ore: id("gold_ore")?,
+            diamond_ore: id("diamond_ore")?,
+            water: id("water")?,
+            lava: id("lava")?,
+            obsidian: id("obsidian")?,
+            cobblestone: id("cobblestone")?,
+        })
+    }
+}
+
+pub struct BlockRegistry {
+    // Indexed by the numeric id
+    definitions: Vec<Option<BlockDefinition>>,
+    ids_by_name: HashMap<String, BlockID>,
+    pub known: KnownBlocks,
+}
+
+impl BlockRegistry {
+    pub fn load(path: impl AsRef<Path>) -> Result<BlockRegistry, String> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read the block registry {}: {err}", path.display()))?;
+
+        BlockRegistry::parse(&text).map_err(|err| format!("{}: {err}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> Result<BlockRegistry, String> {
+        let descriptions: Vec<BlockDescription> =
+            ron::from_str(text).map_err(|err| format!("Invalid block registry: {err}"))?;
+
+        let mut definitions: Vec<Option<BlockDefinition>> =
+            (0..=u8::MAX).map(|_| None).collect();
+        let mut ids_by_name = HashMap::new();
+
+        for description in descriptions {
+            let BlockDescription {
+                name,
+                id,
+                faces,
+                shape,
+                transparent,
+                render_layer,
+                properties,
+                casts_ambient_occlusion,
+                solid,
+                fluid,
+                light_emission,
+                hardness,
+                tool,
+                tool_tier,
+                in_inventory,
+            } = description;
+
+            if let Some(other) = &definitions[id as usize] {
+                return Err(format!(
+                    "Blocks \"{}\" and \"{name}\" both use the id {id}",
+                    other.name
+                ));
+            }
+
+            if ids_by_name.contains_key(&name) {
+                return Err(format!("Block \"{name}\" is defined more than once"));
+            }
+
+            if light_emission > MAX_LIGHT_LEVEL {
+                return Err(format!(
+                    "Block \"{name}\" emits light {light_emission}, the maximum is {MAX_LIGHT_LEVEL}"
+                ));
+            }
+
+            let property_bits: u32 = properties.iter().map(|property| property.bits()).sum();
+            if property_bits > u8::BITS {
+                return Err(format!(
+                    "Block \"{name}\" has too many properties, they take {property_bits} bits out of {}",
+                    u8::BITS
+                ));
+            }
+
+            for (i, property) in properties.iter().enumerate() {
+                if properties[..i].contains(property) {
+                    return Err(format!("Block \"{name}\" has the property {property:?} twice"));
+                }
+            }
+
+            if shape == BlockShape::Stairs && !properties.contains(&Property::Facing) {
+                return Err(format!("Stairs \"{name}\" need the Facing property"));
+            }
+
+            if tool_tier.is_some() && tool.is_none() {
+                return Err(format!("Block \"{name}\" has a tool tier but no tool"));
+            }
+
+            if let Some(fluid) = fluid {
+                if !properties.contains(&Property::Level) {
+                    return Err(format!("Fluid \"{name}\" needs the Level property"));
+                }
+
+                if !(1..=7).contains(&fluid.flow_distance) {
+                    return Err(format!(
+                        "Fluid \"{name}\" flows {} blocks, it must be between 1 and 7",
+                        fluid.flow_distance
+                    ));
+                }
+            }
+
+            match (&faces, id) {
+                (Some(_), 0) => return Err(format!("Air (\"{name}\") can't have textures")),
+                (None, 0) => {}
+                (None, _) => return Err(format!("Block \"{name}\" has no textures")),
+                (Some(faces), _) => {
+                    for texture in faces.textures() {
+                        if !Path::new(texture).is_file() {
+                            return Err(format!(
+                                "Block \"{name}\" uses the texture {texture} which doesn't exist"
+                            ));
+                        }
+                    }
+                }
+            }
+
+            // Light goes around the blocks that aren't cubes, and they don't hide their neighbours
+            let transparent = transparent.unwrap_or(!shape.is_cube());
+
+            ids_by_name.insert(name.clone(), BlockID::from_raw(id));
+            definitions[id as usize] = Some(BlockDefinition {
+                name,
+                id: BlockID::from_raw(id),
+                faces,
+                shape,
+                transparent,
+                render_layer: render_layer.unwrap_or(if transparent && shape.is_cube() {
+                    RenderLayer::Translucent
+                } else {
+                    RenderLayer::Opaque
+                }),
+                properties,
+                casts_ambient_occlusion: casts_ambient_occlusion.unwrap_or(!transparent),
+                solid: solid.unwrap_or(id != 0),
+                fluid,
+                light_emission,
+                hardness,
+                tool,
+                tool_tier,
+                in_inventory: in_inventory.unwrap_or(true),
+            });
+        }
+
+        if definitions[0].is_none() {
+            return Err("The block registry must define air with the id 0".to_string());
+        }
+
+        Ok(BlockRegistry {
+            known: KnownBlocks::resolve(&ids_by_name)?,
+            definitions,
+            ids_by_name,
+        })
+    }
+
+    // Makes the registry available to the whole game, including the chunk workers
+    pub fn install(self) {
+        if BLOCK_REGISTRY.set(self).is_err() {
+            panic!("The block registry was already installed");
+        }
+    }
+
+    pub fn get(&self, id: BlockID) -> Option<&BlockDefinition> {
+        self.definitions[id.to_u8() as usize].as_ref()
+    }
+
+    pub fn id_of(&self, name: &str) -> Option<BlockID> {
+        self.ids_by_name.get(name).copied()
+    }
+
+    // Every block except air, ordered by id
+    pub fn blocks(&self) -> impl Iterator<Item = &BlockDefinition> {
+        self.definitions.iter().skip(1).flatten()
+    }
+}
+
+pub fn block_registry() -> &'static BlockRegistry {
+    BLOCK_REGISTRY
+        .get()
+        .expect("The block registry is used before being loaded")
+}
+
+pub fn known_blocks() -> &'static KnownBlocks {
+    &block_registry().known
+}
+
+// Tests share the registry of the game, installed by the first one that needs it
+#[cfg(test)]
+pub fn install_test_registry() {
+    BLOCK_REGISTRY.get_or_init(|| BlockRegistry::load("assets/blocks.ron").unwrap());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIRT: &str = r#"(name: "dirt", id: 1, faces: All("textures/blocks/dirt.png"))"#;
+
+    // Air, then the given blocks
+    fn parse_error(blocks: &str) -> String {
+        let text = format!("#![enable(implicit_some)]\n[(name: \"air\", id: 0), {blocks}]");
+
+        match BlockRegistry::parse(&text) {
+            Ok(_) => panic!("The registry was parsed without errors"),
+            Err(err) => err,
+        }
+    }
+
+    #[test]
+    fn the_game_registry_loads() {
+        let registry = BlockRegistry::load("assets/blocks.ron").unwrap();
+
+        assert_eq!(registry.known.water, registry.id_of("water").unwrap());
+        assert_eq!(registry.get(BlockID::AIR).unwrap().name, "air");
+    }
+
+    #[test]
+    fn duplicate_ids_are_an_error() {
+        let blocks = r#"(name: "dirt", id: 1, faces: All("textures/blocks/dirt.png")),
+            (name: "stone", id: 1, faces: All("textures/blocks/stone.png"))"#;
+
+        assert_eq!(parse_error(blocks), "Blocks \"dirt\" and \"stone\" both use the id 1");
+    }
+
+    #[test]
+    fn duplicate_names_are_an_error() {
+        let blocks = format!("{DIRT}, {}", DIRT.replace("id: 1", "id: 2"));
+
+        assert_eq!(parse_error(&blocks), "Block \"dirt\" is defined more than once");
+    }
+
+    #[test]
+    fn missing_textures_are_an_error() {
+        let blocks = r#"(name: "dirt", id: 1, faces: All("textures/blocks/missing.png"))"#;
+
+        assert_eq!(
+            parse_error(blocks),
+            "Block \"dirt\" uses the texture textures/blocks/missing.png which doesn't exist"
+        );
+    }
+
+    #[test]
+    fn light_over_15_is_an_error() {
+        let blocks = r#"(name: "lamp", id: 1, faces: All("textures/blocks/glowstone.png"),
+            light_emission: 16)"#;
+
+        assert_eq!(parse_error(blocks), "Block \"lamp\" emits light 16, the maximum is 15");
+    }
+
+    #[test]
+    fn stairs_without_facing_are_an_error() {
+        let blocks = r#"(name: "oak_stairs", id: 1, faces: All("textures/blocks/oak_planks.png"),
+            shape: Stairs)"#;
+
+        assert_eq!(parse_error(blocks), "Stairs \"oak_stairs\" need the Facing property");
+    }
+
+    #[test]
+    fn missing_known_blocks_are_an_error() {
+        assert_eq!(
+            parse_error(DIRT),
+            "The block registry has no block named \"grass\", the game needs it"
+        );
+    }
+
+    #[test]
+    fn invalid_ron_is_an_error() {
+        assert!(parse_error("(name: \"dirt\"").starts_with("Invalid block registry"));
+    }
+}
@@ -0,0 +1,268 @@
+use crate::aabb::AABB;
+use crate::block_state::{BlockState, Facing};
+use crate::chunk::BlockID;
+use crate::chunk_manager::ChunkManager;
+use nalgebra_glm::vec3;
+use serde::Deserialize;
+
+/*
+    Shape of a block, it drives the mesh of the block, the boxes the player collides with and the boxes
+    the raycast can hit. Boxes are in 16ths of a block, like the pixels of the textures :
+
+        Cube        the whole block, the only shape merged by the greedy meshing
+        Slab        the bottom half
+        Stairs      the bottom half and a step on the side given by the `Facing` property
+        Fence       a post with bars towards the fences and the solid cubes around it
+                    the player collides with a 1.5 blocks high box so they can't jump over it
+        Cross       2 diagonal quads for plants, nothing to collide with
+
+    A face of a neighbour is only hidden by a side of the block that the shape covers completely
+*/
+
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum BlockShape {
+    #[default]
+    Cube,
+    Slab,
+    Stairs,
+    Fence,
+    Cross,
+}
+
+// Coordinates in 16ths of a block, relative to the block
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ShapeBox {
+    pub mins: [i32; 3],
+    pub maxs: [i32; 3],
+}
+
+const fn shape_box(mins: [i32; 3], maxs: [i32; 3]) -> ShapeBox {
+    ShapeBox { mins, maxs }
+}
+
+const FULL: ShapeBox = shape_box([0, 0, 0], [16, 16, 16]);
+const SLAB: ShapeBox = shape_box([0, 0, 0], [16, 8, 16]);
+const FENCE_POST: ShapeBox = shape_box([6, 0, 6], [10, 16, 10]);
+const FENCE_HEIGHT: i32 = 24;
+// Selecting a plant is easier with a box a bit smaller than the block
+const CROSS_SELECTION: ShapeBox = shape_box([2, 0, 2], [14, 13, 14]);
+
+// Horizontal neighbours a fence connects to, one bit per `Facing`
+pub type FenceConnections = u8;
+
+// Fences connect to other fences and to the full blocks that hide what is behind them
+pub fn fence_connections(neighbour: impl Fn(i32, i32) -> Option<BlockID>) -> FenceConnections {
+    let mut connections = 0;
+
+    for facing in Facing::ALL {
+        let (dx, dz) = facing.offset();
+
+        let connects = neighbour(dx, dz).map_or(false, |block| match block.shape() {
+            BlockShape::Fence => true,
+            BlockShape::Cube => block.is_solid() && !block.is_transparent(),
+            _ => false,
+        });
+
+        if connects {
+            connections |= 1 << facing as u8;
+        }
+    }
+
+    connections
+}
+
+impl ShapeBox {
+    // Box stretched from the middle of the block to its side towards `facing`
+    // `across` is the range of the box across that direction, `up` its vertical range
+    fn towards(facing: Facing, across: [i32; 2], up: [i32; 2]) -> ShapeBox {
+        let [a0, a1] = across;
+        let [y0, y1] = up;
+
+        match facing {
+            Facing::North => shape_box([a0, y0, 0], [a1, y1, a0]),
+            Facing::South => shape_box([a0, y0, a1], [a1, y1, 16]),
+            Facing::West => shape_box([0, y0, a0], [a0, y1, a1]),
+            Facing::East => shape_box([a1, y0, a0], [16, y1, a1]),
+        }
+    }
+
+    // Whether the face of the box along `normal` is on the side of the block
+    pub fn touches_side(&self, normal: [i32; 3]) -> bool {
+        (0..3).any(|axis| match normal[axis] {
+            1 => self.maxs[axis] == 16,
+            -1 => self.mins[axis] == 0,
+            _ => false,
+        })
+    }
+
+    pub fn to_aabb(&self, (x, y, z): (i32, i32, i32)) -> AABB {
+        let to_world = |coords: [i32; 3]| {
+            vec3(x as f32, y as f32, z as f32)
+                + vec3(coords[0], coords[1], coords[2]).map(|c| c as f32 / 16.0)
+        };
+
+        AABB::new(to_world(self.mins), to_world(self.maxs))
+    }
+}
+
+impl BlockShape {
+    pub fn is_cube(&self) -> bool {
+        *self == BlockShape::Cube
+    }
+
+    // Step of the stairs, from the middle of the block to the side it faces
+    fn stairs_step(state: BlockState) -> ShapeBox {
+        let facing = state.facing();
+        let (dx, dz) = facing.offset();
+
+        // Half of the block along the facing direction, the whole width across it
+        let along = |d: i32| {
+            if d > 0 {
+                [8, 16]
+            } else if d < 0 {
+                [0, 8]
+            } else {
+                [0, 16]
+            }
+        };
+        let [x0, x1] = along(dx);
+        let [z0, z1] = along(dz);
+
+        shape_box([x0, 8, z0], [x1, 16, z1])
+    }
+
+    // Boxes drawn for the block, crosses are meshed separately
+    pub fn model_boxes(&self, state: BlockState, connections: FenceConnections) -> Vec<ShapeBox> {
+        match self {
+            BlockShape::Cube => vec![FULL],
+            BlockShape::Slab => vec![SLAB],
+            BlockShape::Stairs => vec![SLAB, BlockShape::stairs_step(state)],
+            BlockShape::Fence => {
+                let mut boxes = vec![FENCE_POST];
+
+                for facing in Facing::ALL {
+                    if connections & (1 << facing as u8) != 0 {
+                        boxes.push(ShapeBox::towards(facing, [7, 9], [12, 15]));
+                        boxes.push(ShapeBox::towards(facing, [7, 9], [6, 9]));
+                    }
+                }
+
+                boxes
+            }
+            BlockShape::Cross => Vec::new(),
+        }
+    }
+
+    // Boxes the player collides with, they can go above the block
+    pub fn collision_boxes(
+        &self,
+        state: BlockState,
+        connections: FenceConnections,
+    ) -> Vec<ShapeBox> {
+        match self {
+            BlockShape::Fence => {
+                let mut boxes = vec![shape_box([6, 0, 6], [10, FENCE_HEIGHT, 10])];
+
+                for facing in Facing::ALL {
+                    if connections & (1 << facing as u8) != 0 {
+                        boxes.push(ShapeBox::towards(facing, [6, 10], [0, FENCE_HEIGHT]));
+                    }
+                }
+
+                boxes
+            }
+            BlockShape::Cross => Vec::new(),
+            _ => self.model_boxes(state, connections),
+        }
+    }
+
+    // Boxes the raycast can hit, the block outline is drawn around them
+    pub fn selection_boxes(
+        &self,
+        state: BlockState,
+        connections: FenceConnections,
+    ) -> Vec<ShapeBox> {
+        match self {
+            BlockShape::Cross => vec![CROSS_SELECTION],
+            _ => self.model_boxes(state, connections),
+        }
+    }
+
+    // One bit per face of the block (same order as the mesh faces) that the shape covers completely
+    pub fn full_faces(&self, state: BlockState) -> u8 {
+        match self {
+            BlockShape::Cube => 0b111111,
+            BlockShape::Slab => 1 << 3,
+            BlockShape::Stairs => {
+                let back = match state.facing() {
+                    Facing::East => 0,
+                    Facing::West => 1,
+                    Facing::South => 4,
+                    Facing::North => 5,
+                };
+                (1 << 3) | (1 << back)
+            }
+            BlockShape::Fence | BlockShape::Cross => 0,
+        }
+    }
+}
+
+impl ChunkManager {
+    fn fence_connections_at(&self, x: i32, y: i32, z: i32) -> FenceConnections {
+        fence_connections(|dx, dz| self.get_block(x + dx, y, z + dz))
+    }
+
+    // In world coordinates, empty for the blocks the player goes through
+    pub fn get_collision_boxes(&self, x: i32, y: i32, z: i32) -> Vec<AABB> {
+        match self.get_block_state(x, y, z) {
+            Some(state) if state.block().is_solid() => {
+                let connections = self.fence_connections_at(x, y, z);
+
+                state
+                    .block()
+                    .shape()
+                    .collision_boxes(state, connections)
+                    .iter()
+                    .map(|shape_box| shape_box.to_aabb((x, y, z)))
+                    .collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    // Collision boxes of every block overlapping `area`, like the one swept by something moving
+    pub fn get_collision_boxes_in(&self, area: &AABB) -> Vec<AABB> {
+        let mins = area.mins.map(|c| c.floor() as i32);
+        let maxs = area.maxs.map(|c| c.floor() as i32);
+        let mut boxes = Vec::new();
+
+        // Fences are taller than a block, the ones below the area can still collide
+        for y in mins.y - 1..=maxs.y {
+            for z in mins.z..=maxs.z {
+                for x in mins.x..=maxs.x {
+                    boxes.extend(self.get_collision_boxes(x, y, z));
+                }
+            }
+        }
+
+        boxes
+    }
+
+    // In world coordinates, empty for air and fluids
+    pub fn get_selection_boxes(&self, x: i32, y: i32, z: i32) -> Vec<AABB> {
+        match self.get_block_state(x, y, z) {
+            Some(state) if !state.block().is_air() && !state.block().is_fluid() => {
+                let connections = self.fence_connections_at(x, y, z);
+
+                state
+                    .block()
+                    .shape()
+                    .selection_boxes(state, connections)
+                    .iter()
+                    .map(|shape_box| shape_box.to_aabb((x, y, z)))
+                    .collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+}
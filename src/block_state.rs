@@ -0,0 +1,211 @@
+use crate::chunk::BlockID;
+use nalgebra_glm::{IVec3, Vec3};
+use serde::Deserialize;
+
+/*
+    A block with the values of its properties, like a log with the axis it lies along
+
+    Each block declares the properties it has in the block registry, in order. Their values are packed
+    in a byte, the first property in the lowest bits :
+
+        oak_log     properties: [Axis]      bits 0..2 axis
+        water       properties: [Level]     bits 0..4 fluid level
+
+    Every block has at most 8 bits of properties, so a state fits in 2 bytes
+    A state with every property at 0 is the default state of the block
+*/
+
+// Something about a block that can change from one block to another of the same type
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Property {
+    // Axis the block lies along, see `Axis`
+    Axis,
+    // Horizontal direction the block faces, see `Facing`
+    Facing,
+    Open,
+    Lit,
+    // Fluid level, see fluids.rs
+    Level,
+}
+
+impl Property {
+    // Number of values the property can take, from 0 to count - 1
+    pub fn value_count(&self) -> u8 {
+        match self {
+            Property::Axis => 3,
+            Property::Facing => 4,
+            Property::Open | Property::Lit => 2,
+            Property::Level => 9,
+        }
+    }
+
+    pub fn bits(&self) -> u32 {
+        u8::BITS - (self.value_count() - 1).leading_zeros()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Axis {
+    // Blocks stand up by default
+    Y = 0,
+    X = 1,
+    Z = 2,
+}
+
+impl Axis {
+    fn from_value(value: u8) -> Axis {
+        match value {
+            1 => Axis::X,
+            2 => Axis::Z,
+            _ => Axis::Y,
+        }
+    }
+
+    // Axis of a face normal, like the one returned by the raycast
+    pub fn from_normal(normal: &IVec3) -> Axis {
+        if normal.x != 0 {
+            Axis::X
+        } else if normal.z != 0 {
+            Axis::Z
+        } else {
+            Axis::Y
+        }
+    }
+}
+
+// Horizontal directions, in the order of the values of the `Facing` property
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Facing {
+    // Towards -z
+    North = 0,
+    // Towards +x
+    East = 1,
+    South = 2,
+    West = 3,
+}
+
+impl Facing {
+    pub const ALL: [Facing; 4] = [Facing::North, Facing::East, Facing::South, Facing::West];
+
+    fn from_value(value: u8) -> Facing {
+        Facing::ALL[value as usize % 4]
+    }
+
+    // Closest horizontal direction, like the one the player looks at
+    pub fn from_direction(direction: &Vec3) -> Facing {
+        if direction.x.abs() > direction.z.abs() {
+            if direction.x > 0.0 {
+                Facing::East
+            } else {
+                Facing::West
+            }
+        } else if direction.z > 0.0 {
+            Facing::South
+        } else {
+            Facing::North
+        }
+    }
+
+    // (dx, dz) of the neighbour in this direction
+    pub fn offset(&self) -> (i32, i32) {
+        match self {
+            Facing::North => (0, -1),
+            Facing::East => (1, 0),
+            Facing::South => (0, 1),
+            Facing::West => (-1, 0),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BlockState {
+    block: BlockID,
+    properties: u8,
+}
+
+impl BlockState {
+    pub const AIR: BlockState = BlockState {
+        block: BlockID::AIR,
+        properties: 0,
+    };
+
+    pub fn new(block: BlockID) -> BlockState {
+        BlockState {
+            block,
+            properties: 0,
+        }
+    }
+
+    // State read from the world save, None if the properties don't fit the block
+    pub fn from_raw(block: BlockID, properties: u8) -> Option<BlockState> {
+        let state = BlockState { block, properties };
+        let mut used_bits = 0;
+
+        for property in &block.definition().properties {
+            if state.get(*property) >= property.value_count() {
+                return None;
+            }
+
+            used_bits += property.bits();
+        }
+
+        (properties.checked_shr(used_bits).unwrap_or(0) == 0).then_some(state)
+    }
+
+    pub fn block(&self) -> BlockID {
+        self.block
+    }
+
+    // Packed values of the properties, written to the world save
+    pub fn properties(&self) -> u8 {
+        self.properties
+    }
+
+    // First bit of the property in `properties`, None if the block doesn't have it
+    fn shift_of(&self, property: Property) -> Option<u32> {
+        let mut shift = 0;
+
+        for &other in &self.block.definition().properties {
+            if other == property {
+                return Some(shift);
+            }
+
+            shift += other.bits();
+        }
+
+        None
+    }
+
+    // 0 when the block doesn't have the property
+    pub fn get(&self, property: Property) -> u8 {
+        self.shift_of(property).map_or(0, |shift| {
+            (self.properties >> shift) & ((1 << property.bits()) - 1)
+        })
+    }
+
+    // Blocks without the property are left unchanged
+    pub fn with(mut self, property: Property, value: u8) -> BlockState {
+        debug_assert!(value < property.value_count());
+
+        if let Some(shift) = self.shift_of(property) {
+            let mask = ((1 << property.bits()) - 1) << shift;
+            self.properties = (self.properties & !mask) | (value << shift);
+        }
+
+        self
+    }
+
+    pub fn axis(&self) -> Axis {
+        Axis::from_value(self.get(Property::Axis))
+    }
+
+    pub fn facing(&self) -> Facing {
+        Facing::from_value(self.get(Property::Facing))
+    }
+}
+
+impl From<BlockID> for BlockState {
+    fn from(block: BlockID) -> Self {
+        BlockState::new(block)
+    }
+}
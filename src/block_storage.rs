@@ -0,0 +1,375 @@
+use crate::block_state::BlockState;
+use crate::chunk_manager::CHUNK_VOLUME;
+
+/*
+    Blocks of a chunk, stored as indices into a palette of the block states found in the chunk
+
+    Most chunks only hold a handful of different blocks, so each block only takes as many bits as needed
+    to index the palette. The indices are packed in u64 words and never cross a word boundary :
+
+        palette     [air, stone, dirt]          2 bits per block, 32 blocks per word
+        word 0      ... 01 01 10 00             block 0 is air, block 1 is dirt, blocks 2 and 3 are stone
+
+    A chunk made of a single block state (air above the ground, stone deep down) has no indices at all
+    The palette grows when a new block state is placed. Block states that disappear from the chunk leave
+    a hole that the next new state reuses, and the palette is compacted when it gets a lot smaller
+*/
+
+const VOLUME: usize = CHUNK_VOLUME as usize;
+
+// Bits needed to index a palette of `len` entries
+fn bits_for(len: usize) -> u32 {
+    match len {
+        0 | 1 => 0,
+        _ => usize::BITS - (len - 1).leading_zeros(),
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BlockStorage {
+    palette: Vec<BlockState>,
+    // Number of blocks using each entry of the palette, unused entries are at 0
+    counts: Vec<u16>,
+    bits: u32,
+    data: Vec<u64>,
+}
+
+impl BlockStorage {
+    pub fn filled(state: BlockState) -> BlockStorage {
+        BlockStorage {
+            palette: vec![state],
+            counts: vec![VOLUME as u16],
+            bits: 0,
+            data: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, index: usize) -> BlockState {
+        self.palette[self.entry(index)]
+    }
+
+    pub fn set(&mut self, index: usize, state: BlockState) {
+        let old_entry = self.entry(index);
+        if self.palette[old_entry] == state {
+            return;
+        }
+
+        let new_entry = match self.palette.iter().position(|&other| other == state) {
+            Some(entry) => entry,
+            None => self.add_to_palette(state),
+        };
+
+        self.counts[old_entry] -= 1;
+        self.counts[new_entry] += 1;
+        self.set_entry(index, new_entry);
+
+        if self.counts[old_entry] == 0 {
+            let used = self.counts.iter().filter(|&&count| count > 0).count();
+
+            // Going back to a single block state is always worth it, otherwise only compact when it saves
+            // 2 bits per block so that placing and breaking the same block doesn't repack everything each time
+            if used == 1 || bits_for(used) + 1 < self.bits {
+                self.compact();
+            }
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = BlockState> + '_ {
+        (0..VOLUME).map(|index| self.get(index))
+    }
+
+    // Some(state) when every block of the chunk is in this state
+    pub fn single_state(&self) -> Option<BlockState> {
+        (self.bits == 0).then(|| self.palette[0])
+    }
+
+    // Block states in the palette, including the ones that aren't used anymore
+    pub fn palette(&self) -> &[BlockState] {
+        &self.palette
+    }
+
+    // Bytes allocated for the palette and the packed indices
+    pub fn heap_size(&self) -> usize {
+        self.palette.capacity() * std::mem::size_of::<BlockState>()
+            + self.counts.capacity() * std::mem::size_of::<u16>()
+            + self.data.capacity() * std::mem::size_of::<u64>()
+    }
+
+    #[inline]
+    fn entries_per_word(bits: u32) -> usize {
+        (u64::BITS / bits) as usize
+    }
+
+    #[inline]
+    fn entry(&self, index: usize) -> usize {
+        if self.bits == 0 {
+            return 0;
+        }
+
+        let per_word = BlockStorage::entries_per_word(self.bits);
+        let shift = (index % per_word) as u32 * self.bits;
+        ((self.data[index / per_word] >> shift) & ((1 << self.bits) - 1)) as usize
+    }
+
+    #[inline]
+    fn set_entry(&mut self, index: usize, entry: usize) {
+        let per_word = BlockStorage::entries_per_word(self.bits);
+        let shift = (index % per_word) as u32 * self.bits;
+        let word = &mut self.data[index / per_word];
+
+        *word = (*word & !(((1 << self.bits) - 1) << shift)) | ((entry as u64) << shift);
+    }
+
+    // Index of the new entry, the indices get wider when the palette doesn't fit anymore
+    fn add_to_palette(&mut self, state: BlockState) -> usize {
+        if let Some(entry) = self.counts.iter().position(|&count| count == 0) {
+            self.palette[entry] = state;
+            return entry;
+        }
+
+        self.palette.push(state);
+        self.counts.push(0);
+
+        let bits = bits_for(self.palette.len());
+        if bits != self.bits {
+            let entries: Vec<usize> = (0..VOLUME).map(|index| self.entry(index)).collect();
+            self.repack(bits, entries);
+        }
+
+        self.palette.len() - 1
+    }
+
+    // Drops the unused entries and packs the indices with as few bits as possible
+    fn compact(&mut self) {
+        let mut remap = vec![0; self.palette.len()];
+        let mut palette = Vec::new();
+        let mut counts = Vec::new();
+
+        for (entry, (&state, &count)) in self.palette.iter().zip(&self.counts).enumerate() {
+            if count > 0 {
+                remap[entry] = palette.len();
+                palette.push(state);
+                counts.push(count);
+            }
+        }
+
+        let entries: Vec<usize> = (0..VOLUME).map(|index| remap[self.entry(index)]).collect();
+        self.palette = palette;
+        self.counts = counts;
+        self.repack(bits_for(self.palette.len()), entries);
+    }
+
+    fn repack(&mut self, bits: u32, entries: Vec<usize>) {
+        self.bits = bits;
+
+        if bits == 0 {
+            self.data = Vec::new();
+            return;
+        }
+
+        self.data = vec![0; VOLUME.div_ceil(BlockStorage::entries_per_word(bits))];
+        for (index, entry) in entries.into_iter().enumerate() {
+            self.set_entry(index, entry);
+        }
+    }
+}
+
+// Takes exactly one block state per block of the chunk, in the same order as `Chunk::coords_to_index`
+impl FromIterator<BlockState> for BlockStorage {
+    fn from_iter<I: IntoIterator<Item = BlockState>>(states: I) -> Self {
+        let mut palette = Vec::new();
+        let mut counts: Vec<u16> = Vec::new();
+        let mut entries = Vec::with_capacity(VOLUME);
+
+        for state in states {
+            let entry = match palette.iter().position(|&other| other == state) {
+                Some(entry) => entry,
+                None => {
+                    palette.push(state);
+                    counts.push(0);
+                    palette.len() - 1
+                }
+            };
+
+            counts[entry] += 1;
+            entries.push(entry);
+        }
+
+        assert_eq!(entries.len(), VOLUME, "A chunk needs {VOLUME} blocks");
+
+        let mut storage = BlockStorage {
+            palette,
+            counts,
+            bits: 0,
+            data: Vec::new(),
+        };
+        let bits = bits_for(storage.palette.len());
+        storage.repack(bits, entries);
+        storage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_registry::{block_registry, install_test_registry};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    // Every valid state of every block, air first
+    fn all_states() -> Vec<BlockState> {
+        install_test_registry();
+
+        let mut states = vec![BlockState::AIR];
+        for definition in block_registry().blocks() {
+            states.extend((0..=u8::MAX).filter_map(|raw| BlockState::from_raw(definition.id, raw)));
+        }
+        states
+    }
+
+    // Compares the storage with the flat array, and checks the counts of the palette
+    fn assert_matches(storage: &BlockStorage, reference: &[BlockState; VOLUME]) {
+        assert!(storage.iter().eq(reference.iter().copied()));
+
+        let mut counts = vec![0; storage.palette.len()];
+        for index in 0..VOLUME {
+            counts[storage.entry(index)] += 1;
+        }
+        assert_eq!(counts, storage.counts);
+        assert!(storage.palette.len() <= 1 << storage.bits);
+    }
+
+    #[test]
+    fn random_sequences_match_a_flat_array() {
+        let states = all_states();
+        assert!(states.len() > 32, "Not enough block states to need 6 bits");
+
+        for seed in 0..8 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            // Few states at once, then many of them, then few again so the palette shrinks
+            let phases = [4, states.len(), 2];
+
+            let mut storage = BlockStorage::filled(BlockState::AIR);
+            let mut reference = [BlockState::AIR; VOLUME];
+
+            for used_states in phases {
+                for _ in 0..20_000 {
+                    let index = rng.gen_range(0..VOLUME);
+                    let state = states[rng.gen_range(0..used_states)];
+
+                    storage.set(index, state);
+                    reference[index] = state;
+
+                    let probe = rng.gen_range(0..VOLUME);
+                    assert_eq!(storage.get(probe), reference[probe], "seed {seed}");
+                }
+
+                assert_matches(&storage, &reference);
+            }
+        }
+    }
+
+    #[test]
+    fn bits_grow_with_the_palette() {
+        let states = all_states();
+        let mut storage = BlockStorage::filled(BlockState::AIR);
+        let mut reference = [BlockState::AIR; VOLUME];
+
+        for (i, &state) in states.iter().enumerate().skip(1) {
+            storage.set(i, state);
+            reference[i] = state;
+
+            assert_eq!(storage.palette.len(), i + 1);
+            assert_eq!(storage.bits, bits_for(i + 1));
+        }
+
+        assert_eq!(storage.bits, bits_for(states.len()));
+        assert_matches(&storage, &reference);
+    }
+
+    #[test]
+    fn palette_holes_are_reused() {
+        let states = all_states();
+        let mut storage = BlockStorage::filled(BlockState::AIR);
+        let mut reference = [BlockState::AIR; VOLUME];
+
+        for i in 1..4 {
+            storage.set(i, states[i]);
+            reference[i] = states[i];
+        }
+        assert_eq!((storage.palette.len(), storage.bits), (4, 2));
+
+        // Not worth compacting, the entry of the removed state becomes a hole
+        storage.set(3, BlockState::AIR);
+        reference[3] = BlockState::AIR;
+        assert_eq!(storage.counts[3], 0);
+
+        storage.set(10, states[4]);
+        reference[10] = states[4];
+        assert_eq!(storage.palette.len(), 4);
+        assert_eq!(storage.palette[3], states[4]);
+        assert_eq!(storage.bits, 2);
+        assert_matches(&storage, &reference);
+    }
+
+    #[test]
+    fn palette_is_compacted_when_it_gets_smaller() {
+        let states = all_states();
+        let mut storage = BlockStorage::filled(BlockState::AIR);
+        let mut reference = [BlockState::AIR; VOLUME];
+
+        for i in 1..20 {
+            storage.set(i, states[i]);
+            reference[i] = states[i];
+        }
+        assert_eq!(storage.bits, 5);
+
+        // Back to air and a single other state, 1 bit is enough
+        for i in 2..20 {
+            storage.set(i, BlockState::AIR);
+            reference[i] = BlockState::AIR;
+        }
+
+        assert_eq!(storage.palette, vec![BlockState::AIR, states[1]]);
+        assert_eq!(storage.bits, 1);
+        assert_matches(&storage, &reference);
+    }
+
+    #[test]
+    fn filling_with_one_state_goes_back_to_a_single_state() {
+        let states = all_states();
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut storage = BlockStorage::filled(BlockState::AIR);
+
+        for index in 0..VOLUME {
+            storage.set(index, states[rng.gen_range(0..states.len())]);
+        }
+        assert_eq!(storage.single_state(), None);
+
+        let stone = states[1];
+        for index in 0..VOLUME {
+            storage.set(index, stone);
+        }
+
+        assert_eq!(storage.single_state(), Some(stone));
+        assert_eq!(storage.palette, vec![stone]);
+        assert!(storage.data.is_empty());
+        assert_matches(&storage, &[stone; VOLUME]);
+    }
+
+    #[test]
+    fn collected_storage_matches_its_blocks() {
+        let states = all_states();
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut reference = [BlockState::AIR; VOLUME];
+
+        for state in reference.iter_mut() {
+            *state = states[rng.gen_range(0..8)];
+        }
+
+        let storage: BlockStorage = reference.iter().copied().collect();
+        assert_eq!(storage.bits, 3);
+        assert_matches(&storage, &reference);
+    }
+}
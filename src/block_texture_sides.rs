@@ -1,6 +1,8 @@
+use crate::block_state::Axis;
 use crate::types::{UVCoords, UVFaces};
+use serde::Deserialize;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Deserialize)]
 pub enum BlockFaces<T> {
     All(T),
     Sides {
@@ -18,6 +20,50 @@ pub enum BlockFaces<T> {
     },
 }
 
+impl<T> BlockFaces<T> {
+    pub fn map<'a, U>(&'a self, mut f: impl FnMut(&'a T) -> U) -> BlockFaces<U> {
+        match self {
+            BlockFaces::All(all) => BlockFaces::All(f(all)),
+            BlockFaces::Sides { sides, top, bottom } => BlockFaces::Sides {
+                sides: f(sides),
+                top: f(top),
+                bottom: f(bottom),
+            },
+            BlockFaces::Each {
+                top,
+                bottom,
+                front,
+                back,
+                left,
+                right,
+            } => BlockFaces::Each {
+                top: f(top),
+                bottom: f(bottom),
+                front: f(front),
+                back: f(back),
+                left: f(left),
+                right: f(right),
+            },
+        }
+    }
+
+    // Values of the variant, in the order they are declared
+    pub fn textures(&self) -> Vec<&T> {
+        match self {
+            BlockFaces::All(all) => vec![all],
+            BlockFaces::Sides { sides, top, bottom } => vec![sides, top, bottom],
+            BlockFaces::Each {
+                top,
+                bottom,
+                front,
+                back,
+                left,
+                right,
+            } => vec![top, bottom, front, back, left, right],
+        }
+    }
+}
+
 // Unpacks a BlockFaces<UVCoords> instance and returns a tuple of UV coordinates for each face ot the block
 impl BlockFaces<UVCoords> {
     pub fn get_uv_of_every_faces(&self) -> UVFaces {
@@ -34,4 +80,18 @@ impl BlockFaces<UVCoords> {
             } => (front, back, left, right, top, bottom),
         }
     }
+
+    // Texture of each face of a block lying along `axis` and how many quarter turns it is rotated by
+    // Same order as the mesh faces : right, left, top, bottom, front, back
+    pub fn oriented_faces(&self, axis: Axis) -> [(UVCoords, u8); 6] {
+        let (front, back, top, bottom, left, right) = self.get_uv_of_every_faces();
+
+        match axis {
+            Axis::Y => [(right, 0), (left, 0), (top, 0), (bottom, 0), (front, 0), (back, 0)],
+            // The top and bottom textures end up on the faces the block points to,
+            // the sides are turned so that they run along the block
+            Axis::X => [(top, 0), (bottom, 0), (left, 1), (right, 1), (front, 1), (back, 1)],
+            Axis::Z => [(right, 1), (left, 1), (back, 0), (front, 0), (top, 0), (bottom, 0)],
+        }
+    }
 }
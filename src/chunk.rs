@@ -1,9 +1,14 @@
+use crate::block_registry::{block_registry, BlockDefinition, RenderLayer};
+use crate::block_shape::BlockShape;
+use crate::block_state::BlockState;
+use crate::block_storage::BlockStorage;
 use crate::chunk_manager::{CHUNK_SIZE, CHUNK_VOLUME};
+use crate::chunk_visibility::ChunkVisibility;
 use crate::debugging;
-use crate::shapes::write_unit_cube_to_ptr;
+use crate::packed_vertex::VERTEX_SIZE;
 use gl::FRAMEBUFFER_ATTACHMENT_ALPHA_SIZE;
 use rand::distributions::Standard;
-use rand::prelude::Distribution;
+use rand::prelude::{Distribution, IteratorRandom};
 use std::collections::{HashMap, HashSet};
 use std::ffi::c_void;
 
@@ -13,54 +18,17 @@ fn create_vao_vbo() -> (u32, u32) {
     let mut vao = 0;
     gl_call!(gl::CreateVertexArrays(1, &mut vao));
 
-    // pos
+    // Packed vertex, see packed_vertex.rs for the layout
     gl_call!(gl::EnableVertexArrayAttrib(vao, 0));
-    gl_call!(gl::VertexArrayAttribFormat(
+    gl_call!(gl::VertexArrayAttribIFormat(
         vao,
         0,
-        3_i32,
-        gl::FLOAT,
-        gl::FALSE,
+        VERTEX_SIZE as i32,
+        gl::UNSIGNED_INT,
         0
     ));
     gl_call!(gl::VertexArrayAttribBinding(vao, 0, 0));
 
-    // texture
-    gl_call!(gl::EnableVertexArrayAttrib(vao, 1));
-    gl_call!(gl::VertexArrayAttribFormat(
-        vao,
-        1,
-        2_i32,
-        gl::FLOAT,
-        gl::FALSE,
-        (3 * std::mem::size_of::<f32>()) as u32
-    ));
-    gl_call!(gl::VertexArrayAttribBinding(vao, 1, 0));
-
-    // Normals
-    gl_call!(gl::EnableVertexArrayAttrib(vao, 2));
-    gl_call!(gl::VertexArrayAttribFormat(
-        vao,
-        2,
-        3_i32,
-        gl::FLOAT,
-        gl::FALSE,
-        (5 * std::mem::size_of::<f32>()) as u32
-    ));
-    gl_call!(gl::VertexArrayAttribBinding(vao, 2, 0));
-
-    // Ambient occlusion
-    gl_call!(gl::EnableVertexArrayAttrib(vao, 3));
-    gl_call!(gl::VertexArrayAttribFormat(
-        vao,
-        3,
-        1_i32,
-        gl::FLOAT,
-        gl::FALSE,
-        (8 * std::mem::size_of::<f32>()) as u32
-    ));
-    gl_call!(gl::VertexArrayAttribBinding(vao, 3, 0));
-
     let mut vbo = 0;
     gl_call!(gl::CreateBuffers(1, &mut vbo));
 
@@ -69,66 +37,117 @@ fn create_vao_vbo() -> (u32, u32) {
         0,
         vbo,
         0,
-        (9 * std::mem::size_of::<f32>()) as i32
+        (VERTEX_SIZE * std::mem::size_of::<u32>()) as i32
     ));
 
     (vao, vbo)
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash)]
-pub enum BlockID {
-    Air,
-    Dirt,
-    Cobblestone,
-    Obsidian,
-    Grass,
-    OakLog,
-    OakLeaves,
-    OakPlanks,
-    Glass,
-    Debug,
-    Debug2,
-}
+// Lightweight id of a block, its properties are described in the block registry
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct BlockID(u8);
 
 impl BlockID {
+    pub const AIR: BlockID = BlockID(0);
+
+    pub(crate) const fn from_raw(id: u8) -> BlockID {
+        BlockID(id)
+    }
+
+    // The game itself uses `known_blocks`, which can't be missing
+    #[cfg(test)]
+    pub fn named(name: &str) -> BlockID {
+        block_registry()
+            .id_of(name)
+            .unwrap_or_else(|| panic!("The block registry has no block named \"{name}\""))
+    }
+
+    pub fn definition(&self) -> &'static BlockDefinition {
+        block_registry()
+            .get(*self)
+            .expect("BlockID is not in the block registry")
+    }
+
     pub fn is_air(&self) -> bool {
-        self == &BlockID::Air
+        self == &BlockID::AIR
+    }
+
+    pub fn shape(&self) -> BlockShape {
+        self.definition().shape
     }
 
     pub fn is_transparent(&self) -> bool {
-        match self {
-            BlockID::Air | BlockID::OakLeaves | BlockID::Glass => true,
-            _ => false,
-        }
+        self.definition().transparent
     }
 
-    pub fn is_transparent_no_leaves(&self) -> bool {
-        match self {
-            BlockID::Air | BlockID::Glass => true,
-            _ => false,
-        }
+    pub fn render_layer(&self) -> RenderLayer {
+        self.definition().render_layer
+    }
+
+    pub fn casts_ambient_occlusion(&self) -> bool {
+        self.definition().casts_ambient_occlusion
+    }
+
+    pub fn is_solid(&self) -> bool {
+        self.definition().solid
+    }
+
+    // Bedrock can't be broken
+    pub fn is_breakable(&self) -> bool {
+        self.definition().hardness >= 0.0
+    }
+
+    pub fn is_fluid(&self) -> bool {
+        self.definition().fluid.is_some()
+    }
+
+    pub fn light_emission(&self) -> u8 {
+        self.definition().light_emission
+    }
+
+    // Numeric id used when the block is written to disk
+    pub fn to_u8(&self) -> u8 {
+        self.0
+    }
+
+    pub fn from_u8(id: u8) -> Option<BlockID> {
+        block_registry().get(BlockID(id)).map(|definition| definition.id)
     }
 }
 
 impl Distribution<BlockID> for Standard {
     fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> BlockID {
-        match rng.gen_range(1..4) {
-            // 0 => BlockID::AIR,
-            1 => BlockID::Dirt,
-            2 => BlockID::Cobblestone,
-            3 => BlockID::Obsidian,
-            _ => BlockID::Air,
-        }
+        block_registry()
+            .blocks()
+            .filter(|block| block.solid)
+            .choose(rng)
+            .map_or(BlockID::AIR, |block| block.id)
     }
 }
 
+pub type BlockArray = [BlockID; CHUNK_VOLUME as usize];
+
 pub struct Chunk {
-    blocks: [BlockID; CHUNK_VOLUME as usize],
+    blocks: BlockStorage,
+    // Sky light in the high nibble and block light in the low nibble, same order as the blocks
+    light: [u8; CHUNK_VOLUME as usize],
+    // Only created once the chunk has something to draw, 0 until then
     pub vao: u32,
     pub vbo: u32,
-    pub vertices_drawn: u32,
+    // Vertices of the opaque, cutout and translucent passes, stored one after the other in the vbo
+    pub layer_vertices: [u32; 3],
+    // Copy of the translucent vertices, sorted back to front from the camera's block
+    pub translucent_vertices: Vec<u32>,
+    // Block the camera was in when the translucent vertices were sorted, None if they need sorting
+    pub sorted_from: Option<(i32, i32, i32)>,
     pub dirty: bool,
     pub dirty_neighbours: HashSet<(i32, i32, i32)>,
+    // Whether the chunk was changed since it was generated or loaded, only those need to be saved
+    pub modified: bool,
+    // Generation of the last mesh requested for this chunk, older meshes are discarded
+    pub mesh_generation: u64,
+    // Computed with the mesh, see chunk_visibility.rs
+    pub visibility: ChunkVisibility,
 }
 
 impl Chunk {
@@ -146,62 +165,217 @@ impl Chunk {
     }
 
     pub fn empty() -> Chunk {
-        let (vao, vbo) = create_vao_vbo();
-
         Chunk {
-            blocks: [BlockID::Air; CHUNK_VOLUME as usize],
-            vao,
-            vbo,
-            vertices_drawn: 0,
+            blocks: BlockStorage::filled(BlockState::AIR),
+            light: [0; CHUNK_VOLUME as usize],
+            vao: 0,
+            vbo: 0,
+            layer_vertices: [0; 3],
+            translucent_vertices: Vec::new(),
+            sorted_from: None,
             dirty: false,
             dirty_neighbours: Chunk::all_neighbours(),
+            modified: false,
+            mesh_generation: 0,
+            visibility: ChunkVisibility::ALL,
         }
     }
 
     pub fn full_of_block(block: BlockID) -> Chunk {
-        let (vao, vbo) = create_vao_vbo();
-
         Chunk {
-            blocks: [block; CHUNK_VOLUME as usize],
-            vao,
-            vbo,
-            vertices_drawn: 0,
+            blocks: BlockStorage::filled(BlockState::new(block)),
+            light: [0; CHUNK_VOLUME as usize],
+            vao: 0,
+            vbo: 0,
+            layer_vertices: [0; 3],
+            translucent_vertices: Vec::new(),
+            sorted_from: None,
             dirty: false,
             dirty_neighbours: Chunk::all_neighbours(),
+            modified: false,
+            mesh_generation: 0,
+            visibility: ChunkVisibility::ALL,
         }
     }
 
-    pub fn random() -> Chunk {
-        let (vao, vbo) = create_vao_vbo();
-
-        let mut blocks = [BlockID::Air; CHUNK_VOLUME as usize];
-        for i in 0..CHUNK_VOLUME as usize {
-            blocks[i] = rand::random();
+    // Chunk built from blocks generated outside of the main thread
+    pub fn from_blocks(blocks: &BlockArray) -> Chunk {
+        Chunk {
+            blocks: blocks.iter().map(|&block| BlockState::new(block)).collect(),
+            light: [0; CHUNK_VOLUME as usize],
+            vao: 0,
+            vbo: 0,
+            layer_vertices: [0; 3],
+            translucent_vertices: Vec::new(),
+            sorted_from: None,
+            dirty: true,
+            dirty_neighbours: Chunk::all_neighbours(),
+            modified: false,
+            mesh_generation: 0,
+            visibility: ChunkVisibility::ALL,
         }
+    }
 
+    pub fn random() -> Chunk {
         Chunk {
-            blocks,
-            vao,
-            vbo,
-            vertices_drawn: 0,
+            blocks: (0..CHUNK_VOLUME).map(|_| BlockState::new(rand::random())).collect(),
+            light: [0; CHUNK_VOLUME as usize],
+            vao: 0,
+            vbo: 0,
+            layer_vertices: [0; 3],
+            translucent_vertices: Vec::new(),
+            sorted_from: None,
             dirty: true,
             dirty_neighbours: Chunk::all_neighbours(),
+            modified: false,
+            mesh_generation: 0,
+            visibility: ChunkVisibility::ALL,
         }
     }
 
     #[inline]
-    fn coords_to_index(x: u32, y: u32, z: u32) -> usize {
+    pub fn vertices_drawn(&self) -> u32 {
+        self.layer_vertices.iter().sum()
+    }
+
+    // Replaces the vertices in the vbo, the GL objects only exist while there is something to draw
+    pub fn upload_vertices(&mut self, vertices: &[u32]) {
+        if vertices.is_empty() {
+            self.delete_vao_vbo();
+            return;
+        }
+
+        if self.vao == 0 {
+            (self.vao, self.vbo) = create_vao_vbo();
+        }
+
+        gl_call!(gl::NamedBufferData(
+            self.vbo,
+            (vertices.len() * std::mem::size_of::<u32>()) as isize,
+            vertices.as_ptr() as *const c_void,
+            gl::DYNAMIC_DRAW
+        ));
+    }
+
+    fn delete_vao_vbo(&mut self) {
+        if self.vao != 0 {
+            gl_call!(gl::DeleteVertexArrays(1, &self.vao));
+            gl_call!(gl::DeleteBuffers(1, &self.vbo));
+            self.vao = 0;
+            self.vbo = 0;
+        }
+    }
+
+    pub fn blocks(&self) -> &BlockStorage {
+        &self.blocks
+    }
+
+    pub fn coords_to_index(x: u32, y: u32, z: u32) -> usize {
         (y * (CHUNK_SIZE * CHUNK_SIZE) + z * CHUNK_SIZE + x) as usize
     }
 
     #[inline]
     pub fn get_block(&self, x: u32, y: u32, z: u32) -> BlockID {
-        self.blocks[Self::coords_to_index(x, y, z)]
+        self.blocks.get(Self::coords_to_index(x, y, z)).block()
+    }
+
+    #[inline]
+    pub fn get_block_state(&self, x: u32, y: u32, z: u32) -> BlockState {
+        self.blocks.get(Self::coords_to_index(x, y, z))
+    }
+
+    // One byte per block, in the same order as the blocks array
+    // followed by the properties of the block states when some aren't the default ones
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = self.blocks.iter().map(|state| state.block().to_u8()).collect();
+
+        if self.blocks.palette().iter().any(|state| state.properties() != 0) {
+            bytes.extend(self.blocks.iter().map(|state| state.properties()));
+        }
+
+        bytes
+    }
+
+    // Replaces every block of the chunk with the ones stored in `bytes`
+    // The whole chunk and its neighbours are marked dirty so the mesh gets rebuilt
+    pub fn load_bytes(&mut self, bytes: &[u8]) -> Result<(), String> {
+        let volume = CHUNK_VOLUME as usize;
+        if bytes.len() != volume && bytes.len() != 2 * volume {
+            return Err(format!(
+                "Expected {} blocks in chunk data, found {}",
+                CHUNK_VOLUME,
+                bytes.len()
+            ));
+        }
+
+        self.blocks = bytes[..volume]
+            .iter()
+            .enumerate()
+            .map(|(i, &id)| {
+                let block = BlockID::from_u8(id).ok_or(format!("Unknown block id {id}"))?;
+                let properties = bytes.get(volume + i).copied().unwrap_or(0);
+
+                BlockState::from_raw(block, properties).ok_or_else(|| {
+                    format!(
+                        "Invalid properties {properties:#010b} for the block {}",
+                        block.definition().name
+                    )
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        self.dirty = true;
+        self.dirty_neighbours = Chunk::all_neighbours();
+        self.modified = false;
+
+        Ok(())
     }
 
     #[inline]
     pub fn set_block(&mut self, x: u32, y: u32, z: u32, block: BlockID) {
-        self.blocks[Self::coords_to_index(x, y, z)] = block;
+        self.set_block_state(x, y, z, BlockState::new(block));
+    }
+
+    #[inline]
+    pub fn set_block_state(&mut self, x: u32, y: u32, z: u32, state: BlockState) {
+        self.blocks.set(Self::coords_to_index(x, y, z), state);
+        self.modified = true;
+        self.mark_dirty(x, y, z);
+    }
+
+    // Both light levels packed in a byte, see `light`
+    #[inline]
+    pub fn get_light(&self, x: u32, y: u32, z: u32) -> u8 {
+        self.light[Self::coords_to_index(x, y, z)]
+    }
+
+    #[inline]
+    pub fn get_sky_light(&self, x: u32, y: u32, z: u32) -> u8 {
+        self.get_light(x, y, z) >> 4
+    }
+
+    #[inline]
+    pub fn get_block_light(&self, x: u32, y: u32, z: u32) -> u8 {
+        self.get_light(x, y, z) & 0xF
+    }
+
+    // Light is computed when the chunk is loaded so it doesn't make the chunk modified
+    #[inline]
+    pub fn set_sky_light(&mut self, x: u32, y: u32, z: u32, level: u8) {
+        let light = &mut self.light[Self::coords_to_index(x, y, z)];
+        *light = (*light & 0xF) | (level << 4);
+        self.mark_dirty(x, y, z);
+    }
+
+    #[inline]
+    pub fn set_block_light(&mut self, x: u32, y: u32, z: u32, level: u8) {
+        let light = &mut self.light[Self::coords_to_index(x, y, z)];
+        *light = (*light & 0xF0) | level;
+        self.mark_dirty(x, y, z);
+    }
+
+    // The mesh of the chunk needs to be rebuilt, and the one of the neighbours if the block is on the edge
+    fn mark_dirty(&mut self, x: u32, y: u32, z: u32) {
         self.dirty = true;
 
         if x == 0 {
@@ -224,6 +398,12 @@ impl Chunk {
     }
 }
 
+impl Drop for Chunk {
+    fn drop(&mut self) {
+        self.delete_vao_vbo();
+    }
+}
+
 // Iterator that iterates overall possible block coordinates of a chunk on all 3 axis
 // Equivalent in functionality to a triple for loop from 0 to CHUNK_SIZE each
 
@@ -1,29 +1,99 @@
-use crate::ambient_occlusion::compute_ao_of_block;
-use crate::block_texture_sides::BlockFaces;
-use crate::shader::ShaderProgram;
-use crate::types::UVCoords;
-use crate::{
-    chunk::{BlockID, BlockIterator, Chunk},
-    shapes::write_unit_cube_to_ptr,
+use crate::block_state::BlockState;
+use crate::chunk::{BlockID, Chunk};
+use crate::chunk_workers::{ChunkJob, ChunkJobResult, ChunkWorkers};
+use crate::aabb::AABB;
+use crate::constants::{
+    CHUNK_LOADS_PER_FRAME, MESH_UPLOADS_PER_FRAME, RENDER_DISTANCE, UNLOAD_DISTANCE,
+    WORLD_HEIGHT_IN_CHUNKS,
 };
+use crate::chunk_visibility::ChunkVisibility;
+use crate::fluids::FluidTicks;
+use crate::frustum::Frustum;
+use crate::meshing::{sort_translucent_quads, ChunkMesh, ChunkNeighbourhood, MeshingMethod};
+use crate::packed_vertex::VERTEX_SIZE;
+use crate::shader::ShaderProgram;
+use crate::types::UVMap;
+use crate::world_generator::WorldGenerator;
+use crate::world_save::WorldSave;
 use nalgebra::Matrix4;
-use nalgebra_glm::vec3;
-use noise::{NoiseFn, SuperSimplex};
-use rand::random;
+use nalgebra_glm::{vec3, Vec3};
 use std::borrow::Borrow;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ffi::c_void;
+use std::sync::Arc;
 
 pub const CHUNK_SIZE: u32 = 16;
 pub const CHUNK_VOLUME: u32 = CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE;
 
+// Counters of the last frame, to check how many chunks the culling saves
+#[derive(Default, Debug, Copy, Clone)]
+pub struct RenderStats {
+    // Loaded chunks with something to draw
+    pub chunks_considered: usize,
+    // In the frustum but hidden behind solid blocks
+    pub chunks_occluded: usize,
+    pub chunks_drawn: usize,
+}
+
+// Memory taken by the loaded chunks, to see what the palettes save
+#[derive(Default, Debug, Copy, Clone)]
+pub struct MemoryStats {
+    pub chunks: usize,
+    // Chunks made of a single block state, their blocks take no memory besides the palette
+    pub single_state_chunks: usize,
+    // Chunks with a VAO and a VBO, the other ones have nothing to draw
+    pub chunks_with_geometry: usize,
+    // Palettes and packed indices of the blocks
+    pub block_bytes: usize,
+    // What the blocks would take with one block state per block
+    pub flat_block_bytes: usize,
+    // Everything else kept in RAM for the chunks, like the light and the translucent vertices
+    pub other_bytes: usize,
+    pub vertex_bytes: usize,
+}
+
 pub struct ChunkManager {
     pub loaded_chunks: HashMap<(i32, i32, i32), Chunk>,
+    pub generator: Arc<dyn WorldGenerator>,
+    pub meshing_method: MeshingMethod,
+    workers: ChunkWorkers,
+    generating_chunks: HashSet<(i32, i32, i32)>,
+    next_mesh_generation: u64,
+    // Meshes built by the workers, waiting to be uploaded
+    ready_meshes: VecDeque<((i32, i32, i32), u64, ChunkMesh, ChunkVisibility)>,
+    // Chunks within this distance (in chunks) of the player are loaded
+    pub render_distance: i32,
+    // Chunks are only unloaded past this distance so walking back and forth over a border doesn't reload them
+    pub unload_distance: i32,
+    streaming_center: Option<(i32, i32, i32)>,
+    // Chunks waiting to be loaded, the nearest one is at the end
+    pending_chunks: Vec<(i32, i32, i32)>,
+    // Loaded chunks waiting for the chunk above them to be lit, see `light_pending_chunks`
+    pub(crate) unlit_chunks: HashSet<(i32, i32, i32)>,
+    pub render_stats: RenderStats,
+    // Skips the chunks that can't be seen from the camera, see chunk_visibility.rs
+    pub occlusion_culling: bool,
+    pub(crate) fluid_ticks: FluidTicks,
 }
 
 impl ChunkManager {
-    pub fn new() -> ChunkManager {
+    pub fn new(generator: Arc<dyn WorldGenerator>, uv_map: &UVMap) -> ChunkManager {
         ChunkManager {
             loaded_chunks: HashMap::new(),
+            workers: ChunkWorkers::new(Arc::clone(&generator), Arc::new(uv_map.clone())),
+            generator,
+            meshing_method: MeshingMethod::Greedy,
+            generating_chunks: HashSet::new(),
+            next_mesh_generation: 0,
+            ready_meshes: VecDeque::new(),
+            render_distance: RENDER_DISTANCE,
+            unload_distance: UNLOAD_DISTANCE,
+            streaming_center: None,
+            pending_chunks: Vec::new(),
+            unlit_chunks: HashSet::new(),
+            render_stats: RenderStats::default(),
+            occlusion_culling: true,
+            fluid_ticks: FluidTicks::default(),
         }
     }
 
@@ -37,66 +107,143 @@ impl ChunkManager {
         }
     }
 
-    pub fn generate_terrain(&mut self) {
-        let ss = SuperSimplex::new(1296);
-        let n = 10;
+    // Loads the chunks around the player and unloads the ones that are too far away
+    // At most `CHUNK_LOADS_PER_FRAME` chunks are loaded per call, nearest first
+    pub fn update_streaming(&mut self, center: (i32, i32, i32), world_save: &mut WorldSave) {
+        if self.streaming_center != Some(center) {
+            self.streaming_center = Some(center);
+            self.unload_far_chunks(world_save);
+            self.queue_missing_chunks(center);
+        }
+
+        for _ in 0..CHUNK_LOADS_PER_FRAME {
+            match self.pending_chunks.pop() {
+                Some(coords) => self.load_or_generate_chunk(coords, world_save),
+                None => break,
+            }
+        }
+    }
+
+    fn is_within_unload_distance(&self, (x, _, z): (i32, i32, i32)) -> bool {
+        match self.streaming_center {
+            Some((cx, _, cz)) => (x - cx).abs().max((z - cz).abs()) <= self.unload_distance,
+            None => true,
+        }
+    }
+
+    fn queue_missing_chunks(&mut self, center: (i32, i32, i32)) {
+        let (cx, _, cz) = center;
+        let r = self.render_distance;
+
+        self.pending_chunks = (cx - r..=cx + r)
+            .flat_map(|x| (cz - r..=cz + r).map(move |z| (x, z)))
+            .flat_map(|(x, z)| (0..WORLD_HEIGHT_IN_CHUNKS).map(move |y| (x, y, z)))
+            .filter(|coords| {
+                !self.loaded_chunks.contains_key(coords) && !self.generating_chunks.contains(coords)
+            })
+            .collect();
+
+        // Farthest first so that popping from the end gives the nearest chunk
+        // A column is loaded from the top down because chunks are lit in that order
+        let priority = |&(x, y, z): &(i32, i32, i32)| {
+            let (dx, dz) = (x - center.0, z - center.2);
+            (dx * dx + dz * dz, -y)
+        };
+        self.pending_chunks
+            .sort_unstable_by_key(|coords| std::cmp::Reverse(priority(coords)));
+    }
+
+    fn unload_far_chunks(&mut self, world_save: &mut WorldSave) {
+        let far_chunks: Vec<_> = self
+            .loaded_chunks
+            .keys()
+            .filter(|&&coords| !self.is_within_unload_distance(coords))
+            .copied()
+            .collect();
+
+        let mut saved_any = false;
 
-        for y in 0..16 {
-            for z in -n..=n {
-                for x in -n..=n {
-                    self.loaded_chunks.insert((x, y, z), Chunk::empty());
+        for coords in far_chunks {
+            let chunk = &self.loaded_chunks[&coords];
+
+            // A chunk that can't be saved stays loaded so its edits aren't lost, it is tried again
+            // the next time the far chunks are unloaded, or when the world is saved
+            if chunk.modified {
+                if let Err(err) = world_save.store_chunk(coords, &chunk.to_bytes()) {
+                    eprintln!("Failed to save chunk {coords:?}, keeping it loaded: {err}");
+                    continue;
                 }
+                saved_any = true;
             }
+
+            // Dropping the chunk frees its VAO and VBO
+            self.loaded_chunks.remove(&coords);
+            self.unlit_chunks.remove(&coords);
         }
 
-        for x in -16 * n..16 * n {
-            for z in -16 * n..16 * n {
-                let (xf, zf) = (x as f64 / 64.0, z as f64 / 64.0);
-                let y = ss.get([xf, zf]);
-                let y = (16.0 * (y + 1.0)) as i32;
-
-                self.set_block(x, y, z, BlockID::Grass);
-                self.set_block(x, y - 1, z, BlockID::Dirt);
-                self.set_block(x, y - 2, z, BlockID::Dirt);
-                self.set_block(x, y - 3, z, BlockID::Dirt);
-                self.set_block(x, y - 4, z, BlockID::Cobblestone);
-
-                if random::<u32>() % 100 == 0 {
-                    let h = 5;
-                    for i in y + 1..y + 1 + h {
-                        self.set_block(x, i, z, BlockID::OakLog)
-                    }
+        if saved_any {
+            if let Err(err) = world_save.flush() {
+                eprintln!("Failed to save the world: {err}");
+            }
+        }
+    }
 
-                    for yy in y + h - 2..=y + h - 1 {
-                        for xx in x - 2..=x + 2 {
-                            for zz in z - 2..=z + 2 {
-                                if xx != x || zz != z {
-                                    self.set_block(xx, yy, zz, BlockID::OakLeaves);
-                                }
-                            }
-                        }
-                    }
+    // Saved chunks are read right away, new ones are generated by the chunk workers
+    // A chunk that can't be read is generated again, it is only saved over if it gets modified
+    fn load_or_generate_chunk(&mut self, coords: (i32, i32, i32), world_save: &mut WorldSave) {
+        if self.loaded_chunks.contains_key(&coords) || self.generating_chunks.contains(&coords) {
+            return;
+        }
 
-                    for xx in x - 1..=x + 1 {
-                        for zz in z - 1..=z + 1 {
-                            if xx != x || zz != z {
-                                self.set_block(xx, y + h, zz, BlockID::OakLeaves);
-                            }
-                        }
+        match world_save.load_chunk(coords) {
+            Ok(Some(data)) => {
+                let mut chunk = Chunk::empty();
+                match chunk.load_bytes(&data) {
+                    Ok(()) => {
+                        self.loaded_chunks.insert(coords, chunk);
+                        self.unlit_chunks.insert(coords);
+                        return;
                     }
-
-                    self.set_block(x, y + h + 1, z, BlockID::OakLeaves);
-                    self.set_block(x + 1, y + h + 1, z, BlockID::OakLeaves);
-                    self.set_block(x - 1, y + h + 1, z, BlockID::OakLeaves);
-                    self.set_block(x, y + h + 1, z + 1, BlockID::OakLeaves);
-                    self.set_block(x, y + h + 1, z - 1, BlockID::OakLeaves);
+                    Err(err) => eprintln!("Corrupted chunk {coords:?}, generating it again: {err}"),
                 }
             }
+            Ok(None) => {}
+            Err(err) => eprintln!("Failed to load chunk {coords:?}, generating it again: {err}"),
+        }
+
+        self.generating_chunks.insert(coords);
+        self.workers.submit(ChunkJob::Generate { coords });
+    }
+
+    pub fn memory_stats(&self) -> MemoryStats {
+        let mut stats = MemoryStats {
+            chunks: self.loaded_chunks.len(),
+            ..MemoryStats::default()
+        };
+
+        for chunk in self.loaded_chunks.values() {
+            let blocks = chunk.blocks();
+
+            if blocks.single_state().is_some() {
+                stats.single_state_chunks += 1;
+            }
+            if chunk.vao != 0 {
+                stats.chunks_with_geometry += 1;
+            }
+
+            stats.block_bytes += blocks.heap_size();
+            stats.flat_block_bytes += CHUNK_VOLUME as usize * std::mem::size_of::<BlockState>();
+            stats.other_bytes += std::mem::size_of::<Chunk>()
+                + chunk.translucent_vertices.capacity() * std::mem::size_of::<u32>();
+            stats.vertex_bytes +=
+                chunk.vertices_drawn() as usize * VERTEX_SIZE * std::mem::size_of::<u32>();
         }
+
+        stats
     }
 
     // Transform global coordinates into chunk local coordinates
-    fn get_chunk_coords(x: i32, y: i32, z: i32) -> (i32, i32, i32, u32, u32, u32) {
+    pub fn get_chunk_coords(x: i32, y: i32, z: i32) -> (i32, i32, i32, u32, u32, u32) {
         let chunk_x = if x < 0 { (x + 1) / 16 - 1 } else { x / 16 };
         let chunk_y = if y < 0 { (y + 1) / 16 - 1 } else { y / 16 };
         let chunk_z = if z < 0 { (z + 1) / 16 - 1 } else { z / 16 };
@@ -108,17 +255,6 @@ impl ChunkManager {
         (chunk_x, chunk_y, chunk_z, block_x, block_y, block_z)
     }
 
-    // Transform chunk local coordinates into global coordinates
-    fn get_global_coords(
-        (chunk_x, chunk_y, chunk_z, block_x, block_y, block_z): (i32, i32, i32, u32, u32, u32),
-    ) -> (i32, i32, i32) {
-        let x = 16 * chunk_x + block_x as i32;
-        let y = 16 * chunk_y + block_y as i32;
-        let z = 16 * chunk_z + block_z as i32;
-
-        (x, y, z)
-    }
-
     pub fn get_block(&self, x: i32, y: i32, z: i32) -> Option<BlockID> {
         let (chunk_x, chunk_y, chunk_z, block_x, block_y, block_z) =
             ChunkManager::get_chunk_coords(x, y, z);
@@ -128,27 +264,56 @@ impl ChunkManager {
             .and_then(|chunk| Some(chunk.get_block(block_x, block_y, block_z)))
     }
 
-    pub fn set_block(&mut self, x: i32, y: i32, z: i32, block: BlockID) {
+    pub fn get_block_state(&self, x: i32, y: i32, z: i32) -> Option<BlockState> {
         let (chunk_x, chunk_y, chunk_z, block_x, block_y, block_z) =
             ChunkManager::get_chunk_coords(x, y, z);
 
         self.loaded_chunks
-            .get_mut((chunk_x, chunk_y, chunk_z).borrow())
-            .map(|chunk| chunk.set_block(block_x, block_y, block_z, block));
+            .get(&(chunk_x, chunk_y, chunk_z))
+            .map(|chunk| chunk.get_block_state(block_x, block_y, block_z))
+    }
+
+    // Places the block in its default state
+    pub fn set_block(&mut self, x: i32, y: i32, z: i32, block: BlockID) {
+        self.set_block_state(x, y, z, BlockState::new(block));
+    }
+
+    // The fluids around the block start flowing, see fluids.rs
+    pub fn set_block_state(&mut self, x: i32, y: i32, z: i32, state: BlockState) {
+        let (chunk_x, chunk_y, chunk_z, block_x, block_y, block_z) =
+            ChunkManager::get_chunk_coords(x, y, z);
+
+        let chunk = match self.loaded_chunks.get_mut(&(chunk_x, chunk_y, chunk_z)) {
+            Some(chunk) => chunk,
+            None => return,
+        };
+
+        let previous = chunk.get_block(block_x, block_y, block_z);
+        chunk.set_block_state(block_x, block_y, block_z, state);
+
+        if previous != state.block() {
+            self.update_light_at((x, y, z), state.block());
+        }
+
+        self.schedule_fluid_updates((x, y, z));
     }
 
     pub fn is_solid_block_at(&self, x: i32, y: i32, z: i32) -> bool {
         self.get_block(x, y, z)
-            .filter(|&block| block != BlockID::Air)
+            .filter(|block| block.is_solid())
             .is_some()
     }
 
-    // UV coordinates are composed of 4, the first 2, and the last 2 are the UV coordinates of the front face
-    pub fn rebuild_dirty_chunks(&mut self, uv_map: &HashMap<BlockID, BlockFaces<UVCoords>>) {
+    // Collects the work done by the chunk workers, sends the dirty chunks to be meshed
+    // and uploads at most `MESH_UPLOADS_PER_FRAME` finished meshes to the GPU
+    pub fn rebuild_dirty_chunks(&mut self) {
+        self.collect_finished_jobs();
+        self.light_pending_chunks();
+
         let mut dirty_chunks = HashSet::new();
 
         // Nearby chunks can be also dirty if the change happens at the edge
-        for (&(x, y, z), chunk) in self.loaded_chunks.iter() {
+        for (&(x, y, z), chunk) in self.loaded_chunks.iter_mut() {
             if chunk.dirty {
                 dirty_chunks.insert((x, y, z));
             }
@@ -156,163 +321,385 @@ impl ChunkManager {
             for &(dx, dy, dz) in chunk.dirty_neighbours.iter() {
                 dirty_chunks.insert((x + dx, y + dy, z + dz));
             }
+            chunk.dirty_neighbours.clear();
         }
 
-        /*
-           Optimization :
-               If 2 solid blocks are touching, don't render the faces where they touch.
-               Render only the faces that are next to a transparent block (AIR for example)
-        */
-        type ChunkCoords = (i32, i32, i32);
-        pub type Sides = [bool; 6];
-        type CubeAO = [[u8; 4]; 6];
-        let mut active_sides: HashMap<ChunkCoords, Vec<Sides>> = HashMap::new();
-        let mut ao_chunk: HashMap<ChunkCoords, Vec<CubeAO>> = HashMap::new();
-
-        for &coords in dirty_chunks.iter() {
-            let (cx, cy, cz) = coords;
-            let chunk = self.loaded_chunks.get(&coords);
-
-            if let Some(chunk) = chunk {
-                let sides_vec = active_sides.entry(coords).or_default();
-                let ao_vec = ao_chunk.entry(coords).or_default();
-
-                for (bx, by, bz) in BlockIterator::new() {
-                    let block = chunk.get_block(bx, by, bz);
-                    if !block.is_air() {
-                        let (gx, gy, gz) =
-                            ChunkManager::get_global_coords((cx, cy, cz, bx, by, bz));
-                        let acitve_sides_of_block = self.get_active_sides_of_block(gx, gy, gz);
-                        sides_vec.push(acitve_sides_of_block);
-
-                        let does_occlude = |x: i32, y: i32, z: i32| {
-                            self.get_block(x, y, z)
-                                .filter(|&b| !b.is_transparent_no_leaves())
-                                .is_some()
-                        };
-
-                        ao_vec.push(compute_ao_of_block(&does_occlude));
-                    }
-                }
+        for coords in dirty_chunks {
+            // We check for a valid chunk because the chunk might have been removed
+            // Chunks that are not lit yet would be drawn in the dark, they stay dirty until they are
+            if !self.loaded_chunks.contains_key(&coords) || self.unlit_chunks.contains(&coords) {
+                continue;
             }
+
+            let neighbourhood = Box::new(self.get_neighbourhood(coords));
+            self.next_mesh_generation += 1;
+
+            let chunk = self.loaded_chunks.get_mut(&coords).unwrap();
+            chunk.dirty = false;
+            chunk.mesh_generation = self.next_mesh_generation;
+
+            self.workers.submit(ChunkJob::Mesh {
+                coords,
+                generation: self.next_mesh_generation,
+                neighbourhood,
+                method: self.meshing_method,
+            });
         }
 
-        // Update the VBOs of the dirty chunks
-        for coords in dirty_chunks.iter() {
-            let chunk: Option<&mut Chunk> = self.loaded_chunks.get_mut(coords);
+        self.upload_ready_meshes();
+    }
+
+    // Switches the meshing method and rebuilds every chunk with it
+    pub fn set_meshing_method(&mut self, method: MeshingMethod) {
+        self.meshing_method = method;
 
-            // We check for a valid chunk because the chunk might have been removed
-            if let Some(chunk) = chunk {
-                chunk.dirty = false;
-                chunk.dirty_neighbours.clear();
-                chunk.vertices_drawn = 0;
-
-                let sides = active_sides.get(coords).unwrap();
-                let n_visible_faces = sides
-                    .iter()
-                    .map(|faces| faces.iter().fold(0, |acc, &x| acc + x as u32))
-                    .fold(0, |acc, n| acc + n);
-
-                if n_visible_faces == 0 {
-                    continue;
-                }
+        for chunk in self.loaded_chunks.values_mut() {
+            chunk.dirty = true;
+        }
+    }
 
-                // Initialize the VAO and VBO
-                // NOTE: 324 = 6 * 6 * 9 (6 faces, 6 vertices per face, 9 floats per vertex)
-                gl_call!(gl::NamedBufferData(
-                    chunk.vbo,
-                    (324 * std::mem::size_of::<f32>() * n_visible_faces as usize) as isize,
-                    std::ptr::null(),
-                    gl::DYNAMIC_DRAW
-                ));
+    fn collect_finished_jobs(&mut self) {
+        let results: Vec<_> = self.workers.finished_jobs().collect();
+
+        for result in results {
+            match result {
+                ChunkJobResult::Generated { coords, blocks } => {
+                    self.generating_chunks.remove(&coords);
 
-                let vbo_ptr = gl_call!(gl::MapNamedBuffer(chunk.vbo, gl::WRITE_ONLY)) as *mut f32;
-                let mut idx = 0;
-
-                let sides_vec = active_sides.get(coords).unwrap();
-                let ao_vec = ao_chunk.get(coords).unwrap();
-                let mut cnt = 0;
-
-                for (x, y, z) in BlockIterator::new() {
-                    let block = chunk.get_block(x, y, z);
-
-                    if block != BlockID::Air {
-                        let active_sides = sides_vec[cnt];
-                        let ao_block = ao_vec[cnt];
-                        let uvs = uv_map.get(&block).unwrap().clone();
-                        let uvs = uvs.get_uv_of_every_faces();
-
-                        let copied_vertices = unsafe {
-                            write_unit_cube_to_ptr(
-                                vbo_ptr.offset(idx),
-                                (x as f32, y as f32, z as f32),
-                                uvs,
-                                active_sides,
-                                ao_block,
-                            )
-                        };
-
-                        chunk.vertices_drawn += copied_vertices;
-                        idx += copied_vertices as isize * 9;
-                        cnt += 1;
+                    // The player might have walked away while the chunk was being generated
+                    if self.is_within_unload_distance(coords)
+                        && !self.loaded_chunks.contains_key(&coords)
+                    {
+                        self.loaded_chunks.insert(coords, Chunk::from_blocks(&blocks));
+                        self.unlit_chunks.insert(coords);
+                    }
+                }
+                ChunkJobResult::Meshed {
+                    coords,
+                    generation,
+                    mesh,
+                    visibility,
+                } => {
+                    let is_latest = self
+                        .loaded_chunks
+                        .get(&coords)
+                        .map_or(false, |chunk| chunk.mesh_generation == generation);
+
+                    if is_latest {
+                        self.ready_meshes
+                            .push_back((coords, generation, mesh, visibility));
                     }
                 }
-                gl_call!(gl::UnmapNamedBuffer(chunk.vbo));
             }
         }
     }
 
-    pub fn get_active_sides_of_block(&self, x: i32, y: i32, z: i32) -> [bool; 6] {
-        let right = self
-            .get_block(x + 1, y, z)
-            .filter(|&b| !b.is_transparent())
-            .is_none();
-        let left = self
-            .get_block(x - 1, y, z)
-            .filter(|&b| !b.is_transparent())
-            .is_none();
-        let top = self
-            .get_block(x, y + 1, z)
-            .filter(|&b| !b.is_transparent())
-            .is_none();
-        let bottom = self
-            .get_block(x, y - 1, z)
-            .filter(|&b| !b.is_transparent())
-            .is_none();
-        let front = self
-            .get_block(x, y, z + 1)
-            .filter(|&b| !b.is_transparent())
-            .is_none();
-        let back = self
-            .get_block(x, y, z - 1)
-            .filter(|&b| !b.is_transparent())
-            .is_none();
-
-        [right, left, top, bottom, front, back]
+    fn upload_ready_meshes(&mut self) {
+        let mut uploads = 0;
+
+        while uploads < MESH_UPLOADS_PER_FRAME {
+            let (coords, generation, mesh, visibility) = match self.ready_meshes.pop_front() {
+                Some(mesh) => mesh,
+                None => break,
+            };
+
+            let chunk = match self.loaded_chunks.get_mut(&coords) {
+                // A newer mesh is on its way, don't waste the upload
+                Some(chunk) if chunk.mesh_generation == generation => chunk,
+                _ => continue,
+            };
+
+            chunk.layer_vertices = [&mesh.opaque, &mesh.cutout, &mesh.translucent]
+                .map(|vertices| (vertices.len() / VERTEX_SIZE) as u32);
+            chunk.visibility = visibility;
+
+            let vertices = [&mesh.opaque[..], &mesh.cutout, &mesh.translucent].concat();
+            chunk.translucent_vertices = mesh.translucent;
+            chunk.sorted_from = None;
+
+            chunk.upload_vertices(&vertices);
+
+            uploads += 1;
+        }
+    }
+
+    // Copies the blocks of the chunk and of the borders of its neighbours
+    fn get_neighbourhood(&self, (cx, cy, cz): (i32, i32, i32)) -> ChunkNeighbourhood {
+        let mut chunks = [None; 27];
+
+        for dy in -1..=1 {
+            for dz in -1..=1 {
+                for dx in -1..=1 {
+                    chunks[ChunkNeighbourhood::neighbour_index(dx, dy, dz)] =
+                        self.loaded_chunks.get(&(cx + dx, cy + dy, cz + dz));
+                }
+            }
+        }
+
+        ChunkNeighbourhood::new(&chunks)
     }
 
-    pub fn render_loaded_chunks(&mut self, program: &mut ShaderProgram) {
-        for ((x, y, z), chunk) in &self.loaded_chunks {
+    /*
+        Chunks are drawn in 3 passes :
+            1. Opaque blocks
+            2. Cutout blocks (leaves), their transparent pixels are discarded
+            3. Translucent blocks (glass, water), blended from the farthest chunk to the nearest without writing
+               the depth so they don't hide each other. Their faces are also sorted inside of each chunk
+    */
+    pub fn render_loaded_chunks(
+        &mut self,
+        program: &mut ShaderProgram,
+        frustum: &Frustum,
+        camera_position: &Vec3,
+    ) {
+        let camera_block = (
+            camera_position.x.floor() as i32,
+            camera_position.y.floor() as i32,
+            camera_position.z.floor() as i32,
+        );
+        let (cx, cy, cz, _, _, _) =
+            ChunkManager::get_chunk_coords(camera_block.0, camera_block.1, camera_block.2);
+
+        let mut stats = RenderStats::default();
+        let visible_chunks = if self.occlusion_culling {
+            self.find_visible_chunks((cx, cy, cz), frustum)
+        } else {
+            None
+        };
+
+        let mut drawn_chunks = Vec::new();
+
+        for (&(x, y, z), chunk) in &self.loaded_chunks {
             // skip rendering the chunk if there is nothing to draw
-            if chunk.vertices_drawn == 0 {
+            if chunk.vertices_drawn() == 0 {
                 continue;
             }
-            let model_matrix = {
-                let translate_matrix =
-                    Matrix4::new_translation(&vec3(*x as f32, *y as f32, *z as f32).scale(16.0));
-                let rotate_matrix = Matrix4::from_euler_angles(0.0f32, 0.0, 0.0);
-                let scale_matrix = Matrix4::new_nonuniform_scaling(&vec3(1.0f32, 1.0f32, 1.0f32));
 
-                translate_matrix * rotate_matrix * scale_matrix
+            stats.chunks_considered += 1;
+
+            let mins = vec3(x as f32, y as f32, z as f32).scale(CHUNK_SIZE as f32);
+            let aabb = AABB::new(mins, mins.add_scalar(CHUNK_SIZE as f32));
+            if !frustum.intersects_aabb(&aabb) {
+                continue;
+            }
+
+            if let Some(visible_chunks) = &visible_chunks {
+                if !visible_chunks.contains(&(x, y, z)) {
+                    stats.chunks_occluded += 1;
+                    continue;
+                }
+            }
+
+            stats.chunks_drawn += 1;
+            drawn_chunks.push((x, y, z));
+        }
+
+        let origin_of = |(x, y, z): (i32, i32, i32)| {
+            vec3(x as f32, y as f32, z as f32).scale(CHUNK_SIZE as f32)
+        };
+
+        let draw_layer =
+            |program: &mut ShaderProgram, coords: (i32, i32, i32), chunk: &Chunk, layer: usize| {
+                let first: u32 = chunk.layer_vertices[..layer].iter().sum();
+                let count = chunk.layer_vertices[layer];
+                if count == 0 {
+                    return;
+                }
+
+                let model_matrix = Matrix4::new_translation(&origin_of(coords));
+
+                gl_call!(gl::BindVertexArray(chunk.vao));
+                program.set_uniform_matrix4fv("model", model_matrix.as_ptr());
+                gl_call!(gl::DrawArrays(gl::TRIANGLES, first as i32, count as i32));
             };
 
-            gl_call!(gl::BindVertexArray(chunk.vao));
-            program.set_uniform_matrix4fv("model", model_matrix.as_ptr());
-            gl_call!(gl::DrawArrays(
-                gl::TRIANGLES,
-                0,
-                chunk.vertices_drawn as i32
-            ));
+        // Opaque and cutout passes, the depth test takes care of the order
+        gl_call!(gl::Disable(gl::BLEND));
+        for (layer, alpha_cutoff) in [(0, 0.0), (1, 0.5)] {
+            program.set_uniform1f("alpha_cutoff", alpha_cutoff);
+
+            for &coords in &drawn_chunks {
+                draw_layer(program, coords, &self.loaded_chunks[&coords], layer);
+            }
         }
+
+        // Translucent pass, back to front
+        drawn_chunks.retain(|coords| self.loaded_chunks[coords].layer_vertices[2] > 0);
+        drawn_chunks.sort_by(|&a, &b| {
+            let distance = |coords| {
+                nalgebra_glm::distance2(
+                    &origin_of(coords).add_scalar(CHUNK_SIZE as f32 / 2.0),
+                    camera_position,
+                )
+            };
+            distance(b).total_cmp(&distance(a))
+        });
+
+        gl_call!(gl::Enable(gl::BLEND));
+        gl_call!(gl::DepthMask(gl::FALSE));
+        program.set_uniform1f("alpha_cutoff", 0.0);
+
+        for coords in drawn_chunks {
+            let chunk = self.loaded_chunks.get_mut(&coords).unwrap();
+
+            // The order only changes when the camera goes into another block
+            if chunk.sorted_from != Some(camera_block) {
+                sort_translucent_quads(
+                    &mut chunk.translucent_vertices,
+                    camera_position - origin_of(coords),
+                );
+                chunk.sorted_from = Some(camera_block);
+
+                let offset =
+                    (chunk.layer_vertices[0] + chunk.layer_vertices[1]) as usize * VERTEX_SIZE;
+                gl_call!(gl::NamedBufferSubData(
+                    chunk.vbo,
+                    (offset * std::mem::size_of::<u32>()) as isize,
+                    (chunk.translucent_vertices.len() * std::mem::size_of::<u32>()) as isize,
+                    chunk.translucent_vertices.as_ptr() as *const c_void
+                ));
+            }
+
+            draw_layer(program, coords, chunk, 2);
+        }
+
+        gl_call!(gl::DepthMask(gl::TRUE));
+
+        self.render_stats = stats;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_registry::install_test_registry;
+    use crate::world_generator::TerrainGenerator;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::thread;
+    use std::time::Duration;
+
+    const COORDS: (i32, i32, i32) = (0, 0, 0);
+
+    fn temp_directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir()
+            .join(format!("minecraft-chunk-manager-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        directory
+    }
+
+    fn new_chunk_manager() -> ChunkManager {
+        install_test_registry();
+        ChunkManager::new(Arc::new(TerrainGenerator::new(1)), &UVMap::default())
+    }
+
+    // Loads the chunk and waits for the workers to generate it again
+    fn regenerate(chunk_manager: &mut ChunkManager, world_save: &mut WorldSave) {
+        chunk_manager.load_or_generate_chunk(COORDS, world_save);
+        assert!(chunk_manager.generating_chunks.contains(&COORDS));
+
+        for _ in 0..1000 {
+            chunk_manager.collect_finished_jobs();
+            if chunk_manager.loaded_chunks.contains_key(&COORDS) {
+                return;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        panic!("The chunk was never generated");
+    }
+
+    #[test]
+    fn corrupted_chunks_are_generated_again() {
+        let directory = temp_directory("corrupted");
+        let mut world_save = WorldSave::new(&directory);
+        world_save.store_chunk(COORDS, b"not a chunk").unwrap();
+        world_save.flush().unwrap();
+
+        let mut world_save = WorldSave::new(&directory);
+        let mut chunk_manager = new_chunk_manager();
+        regenerate(&mut chunk_manager, &mut world_save);
+        let generated = chunk_manager.generator.generate_chunk(COORDS);
+        assert_eq!(
+            chunk_manager.loaded_chunks[&COORDS].to_bytes(),
+            Chunk::from_blocks(&generated).to_bytes()
+        );
+
+        // The saved data is left alone until the chunk is modified
+        chunk_manager.save_world(&mut world_save).unwrap();
+        world_save.flush().unwrap();
+        let data = WorldSave::new(&directory).load_chunk(COORDS).unwrap();
+        assert_eq!(data.as_deref(), Some(&b"not a chunk"[..]));
+
+        chunk_manager.set_block(0, 0, 0, BlockID::named("glass"));
+        chunk_manager.save_world(&mut world_save).unwrap();
+        world_save.flush().unwrap();
+        let data = WorldSave::new(&directory).load_chunk(COORDS).unwrap().unwrap();
+        let mut chunk = Chunk::empty();
+        chunk.load_bytes(&data).unwrap();
+        assert_eq!(chunk.get_block(0, 0, 0), BlockID::named("glass"));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn chunks_of_unreadable_regions_are_generated_again() {
+        let directory = temp_directory("unreadable");
+        let regions = directory.join("regions");
+        fs::create_dir_all(&regions).unwrap();
+        fs::write(regions.join("r.0.0.0.region"), b"not a region").unwrap();
+
+        let mut world_save = WorldSave::new(&directory);
+        let mut chunk_manager = new_chunk_manager();
+        regenerate(&mut chunk_manager, &mut world_save);
+
+        // The unreadable file is kept aside, nothing is written until a chunk is modified
+        chunk_manager.save_world(&mut world_save).unwrap();
+        assert_eq!(fs::read(regions.join("r.0.0.0.corrupt")).unwrap(), b"not a region");
+        assert!(!regions.join("r.0.0.0.region").exists());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn edits_in_unreadable_regions_are_saved() {
+        let directory = temp_directory("unreadable-edits");
+        fs::create_dir_all(directory.join("regions")).unwrap();
+        fs::write(directory.join("regions").join("r.0.0.0.region"), b"not a region").unwrap();
+
+        let mut world_save = WorldSave::new(&directory);
+        let mut chunk_manager = new_chunk_manager();
+        regenerate(&mut chunk_manager, &mut world_save);
+        chunk_manager.set_block(0, 0, 0, BlockID::named("glass"));
+
+        // The player walked far away
+        chunk_manager.streaming_center = Some((100, 0, 100));
+        chunk_manager.unload_far_chunks(&mut world_save);
+        assert!(!chunk_manager.loaded_chunks.contains_key(&COORDS));
+
+        let mut world_save = WorldSave::new(&directory);
+        let mut reloaded = new_chunk_manager();
+        reloaded.load_or_generate_chunk(COORDS, &mut world_save);
+        assert_eq!(reloaded.get_block(0, 0, 0), Some(BlockID::named("glass")));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn chunks_that_cant_be_saved_stay_loaded() {
+        // The regions can be neither read nor moved aside
+        let directory = temp_directory("unsaved");
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("regions"), b"not a directory").unwrap();
+
+        let mut world_save = WorldSave::new(&directory);
+        let mut chunk_manager = new_chunk_manager();
+        regenerate(&mut chunk_manager, &mut world_save);
+        chunk_manager.set_block(0, 0, 0, BlockID::named("glass"));
+
+        chunk_manager.streaming_center = Some((100, 0, 100));
+        chunk_manager.unload_far_chunks(&mut world_save);
+        assert_eq!(chunk_manager.get_block(0, 0, 0), Some(BlockID::named("glass")));
+
+        fs::remove_dir_all(&directory).unwrap();
     }
 }
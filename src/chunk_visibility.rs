@@ -0,0 +1,162 @@
+use crate::aabb::AABB;
+use crate::chunk_manager::{ChunkManager, CHUNK_SIZE, CHUNK_VOLUME};
+use crate::frustum::Frustum;
+use nalgebra_glm::vec3;
+use std::collections::{HashSet, VecDeque};
+
+/*
+    Cave culling (Tommaso Checchi's "advanced cave culling")
+
+    When a chunk is meshed, its transparent blocks are flood filled to find which of its faces can see
+    each other through the chunk. Rendering then walks the chunks from the one holding the camera,
+    only going from a chunk to its neighbour when the face it entered from is connected to the face
+    towards the neighbour. Chunks that can't be reached this way are hidden behind solid blocks
+
+    The walk never goes back towards the camera (a chunk reached by going +x never continues to -x),
+    otherwise it could go around solid walls and reach almost everything
+*/
+
+type ChunkCoords = (i32, i32, i32);
+
+// Same order as the mesh faces : right, left, top, bottom, front, back
+pub const FACE_DIRECTIONS: [ChunkCoords; 6] = [
+    (1, 0, 0),
+    (-1, 0, 0),
+    (0, 1, 0),
+    (0, -1, 0),
+    (0, 0, 1),
+    (0, 0, -1),
+];
+
+// Faces come in pairs, the opposite face only differs by the lowest bit
+fn opposite_face(face: usize) -> usize {
+    face ^ 1
+}
+
+// Which faces of a chunk can be seen from which other faces
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ChunkVisibility {
+    // Bit `from * 6 + to` is set when `to` can be seen from `from`, always symmetric
+    connections: u64,
+}
+
+impl ChunkVisibility {
+    // Chunks that weren't meshed yet don't hide anything
+    pub const ALL: ChunkVisibility = ChunkVisibility {
+        connections: (1 << 36) - 1,
+    };
+
+    pub const NONE: ChunkVisibility = ChunkVisibility { connections: 0 };
+
+    pub fn can_see_through(&self, from: usize, to: usize) -> bool {
+        self.connections & (1 << (from * 6 + to)) != 0
+    }
+
+    // `is_transparent` takes coordinates local to the chunk
+    pub fn compute(is_transparent: impl Fn(u32, u32, u32) -> bool) -> ChunkVisibility {
+        let size = CHUNK_SIZE as i32;
+        let index = |x: i32, y: i32, z: i32| (y * size * size + z * size + x) as usize;
+
+        let mut visited = vec![false; CHUNK_VOLUME as usize];
+        let mut queue = VecDeque::new();
+        let mut visibility = ChunkVisibility::NONE;
+
+        for (x, y, z) in
+            (0..size).flat_map(|y| (0..size).flat_map(move |z| (0..size).map(move |x| (x, y, z))))
+        {
+            if visited[index(x, y, z)] || !is_transparent(x as u32, y as u32, z as u32) {
+                continue;
+            }
+
+            // Faces of the chunk touched by this pocket of transparent blocks
+            let mut faces = 0u8;
+            visited[index(x, y, z)] = true;
+            queue.push_back((x, y, z));
+
+            while let Some((x, y, z)) = queue.pop_front() {
+                for (face, &(dx, dy, dz)) in FACE_DIRECTIONS.iter().enumerate() {
+                    let (nx, ny, nz) = (x + dx, y + dy, z + dz);
+
+                    if !(0..size).contains(&nx)
+                        || !(0..size).contains(&ny)
+                        || !(0..size).contains(&nz)
+                    {
+                        faces |= 1 << face;
+                        continue;
+                    }
+
+                    let i = index(nx, ny, nz);
+                    if !visited[i] && is_transparent(nx as u32, ny as u32, nz as u32) {
+                        visited[i] = true;
+                        queue.push_back((nx, ny, nz));
+                    }
+                }
+            }
+
+            for from in 0..6 {
+                for to in 0..6 {
+                    if faces & (1 << from) != 0 && faces & (1 << to) != 0 {
+                        visibility.connections |= 1 << (from * 6 + to);
+                    }
+                }
+            }
+
+            if visibility == ChunkVisibility::ALL {
+                break;
+            }
+        }
+
+        visibility
+    }
+}
+
+impl ChunkManager {
+    // Chunks that can be seen from the camera, None when the camera isn't in a loaded chunk
+    pub(crate) fn find_visible_chunks(
+        &self,
+        camera_chunk: ChunkCoords,
+        frustum: &Frustum,
+    ) -> Option<HashSet<ChunkCoords>> {
+        if !self.loaded_chunks.contains_key(&camera_chunk) {
+            return None;
+        }
+
+        let in_frustum = |(x, y, z): ChunkCoords| {
+            let mins = vec3(x as f32, y as f32, z as f32).scale(CHUNK_SIZE as f32);
+            frustum.intersects_aabb(&AABB::new(mins, mins.add_scalar(CHUNK_SIZE as f32)))
+        };
+
+        let mut visible = HashSet::from([camera_chunk]);
+        // Chunk, face it was entered from and the directions taken to reach it
+        let mut queue = VecDeque::from([(camera_chunk, None, 0u8)]);
+
+        while let Some((coords, entered_from, directions)) = queue.pop_front() {
+            let visibility = self.loaded_chunks[&coords].visibility;
+
+            for (face, &(dx, dy, dz)) in FACE_DIRECTIONS.iter().enumerate() {
+                if directions & (1 << opposite_face(face)) != 0 {
+                    continue;
+                }
+
+                if let Some(from) = entered_from {
+                    if !visibility.can_see_through(from, face) {
+                        continue;
+                    }
+                }
+
+                let neighbour = (coords.0 + dx, coords.1 + dy, coords.2 + dz);
+                if visible.contains(&neighbour)
+                    || !self.loaded_chunks.contains_key(&neighbour)
+                    || !in_frustum(neighbour)
+                {
+                    continue;
+                }
+
+                visible.insert(neighbour);
+                queue.push_back((neighbour, Some(opposite_face(face)), directions | 1 << face));
+            }
+        }
+
+        Some(visible)
+    }
+}
@@ -0,0 +1,142 @@
+use crate::chunk::BlockArray;
+use crate::chunk_visibility::ChunkVisibility;
+use crate::meshing::{build_chunk_mesh, ChunkMesh, ChunkNeighbourhood, MeshingMethod};
+use crate::types::UVMap;
+use crate::world_generator::WorldGenerator;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+type ChunkCoords = (i32, i32, i32);
+
+pub enum ChunkJob {
+    Generate {
+        coords: ChunkCoords,
+    },
+    Mesh {
+        coords: ChunkCoords,
+        // Used to throw away meshes that were already outdated when they finished
+        generation: u64,
+        neighbourhood: Box<ChunkNeighbourhood>,
+        method: MeshingMethod,
+    },
+}
+
+pub enum ChunkJobResult {
+    Generated {
+        coords: ChunkCoords,
+        blocks: Box<BlockArray>,
+    },
+    Meshed {
+        coords: ChunkCoords,
+        generation: u64,
+        mesh: ChunkMesh,
+        visibility: ChunkVisibility,
+    },
+}
+
+// Pool of threads doing the CPU heavy part of terrain generation and meshing
+// The main thread only sends jobs and collects the results, all OpenGL calls stay on the main thread
+pub struct ChunkWorkers {
+    job_sender: Option<Sender<ChunkJob>>,
+    result_receiver: Receiver<ChunkJobResult>,
+    threads: Vec<JoinHandle<()>>,
+    shutting_down: Arc<AtomicBool>,
+}
+
+impl ChunkWorkers {
+    pub fn new(generator: Arc<dyn WorldGenerator>, uv_map: Arc<UVMap>) -> Self {
+        let (job_sender, job_receiver) = channel::<ChunkJob>();
+        let (result_sender, result_receiver) = channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let shutting_down = Arc::new(AtomicBool::new(false));
+
+        // Keep a core free for the main thread
+        let n_threads = thread::available_parallelism()
+            .map(|n| n.get().saturating_sub(1))
+            .unwrap_or(1)
+            .max(1);
+
+        let threads = (0..n_threads)
+            .map(|i| {
+                let job_receiver = Arc::clone(&job_receiver);
+                let result_sender = result_sender.clone();
+                let generator = Arc::clone(&generator);
+                let uv_map = Arc::clone(&uv_map);
+                let shutting_down = Arc::clone(&shutting_down);
+
+                thread::Builder::new()
+                    .name(format!("chunk worker {i}"))
+                    .spawn(move || loop {
+                        // The lock is released as soon as a job is received
+                        let job = job_receiver.lock().unwrap().recv();
+
+                        // Don't bother finishing the queued jobs when the game is closing
+                        if shutting_down.load(Ordering::Relaxed) {
+                            break;
+                        }
+
+                        let result = match job {
+                            Ok(ChunkJob::Generate { coords }) => ChunkJobResult::Generated {
+                                coords,
+                                blocks: generator.generate_chunk(coords),
+                            },
+                            Ok(ChunkJob::Mesh {
+                                coords,
+                                generation,
+                                neighbourhood,
+                                method,
+                            }) => ChunkJobResult::Meshed {
+                                coords,
+                                generation,
+                                mesh: build_chunk_mesh(&neighbourhood, &uv_map, method),
+                                visibility: ChunkVisibility::compute(|x, y, z| {
+                                    neighbourhood.get(x as i32, y as i32, z as i32).is_transparent()
+                                }),
+                            },
+                            // The pool was dropped
+                            Err(_) => break,
+                        };
+
+                        if result_sender.send(result).is_err() {
+                            break;
+                        }
+                    })
+                    .expect("Failed to spawn a chunk worker")
+            })
+            .collect();
+
+        Self {
+            job_sender: Some(job_sender),
+            result_receiver,
+            threads,
+            shutting_down,
+        }
+    }
+
+    pub fn submit(&self, job: ChunkJob) {
+        self.job_sender
+            .as_ref()
+            .unwrap()
+            .send(job)
+            .expect("Chunk workers stopped unexpectedly");
+    }
+
+    // Results that are ready, never blocks
+    pub fn finished_jobs(&self) -> impl Iterator<Item = ChunkJobResult> + '_ {
+        self.result_receiver.try_iter()
+    }
+}
+
+impl Drop for ChunkWorkers {
+    fn drop(&mut self) {
+        // Closing the channel makes every idle worker exit its loop
+        self.shutting_down.store(true, Ordering::Relaxed);
+        self.job_sender.take();
+
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
@@ -23,6 +23,35 @@ pub const PHYSICS_TICKRATE: f32 = 60.0;
 pub const GRAVITY: f32 = -28.0;
 pub const MAX_VERTICAL_VELOCITY: f32 = 90.0;
 
+// World
+pub const WORLD_SEED: u32 = 1296;
+pub const SAVE_DIRECTORY: &str = "saves/world";
+pub const WORLD_HEIGHT_IN_CHUNKS: i32 = 16;
+pub const SEA_LEVEL: i32 = 40;
+pub const SNOW_LINE: i32 = SEA_LEVEL + 45;
+pub const RENDER_DISTANCE: i32 = 8;
+pub const UNLOAD_DISTANCE: i32 = RENDER_DISTANCE + 2;
+pub const CHUNK_LOADS_PER_FRAME: usize = 16;
+pub const MESH_UPLOADS_PER_FRAME: usize = 8;
+
+// Fluids
+pub const FLUID_TICKS_PER_SECOND: f32 = 20.0;
+pub const FLUID_UPDATES_PER_TICK: usize = 2048;
+pub const MAX_FLUID_TICKS_PER_FRAME: u32 = 5;
+
+// Time
+pub const DAY_LENGTH: f32 = 1200.0; // in seconds
+pub const START_TIME_OF_DAY: f32 = 0.05; // fraction of the day, 0.0 is sunrise
+pub const NIGHT_SKY_LIGHT: f32 = 0.2; // sky light multiplier at midnight
+pub const FAST_FORWARD_SPEED: f32 = 60.0;
+pub const SKY_DISTANCE: f32 = 100.0;
+pub const SUN_SIZE: f32 = 30.0;
+pub const MOON_SIZE: f32 = 20.0;
+pub const FOG_START: f32 = 0.7; // fraction of the render distance where the fog starts
+
+// Blocks
+pub const BLOCK_REGISTRY_PATH: &str = "assets/blocks.ron";
+
 // Texture
 pub const TEXTURE_ATLAS_SIZE: u32 = 1024;
 pub const BLOCK_TEXTURE_SIZE: u32 = 16;
@@ -33,16 +62,37 @@ pub const JUMP_HEIGHT: f32 = 1.3;
 pub const HORIZONTAL_ACCELERATION: f32 = 30.0;
 pub const WALKING_SPEED: f32 = 4.137;
 pub const FLYING_SPEED: f32 = 10.92;
+pub const SPRINTING_SPEED: f32 = 5.612;
+pub const SNEAKING_SPEED: f32 = 1.31;
+pub const SPRINT_FOV_FACTOR: f32 = 1.15;
+pub const SPECTATOR_SPEED: f32 = FLYING_SPEED;
+pub const MIN_SPECTATOR_SPEED: f32 = 1.0;
+pub const MAX_SPECTATOR_SPEED: f32 = 100.0;
+pub const SPECTATOR_SPEED_STEP: f32 = 1.25; // factor applied by each step of the scroll wheel
 
 pub const PLAYER_WIDTH: f32 = 0.6;
 pub const PLAYER_HEIGHT: f32 = 1.8;
 pub const PLAYER_EYES_HEIGHT: f32 = 1.6;
+pub const SNEAKING_EYES_HEIGHT: f32 = 1.27;
 pub const PLAYER_HALF_WIDTH: f32 = PLAYER_WIDTH / 2.0;
 pub const PLAYER_HALF_HEIGHT: f32 = PLAYER_HEIGHT / 2.0;
+pub const STEP_HEIGHT: f32 = 0.5; // highest block the player walks up without jumping
 
 pub const ON_GROUND_FRICTION: f32 = 12.0;
 pub const IN_AIR_FRICTION: f32 = 2.0;
 
+// Survival, in halves of the HUD icons
+pub const MAX_HEALTH: u32 = 20;
+pub const MAX_FOOD: u32 = 20;
+pub const APPLE_DROP_CHANCE: f32 = 0.05; // of the leaves broken in survival
+
+// Swimming
+pub const SWIMMING_SPEED: f32 = 2.2;
+pub const SWIM_ACCELERATION: f32 = 25.0; // up, while holding space
+pub const SWIM_OUT_SPEED: f32 = 5.0; // vertical speed when swimming against a wall
+pub const IN_FLUID_FRICTION: f32 = 6.0;
+pub const FLUID_GRAVITY_FACTOR: f32 = 0.2;
+
 lazy_static! {
     pub static ref JUMP_IMPULSE: f32 = (2.0 * -GRAVITY * JUMP_HEIGHT).sqrt();
 }
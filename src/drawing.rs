@@ -1,6 +1,13 @@
-use crate::constants::{GUI_SCALE, WINDOW_HEIGHT, WINDOW_WIDTH};
-use crate::inventory::Inventory;
+use crate::constants::{GUI_SCALE, MAX_FOOD, MAX_HEALTH, WINDOW_HEIGHT, WINDOW_WIDTH};
+use crate::gui::{StatusBarVaos, DIGIT_HEIGHT, DIGIT_WIDTH};
+use crate::inventory::item::ItemStack;
+use crate::inventory::screen::{
+    panel_position, slot_position, InventoryScreen, PANEL_HEIGHT, PANEL_WIDTH,
+};
+use crate::inventory::{Inventory, HOTBAR_SIZE};
 use crate::shader::ShaderProgram;
+use crate::survival::PlayerStats;
+use crate::types::UVMap;
 use nalgebra::Matrix4;
 use nalgebra_glm::{vec3, vec4};
 
@@ -73,20 +80,250 @@ impl Inventory {
         gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, 6));
     }
 
-    pub fn draw_hotbar_items(&self, shader: &mut ShaderProgram) {
+    // The counts of the stacks go in the bottom right corner of their slot, over the items
+    pub fn draw_hotbar_items(
+        &self,
+        item_shader: &mut ShaderProgram,
+        gui_shader: &mut ShaderProgram,
+        digit_vaos: &[u32; 10],
+        uv_map: &UVMap,
+    ) {
         let inter_slot_spacing = 20.0;
         let hotbar_left_margin = WINDOW_WIDTH as f32 / 2.0 - 4.0 * inter_slot_spacing * GUI_SCALE;
 
         let mut x = 0;
         let y = 11;
 
-        for slot in self.slots.iter() {
+        for slot in self.slots.iter().take(HOTBAR_SIZE) {
             if let Some(slot) = slot {
                 let item_x_pos = hotbar_left_margin + (x as f32) * inter_slot_spacing * GUI_SCALE;
-                slot.item_render
-                    .draw(item_x_pos, y as f32 * GUI_SCALE, shader);
+                slot.item_render(uv_map)
+                    .draw(item_x_pos, y as f32 * GUI_SCALE, item_shader);
             }
             x += 1;
         }
+
+        for (x, slot) in self.slots.iter().take(HOTBAR_SIZE).enumerate() {
+            if let Some(slot) = slot.filter(|slot| slot.amount > 1) {
+                let item_x_pos = hotbar_left_margin + (x as f32) * inter_slot_spacing * GUI_SCALE;
+                draw_number(
+                    slot.amount,
+                    item_x_pos + 9.0 * GUI_SCALE,
+                    (y as f32 - 9.0) * GUI_SCALE,
+                    digit_vaos,
+                    gui_shader,
+                );
+            }
+        }
+    }
+}
+
+impl InventoryScreen {
+    // The panel with every slot of the inventory, and the held stack over it under the cursor
+    pub fn draw(
+        &self,
+        inventory: &Inventory,
+        vao: u32,
+        item_shader: &mut ShaderProgram,
+        gui_shader: &mut ShaderProgram,
+        digit_vaos: &[u32; 10],
+        uv_map: &UVMap,
+    ) {
+        let model_matrix = {
+            let translate_matrix = Matrix4::new_translation(&vec3(
+                WINDOW_WIDTH as f32 / 2.0,
+                WINDOW_HEIGHT as f32 / 2.0,
+                0.0,
+            ));
+            let scale_matrix = Matrix4::new_nonuniform_scaling(&vec3(
+                PANEL_WIDTH * GUI_SCALE,
+                PANEL_HEIGHT * GUI_SCALE,
+                1.0,
+            ));
+
+            translate_matrix * scale_matrix
+        };
+
+        let projection_matrix = nalgebra_glm::ortho(
+            0.0,
+            WINDOW_WIDTH as f32,
+            0.0,
+            WINDOW_HEIGHT as f32,
+            -5.0,
+            5.0,
+        );
+
+        gui_shader.use_program();
+        gui_shader.set_uniform_matrix4fv("model", model_matrix.as_ptr());
+        gui_shader.set_uniform_matrix4fv("projection", projection_matrix.as_ptr());
+        gui_shader.set_uniform1i("tex", 8);
+
+        gl_call!(gl::BindVertexArray(vao));
+        gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, 6));
+
+        // The slots are laid out from the top left corner of the panel, the window from its bottom
+        let (left, top) = panel_position();
+        let slot_center = |slot: usize| {
+            let (x, y) = slot_position(slot);
+            (
+                left + (x + 9.0) * GUI_SCALE,
+                WINDOW_HEIGHT as f32 - top - (y + 9.0) * GUI_SCALE,
+            )
+        };
+
+        for (slot, stack) in inventory.slots.iter().enumerate() {
+            if let Some(stack) = stack {
+                let (x, y) = slot_center(slot);
+                draw_stack(stack, x, y, item_shader, gui_shader, digit_vaos, uv_map);
+            }
+        }
+
+        if let Some(held) = &self.held {
+            let (x, y) = self.cursor;
+            draw_stack(
+                held,
+                x,
+                WINDOW_HEIGHT as f32 - y,
+                item_shader,
+                gui_shader,
+                digit_vaos,
+                uv_map,
+            );
+        }
+    }
+}
+
+// The item centered on (x, y) with its count in the bottom right corner, like in the hotbar
+fn draw_stack(
+    stack: &ItemStack,
+    x: f32,
+    y: f32,
+    item_shader: &mut ShaderProgram,
+    gui_shader: &mut ShaderProgram,
+    digit_vaos: &[u32; 10],
+    uv_map: &UVMap,
+) {
+    stack.item_render(uv_map).draw(x, y, item_shader);
+
+    if stack.amount > 1 {
+        draw_number(
+            stack.amount,
+            x + 9.0 * GUI_SCALE,
+            y - 9.0 * GUI_SCALE,
+            digit_vaos,
+            gui_shader,
+        );
+    }
+}
+
+// Right aligned on `right`, with its bottom on `bottom`, in pixels of the window
+fn draw_number(
+    number: u32,
+    right: f32,
+    bottom: f32,
+    digit_vaos: &[u32; 10],
+    shader: &mut ShaderProgram,
+) {
+    let projection_matrix = nalgebra_glm::ortho(
+        0.0,
+        WINDOW_WIDTH as f32,
+        0.0,
+        WINDOW_HEIGHT as f32,
+        -5.0,
+        5.0,
+    );
+
+    shader.use_program();
+    shader.set_uniform_matrix4fv("projection", projection_matrix.as_ptr());
+    shader.set_uniform1i("tex", 7);
+
+    let width = DIGIT_WIDTH as f32 * GUI_SCALE;
+    let height = DIGIT_HEIGHT as f32 * GUI_SCALE;
+
+    for (i, digit) in number.to_string().bytes().rev().enumerate() {
+        let model_matrix = {
+            let translate_matrix = Matrix4::new_translation(&vec3(
+                right - (i as f32 + 0.5) * width,
+                bottom + height / 2.0,
+                0.0,
+            ));
+            let scale_matrix = Matrix4::new_nonuniform_scaling(&vec3(width, height, 1.0));
+
+            translate_matrix * scale_matrix
+        };
+
+        shader.set_uniform_matrix4fv("model", model_matrix.as_ptr());
+
+        gl_call!(gl::BindVertexArray(digit_vaos[(digit - b'0') as usize]));
+        gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, 6));
+    }
+}
+
+impl PlayerStats {
+    // Hearts on the left and food on the right, right above the hotbar
+    pub fn draw_status_bars(&self, vaos: &StatusBarVaos, shader: &mut ShaderProgram) {
+        let icon_spacing = 8.0;
+        let hotbar_half_width = 91.0;
+        let y = (22.0 + 2.0 + 4.5) * GUI_SCALE;
+
+        let projection_matrix = nalgebra_glm::ortho(
+            0.0,
+            WINDOW_WIDTH as f32,
+            0.0,
+            WINDOW_HEIGHT as f32,
+            -5.0,
+            5.0,
+        );
+
+        shader.use_program();
+        shader.set_uniform_matrix4fv("projection", projection_matrix.as_ptr());
+        shader.set_uniform1i("tex", 1);
+
+        let mut draw_icon = |vao: u32, x: f32| {
+            let model_matrix = {
+                let translate_matrix = Matrix4::new_translation(&vec3(x, y, 0.0));
+                let scale_matrix =
+                    Matrix4::new_nonuniform_scaling(&vec3(9.0 * GUI_SCALE, 9.0 * GUI_SCALE, 1.0));
+
+                translate_matrix * scale_matrix
+            };
+
+            shader.set_uniform_matrix4fv("model", model_matrix.as_ptr());
+
+            gl_call!(gl::BindVertexArray(vao));
+            gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, 6));
+        };
+
+        // Each icon is worth 2 points, a half icon shows an odd value
+        let icon_for = |value: u32, i: u32, full: u32, half: u32| {
+            if value >= 2 * i + 2 {
+                Some(full)
+            } else if value == 2 * i + 1 {
+                Some(half)
+            } else {
+                None
+            }
+        };
+
+        for i in 0..MAX_HEALTH / 2 {
+            let x = WINDOW_WIDTH as f32 / 2.0
+                + (-hotbar_half_width + i as f32 * icon_spacing + 4.5) * GUI_SCALE;
+
+            draw_icon(vaos.heart_container, x);
+            if let Some(vao) = icon_for(self.health, i, vaos.full_heart, vaos.half_heart) {
+                draw_icon(vao, x);
+            }
+        }
+
+        // Food empties from the left
+        for i in 0..MAX_FOOD / 2 {
+            let x = WINDOW_WIDTH as f32 / 2.0
+                + (hotbar_half_width - i as f32 * icon_spacing - 4.5) * GUI_SCALE;
+
+            draw_icon(vaos.food_container, x);
+            if let Some(vao) = icon_for(self.food, i, vaos.full_food, vaos.half_food) {
+                draw_icon(vao, x);
+            }
+        }
     }
 }
@@ -0,0 +1,270 @@
+use crate::aabb::AABB;
+use crate::chunk_manager::ChunkManager;
+use crate::constants::GRAVITY;
+use crate::inventory::item::{Item, ItemStack};
+use crate::inventory::Inventory;
+use crate::shader::ShaderProgram;
+use crate::types::UVMap;
+use nalgebra::Matrix4;
+use nalgebra_glm::{vec3, Mat4, Vec3};
+use rand::random;
+
+/*
+    Items lying in the world, dropped by the blocks the player breaks or thrown with Q
+
+    Each item is a small box that falls and collides with the blocks like the player does
+    The model spins and bobs up and down above the box, the box itself doesn't move
+
+    Stacks of the same item lying next to each other merge into one, and the player picks up the
+    items they walk into once the pickup delay is over (longer for the items they threw themselves)
+    Items left alone for DESPAWN_TIME disappear, the ones in unloaded chunks wait for them
+*/
+
+const ITEM_HALF_SIZE: f32 = 0.125;
+// The longest step of the simulation, in seconds
+const MAX_STEP: f32 = 0.05;
+const TERMINAL_SPEED: f32 = 40.0;
+// Fraction of the horizontal speed kept after a second
+const GROUND_FRICTION: f32 = 0.002;
+const AIR_FRICTION: f32 = 0.3;
+// Items stuck in a block slowly go up until they are out
+const ESCAPE_SPEED: f32 = 2.0;
+const STUCK_MARGIN: f32 = 0.01;
+
+const PICKUP_DELAY: f32 = 0.5; // in seconds
+const THROWN_PICKUP_DELAY: f32 = 2.0; // in seconds
+const THROW_SPEED: f32 = 6.0;
+const DESPAWN_TIME: f32 = 300.0; // in seconds
+
+// Horizontal distance between two boxes that merge, and reach of the player around their box
+const MERGE_DISTANCE: f32 = 0.5;
+const PICKUP_REACH: Vec3 = Vec3::new(1.0, 0.5, 1.0);
+
+const BOB_HEIGHT: f32 = 0.1;
+const BOB_SPEED: f32 = 2.5; // in radians per second
+const SPIN_SPEED: f32 = 1.0; // in radians per second
+
+pub struct DroppedItem {
+    pub stack: ItemStack,
+    pub aabb: AABB,
+    velocity: Vec3,
+    // Seconds since the item was dropped
+    age: f32,
+    pickup_delay: f32,
+    // Keeps the items that fall together from spinning in sync
+    spin_offset: f32,
+}
+
+impl DroppedItem {
+    fn new(stack: ItemStack, center: Vec3, velocity: Vec3, pickup_delay: f32) -> Self {
+        let half_size = vec3(ITEM_HALF_SIZE, ITEM_HALF_SIZE, ITEM_HALF_SIZE);
+
+        Self {
+            stack,
+            aabb: AABB::new(center - half_size, center + half_size),
+            velocity,
+            age: 0.0,
+            pickup_delay,
+            spin_offset: random::<f32>() * std::f32::consts::TAU,
+        }
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.aabb.mins + self.aabb.maxs) / 2.0
+    }
+
+    fn update(&mut self, chunk_manager: &ChunkManager, dt: f32) {
+        self.age += dt;
+        self.pickup_delay = (self.pickup_delay - dt).max(0.0);
+
+        // Resting on a block isn't being stuck in it
+        let mut inside = self.aabb;
+        inside.mins += vec3(STUCK_MARGIN, STUCK_MARGIN, STUCK_MARGIN);
+        inside.maxs -= vec3(STUCK_MARGIN, STUCK_MARGIN, STUCK_MARGIN);
+
+        let obstacles = chunk_manager.get_collision_boxes_in(&inside);
+        if obstacles.iter().any(|obstacle| inside.intersects(obstacle)) {
+            self.velocity = vec3(0.0, 0.0, 0.0);
+            self.aabb.translate(&vec3(0.0, ESCAPE_SPEED * dt, 0.0));
+            return;
+        }
+
+        self.velocity.y = (self.velocity.y + GRAVITY * dt).max(-TERMINAL_SPEED);
+
+        let offset = self.velocity * dt;
+        let obstacles = chunk_manager.get_collision_boxes_in(&self.aabb.expanded_towards(&offset));
+        let moved = self.aabb.sweep(&obstacles, &offset);
+
+        if moved.x != offset.x {
+            self.velocity.x = 0.0;
+        }
+        if moved.y != offset.y {
+            self.velocity.y = 0.0;
+        }
+        if moved.z != offset.z {
+            self.velocity.z = 0.0;
+        }
+
+        let is_on_ground = offset.y < 0.0 && moved.y != offset.y;
+        let friction = if is_on_ground {
+            GROUND_FRICTION
+        } else {
+            AIR_FRICTION
+        };
+        let kept = friction.powf(dt);
+        self.velocity.x *= kept;
+        self.velocity.z *= kept;
+    }
+
+    fn can_merge_with(&self, other: &DroppedItem) -> bool {
+        let mut area = self.aabb;
+        area.mins -= vec3(MERGE_DISTANCE, 0.0, MERGE_DISTANCE);
+        area.maxs += vec3(MERGE_DISTANCE, 0.0, MERGE_DISTANCE);
+
+        self.stack.stacks_with(&other.stack)
+            && !self.stack.is_full()
+            && !other.stack.is_full()
+            && area.intersects(&other.aabb)
+    }
+}
+
+#[derive(Default)]
+pub struct DroppedItems {
+    pub items: Vec<DroppedItem>,
+}
+
+impl DroppedItems {
+    // The item pops out of the middle of the block
+    pub fn drop_from_block(&mut self, stack: ItemStack, (x, y, z): (i32, i32, i32)) {
+        let center = vec3(x as f32 + 0.5, y as f32 + 0.5, z as f32 + 0.5);
+        let velocity = vec3(random::<f32>() - 0.5, 4.0, random::<f32>() - 0.5);
+
+        self.items
+            .push(DroppedItem::new(stack, center, velocity, PICKUP_DELAY));
+    }
+
+    // Thrown in front of the player, slightly upwards
+    pub fn throw(&mut self, stack: ItemStack, eyes_position: Vec3, looking_dir: Vec3) {
+        let center = eyes_position - vec3(0.0, 0.3, 0.0);
+        let velocity = looking_dir.normalize() * THROW_SPEED + vec3(0.0, 2.0, 0.0);
+
+        self.items.push(DroppedItem::new(
+            stack,
+            center,
+            velocity,
+            THROWN_PICKUP_DELAY,
+        ));
+    }
+
+    // Moves the items and merges the ones close to each other
+    pub fn update(&mut self, chunk_manager: &ChunkManager, dt: f32) {
+        for item in &mut self.items {
+            let center = item.center().map(|c| c.floor() as i32);
+            if chunk_manager
+                .get_block(center.x, center.y, center.z)
+                .is_none()
+            {
+                continue;
+            }
+
+            let mut remaining = dt;
+            while remaining > 0.0 {
+                let step = remaining.min(MAX_STEP);
+                item.update(chunk_manager, step);
+                remaining -= step;
+            }
+        }
+
+        self.items.retain(|item| item.age < DESPAWN_TIME);
+        self.merge_stacks();
+    }
+
+    // The first item takes as much of the stack of the other one as it can
+    // Merged items live as long as the newest of them
+    fn merge_stacks(&mut self) {
+        let mut i = 0;
+
+        while i < self.items.len() {
+            let mut j = i + 1;
+
+            while j < self.items.len() {
+                if !self.items[i].can_merge_with(&self.items[j]) {
+                    j += 1;
+                    continue;
+                }
+
+                let other = &self.items[j];
+                let (other_stack, age, pickup_delay) = (other.stack, other.age, other.pickup_delay);
+
+                let item = &mut self.items[i];
+                let leftover = item.stack.merge(other_stack);
+
+                item.age = item.age.min(age);
+                item.pickup_delay = item.pickup_delay.max(pickup_delay);
+
+                match leftover {
+                    Some(leftover) => {
+                        self.items[j].stack = leftover;
+                        j += 1;
+                    }
+                    None => {
+                        self.items.swap_remove(j);
+                    }
+                }
+            }
+
+            i += 1;
+        }
+    }
+
+    // Moves the items the player touches into the inventory, as long as there is room
+    pub fn pick_up(&mut self, player_aabb: &AABB, inventory: &mut Inventory) {
+        let mut reach = *player_aabb;
+        reach.mins -= PICKUP_REACH;
+        reach.maxs += PICKUP_REACH;
+
+        self.items.retain_mut(|item| {
+            if item.pickup_delay > 0.0 || !reach.intersects(&item.aabb) {
+                return true;
+            }
+
+            match inventory.add_stack(item.stack) {
+                Some(leftover) => {
+                    item.stack = leftover;
+                    true
+                }
+                None => false,
+            }
+        });
+    }
+
+    pub fn draw(&self, shader: &mut ShaderProgram, view_projection: &Mat4, uv_map: &UVMap) {
+        // Tools and food are flat and spin, both of their sides are seen
+        gl_call!(gl::Disable(gl::CULL_FACE));
+
+        for item in &self.items {
+            let (size, height) = match item.stack.item {
+                Item::Block(_) => (2.0 * ITEM_HALF_SIZE, ITEM_HALF_SIZE),
+                Item::Tool(_) | Item::Food(_) => (4.0 * ITEM_HALF_SIZE, 2.0 * ITEM_HALF_SIZE),
+            };
+
+            let bob = BOB_HEIGHT * (1.0 + (item.age * BOB_SPEED).sin());
+            let position = vec3(
+                item.center().x,
+                item.aabb.mins.y + height + bob,
+                item.center().z,
+            );
+            let angle = item.age * SPIN_SPEED + item.spin_offset;
+
+            let model_matrix = Matrix4::new_translation(&position)
+                * Matrix4::from_euler_angles(0.0, angle, 0.0)
+                * Matrix4::new_scaling(size);
+
+            item.stack
+                .item_render(uv_map)
+                .draw_in_world(&model_matrix, view_projection, shader);
+        }
+
+        gl_call!(gl::Enable(gl::CULL_FACE));
+    }
+}
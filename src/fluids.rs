@@ -0,0 +1,273 @@
+use crate::block_registry::{known_blocks, FluidProperties};
+use crate::block_state::{BlockState, Property};
+use crate::chunk::BlockID;
+use crate::chunk_manager::ChunkManager;
+use crate::constants::{FLUID_TICKS_PER_SECOND, FLUID_UPDATES_PER_TICK, MAX_FLUID_TICKS_PER_FRAME};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+use std::time::Instant;
+
+/*
+    Fluids flow with scheduled updates : when a fluid or one of its neighbours changes, the fluid is
+    updated again `flow_delay` game ticks later (5 for water, 30 for lava)
+
+    Each fluid block has a level, stored in the `Level` property of its block state :
+        0           source, it never dries out
+        1 to 7      flowing, the level goes up by `7 / flow_distance` per block away from the source
+        FALLING     has the same fluid above it, it fills the whole block and spreads like a source
+
+    An update first takes the level of a flowing block from its neighbours (it dries out without any),
+    then the fluid flows down if it can. Sources and fluids that can't go down spread sideways
+    Lava touching water hardens into obsidian when it is a source and into cobblestone otherwise
+*/
+
+pub const MAX_FLUID_LEVEL: u8 = 7;
+pub const FALLING: u8 = 8;
+
+// Height of the top of a fluid for each level, in 16ths of a block
+const LEVEL_HEIGHTS: [u8; 8] = [14, 12, 11, 9, 7, 5, 4, 2];
+
+type BlockCoords = (i32, i32, i32);
+
+const HORIZONTAL_DIRECTIONS: [BlockCoords; 4] = [(1, 0, 0), (-1, 0, 0), (0, 0, 1), (0, 0, -1)];
+
+const DIRECTIONS: [BlockCoords; 6] = [
+    (1, 0, 0),
+    (-1, 0, 0),
+    (0, 1, 0),
+    (0, -1, 0),
+    (0, 0, 1),
+    (0, 0, -1),
+];
+
+fn offset((x, y, z): BlockCoords, (dx, dy, dz): BlockCoords) -> BlockCoords {
+    (x + dx, y + dy, z + dz)
+}
+
+// In 16ths of a block, falling fluids are full
+pub fn fluid_height(level: u8) -> u8 {
+    match level {
+        FALLING => 16,
+        _ => LEVEL_HEIGHTS[level as usize],
+    }
+}
+
+// Level given to the neighbours of a fluid at `level`, it can't flow further when above MAX_FLUID_LEVEL
+fn spread_level(level: u8, fluid: &FluidProperties) -> u8 {
+    let step = MAX_FLUID_LEVEL / fluid.flow_distance;
+
+    match level {
+        0 | FALLING => step,
+        _ => level + step,
+    }
+}
+
+// Fluid updates waiting for their game tick
+#[derive(Default)]
+pub struct FluidTicks {
+    tick: u64,
+    last_update: Option<Instant>,
+    // Time that wasn't simulated yet, in seconds
+    accumulator: f32,
+    scheduled: BinaryHeap<Reverse<(u64, BlockCoords)>>,
+    // Blocks in `scheduled`, a block is never waiting for 2 updates at once
+    pending: HashSet<BlockCoords>,
+}
+
+impl ChunkManager {
+    pub fn get_fluid_level(&self, x: i32, y: i32, z: i32) -> Option<u8> {
+        self.get_block_state(x, y, z).map(|state| state.get(Property::Level))
+    }
+
+    fn set_fluid(&mut self, (x, y, z): BlockCoords, block: BlockID, level: u8) {
+        self.set_block_state(x, y, z, BlockState::new(block).with(Property::Level, level));
+    }
+
+    // Runs the fluid updates of the game ticks since the last call
+    pub fn update_fluids(&mut self, now: Instant) {
+        let tick_duration = 1.0 / FLUID_TICKS_PER_SECOND;
+        let ticks = &mut self.fluid_ticks;
+
+        if let Some(last_update) = ticks.last_update {
+            // Don't try to catch up after a long frame, the fluids just flow slower
+            ticks.accumulator = (ticks.accumulator + (now - last_update).as_secs_f32())
+                .min(MAX_FLUID_TICKS_PER_FRAME as f32 * tick_duration);
+        }
+        ticks.last_update = Some(now);
+
+        while self.fluid_ticks.accumulator >= tick_duration {
+            self.fluid_ticks.accumulator -= tick_duration;
+            self.fluid_ticks.tick += 1;
+            self.run_fluid_tick();
+        }
+    }
+
+    // Updates left over when there are too many wait for the next tick
+    fn run_fluid_tick(&mut self) {
+        for _ in 0..FLUID_UPDATES_PER_TICK {
+            let position = match self.fluid_ticks.scheduled.peek() {
+                Some(&Reverse((tick, position))) if tick <= self.fluid_ticks.tick => position,
+                _ => break,
+            };
+
+            self.fluid_ticks.scheduled.pop();
+            self.fluid_ticks.pending.remove(&position);
+            self.update_fluid(position);
+        }
+    }
+
+    // The fluids at `position` and around it need to be updated after their flow delay
+    pub(crate) fn schedule_fluid_updates(&mut self, position: BlockCoords) {
+        let neighbours = DIRECTIONS.map(|direction| offset(position, direction));
+
+        for (x, y, z) in std::iter::once(position).chain(neighbours) {
+            let fluid = match self.get_block(x, y, z) {
+                Some(block) => block.definition().fluid,
+                None => continue,
+            };
+
+            if let Some(fluid) = fluid {
+                if self.fluid_ticks.pending.insert((x, y, z)) {
+                    let tick = self.fluid_ticks.tick + fluid.flow_delay;
+                    self.fluid_ticks.scheduled.push(Reverse((tick, (x, y, z))));
+                }
+            }
+        }
+    }
+
+    fn update_fluid(&mut self, position: BlockCoords) {
+        let (x, y, z) = position;
+        let block = match self.get_block(x, y, z) {
+            Some(block) if block.is_fluid() => block,
+            // Replaced or unloaded since the update was scheduled
+            _ => return,
+        };
+        let fluid = block.definition().fluid.unwrap();
+        let mut level = self.get_fluid_level(x, y, z).unwrap();
+
+        if let Some(hardened) = self.hardened_lava(position, block, level) {
+            self.set_block(x, y, z, hardened);
+            return;
+        }
+
+        if level != 0 {
+            match self.fed_level(position, block, &fluid) {
+                None => {
+                    self.set_block(x, y, z, BlockID::AIR);
+                    return;
+                }
+                Some(fed_level) if fed_level != level => {
+                    self.set_fluid(position, block, fed_level);
+                    level = fed_level;
+                }
+                _ => {}
+            }
+        }
+
+        // Flowing fluids above air or the same fluid only go down
+        let below = (x, y - 1, z);
+        if let Some(below_block) = self.get_block(below.0, below.1, below.2) {
+            if below_block.is_air() || below_block == block {
+                if self.can_flow_into(below, block, FALLING) {
+                    self.set_fluid(below, block, FALLING);
+                }
+
+                if level != 0 {
+                    return;
+                }
+            }
+        }
+
+        let new_level = spread_level(level, &fluid);
+        if new_level > MAX_FLUID_LEVEL {
+            return;
+        }
+
+        for direction in HORIZONTAL_DIRECTIONS {
+            let neighbour = offset(position, direction);
+
+            if self.can_flow_into(neighbour, block, new_level) {
+                self.set_fluid(neighbour, block, new_level);
+            }
+        }
+    }
+
+    // Level of a flowing block given by its neighbours, None when nothing feeds it anymore
+    fn fed_level(
+        &self,
+        (x, y, z): BlockCoords,
+        block: BlockID,
+        fluid: &FluidProperties,
+    ) -> Option<u8> {
+        if self.get_block(x, y + 1, z) == Some(block) {
+            return Some(FALLING);
+        }
+
+        let mut sources = 0;
+        let mut fed_level = None;
+
+        for direction in HORIZONTAL_DIRECTIONS {
+            let (nx, ny, nz) = offset((x, y, z), direction);
+            if self.get_block(nx, ny, nz) != Some(block) {
+                continue;
+            }
+
+            let level = self.get_fluid_level(nx, ny, nz).unwrap();
+            if level == 0 {
+                sources += 1;
+            }
+
+            let level = spread_level(level, fluid);
+            fed_level = Some(fed_level.map_or(level, |fed_level: u8| fed_level.min(level)));
+        }
+
+        // A new source needs something to stand on, otherwise it would flow down forever
+        if fluid.infinite_sources && sources >= 2 {
+            let stands_on_something = self.get_block(x, y - 1, z).map_or(false, |below| {
+                below.is_solid() || (below == block && self.get_fluid_level(x, y - 1, z) == Some(0))
+            });
+
+            if stands_on_something {
+                return Some(0);
+            }
+        }
+
+        fed_level.filter(|&level| level <= MAX_FLUID_LEVEL)
+    }
+
+    // Air, or the same fluid when it would get higher
+    fn can_flow_into(&self, (x, y, z): BlockCoords, block: BlockID, new_level: u8) -> bool {
+        match self.get_block(x, y, z) {
+            Some(other) if other.is_air() => true,
+            Some(other) if other == block => match self.get_fluid_level(x, y, z).unwrap() {
+                0 | FALLING => false,
+                level => new_level == FALLING || level > new_level,
+            },
+            _ => false,
+        }
+    }
+
+    // Water above or beside lava turns it into stone
+    fn hardened_lava(&self, position: BlockCoords, block: BlockID, level: u8) -> Option<BlockID> {
+        let known = known_blocks();
+        if block != known.lava {
+            return None;
+        }
+
+        let touches_water = DIRECTIONS
+            .iter()
+            .filter(|&&direction| direction != (0, -1, 0))
+            .any(|&direction| {
+                let (x, y, z) = offset(position, direction);
+                self.get_block(x, y, z) == Some(known.water)
+            });
+
+        touches_water.then(|| {
+            if level == 0 {
+                known.obsidian
+            } else {
+                known.cobblestone
+            }
+        })
+    }
+}
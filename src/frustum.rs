@@ -0,0 +1,128 @@
+use crate::aabb::AABB;
+use nalgebra_glm::{Mat4, Vec4};
+
+/*
+    The 6 planes of the camera's view volume, extracted from projection * view (Gribb & Hartmann)
+    Each plane is (a, b, c, d) with a point p inside when a * p.x + b * p.y + c * p.z + d >= 0
+*/
+pub struct Frustum {
+    // Left, right, bottom, top, near, far
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    pub fn from_matrix(view_projection: &Mat4) -> Self {
+        let row = |i: usize| view_projection.row(i).transpose();
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+
+        Self {
+            planes: [w + x, w - x, w + y, w - y, w + z, w - z],
+        }
+    }
+
+    // Conservative : a box close to a corner of the frustum can be reported as visible
+    pub fn intersects_aabb(&self, aabb: &AABB) -> bool {
+        self.planes.iter().all(|plane| {
+            // Corner of the box that is the furthest along the normal of the plane
+            let corner = |axis: usize| {
+                if plane[axis] >= 0.0 {
+                    aabb.maxs[axis]
+                } else {
+                    aabb.mins[axis]
+                }
+            };
+
+            plane.x * corner(0) + plane.y * corner(1) + plane.z * corner(2) + plane.w >= 0.0
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::{vec3, Vec3};
+
+    const EYE: (f32, f32, f32) = (8.0, 70.0, -4.0);
+    const NEAR: f32 = 0.1;
+    const FAR: f32 = 100.0;
+
+    // Camera at EYE looking along +x, so right is +z. With a square 90° view, the view volume
+    // is as wide as it is far from the camera
+    fn frustum() -> Frustum {
+        let eye = vec3(EYE.0, EYE.1, EYE.2);
+        let view = nalgebra_glm::look_at(&eye, &(eye + Vec3::x()), &Vec3::y());
+        let projection = nalgebra_glm::perspective(1.0, 90f32.to_radians(), NEAR, FAR);
+
+        Frustum::from_matrix(&(projection * view))
+    }
+
+    // Cube of the given half size, centered at a distance forward, right and up of the camera
+    fn cube(forward: f32, right: f32, up: f32, half: f32) -> AABB {
+        let center = vec3(EYE.0 + forward, EYE.1 + up, EYE.2 + right);
+        AABB::new(center.add_scalar(-half), center.add_scalar(half))
+    }
+
+    fn assert_visible(cases: &[(&str, AABB)], visible: bool) {
+        let frustum = frustum();
+        for (name, aabb) in cases {
+            assert_eq!(frustum.intersects_aabb(aabb), visible, "{name}");
+        }
+    }
+
+    #[test]
+    fn boxes_inside_are_visible() {
+        assert_visible(
+            &[
+                ("center", cube(10.0, 0.0, 0.0, 0.5)),
+                ("close", cube(1.0, 0.0, 0.0, 0.1)),
+                ("far", cube(90.0, 0.0, 0.0, 5.0)),
+                ("off center", cube(10.0, 7.0, -7.0, 1.0)),
+                ("around the camera", cube(0.0, 0.0, 0.0, 1.0)),
+            ],
+            true,
+        );
+    }
+
+    #[test]
+    fn boxes_outside_of_each_plane_are_culled() {
+        assert_visible(
+            &[
+                ("left", cube(10.0, -13.0, 0.0, 1.0)),
+                ("right", cube(10.0, 13.0, 0.0, 1.0)),
+                ("bottom", cube(10.0, 0.0, -13.0, 1.0)),
+                ("top", cube(10.0, 0.0, 13.0, 1.0)),
+                ("near", cube(0.05, 0.0, 0.0, 0.02)),
+                ("far", cube(120.0, 0.0, 0.0, 5.0)),
+            ],
+            false,
+        );
+    }
+
+    #[test]
+    fn boxes_straddling_a_plane_are_visible() {
+        assert_visible(
+            &[
+                ("left", cube(10.0, -10.0, 0.0, 1.0)),
+                ("right", cube(10.0, 10.0, 0.0, 1.0)),
+                ("bottom", cube(10.0, 0.0, -10.0, 1.0)),
+                ("top", cube(10.0, 0.0, 10.0, 1.0)),
+                ("near", cube(NEAR, 0.0, 0.0, 0.05)),
+                ("far", cube(FAR, 0.0, 0.0, 5.0)),
+            ],
+            true,
+        );
+    }
+
+    #[test]
+    fn boxes_behind_the_camera_are_culled() {
+        assert_visible(
+            &[
+                ("behind", cube(-10.0, 0.0, 0.0, 1.0)),
+                ("right behind", cube(-1.5, 0.0, 0.0, 1.0)),
+                ("behind to the side", cube(-10.0, 10.0, 0.0, 1.0)),
+                ("far behind", cube(-120.0, 0.0, 0.0, 50.0)),
+            ],
+            false,
+        );
+    }
+}
@@ -3,14 +3,19 @@ use std::os::raw::c_void;
 use glfw::WindowHint;
 use image::ColorType;
 use nalgebra::Matrix4;
-use nalgebra_glm::vec3;
+use nalgebra_glm::{vec3, IVec3};
 
 use crate::{
     shader::ShaderProgram,
-    shapes::{block_outline, quad},
+    shapes::{block_outline, centered_unit_cube, quad},
+    types::UVCoords,
     CROSSHAIR_SIZE, WINDOW_HEIGHT, WINDOW_WIDTH,
 };
 
+// Size of a digit of the item counts, in pixels of digits.png
+pub const DIGIT_WIDTH: usize = 6;
+pub const DIGIT_HEIGHT: usize = 8;
+
 pub fn create_gui_icons_texture() -> u32 {
     let gui_icons_image = match image::open("textures/gui/icons.png") {
         Ok(img) => img,
@@ -146,6 +151,92 @@ pub fn draw_crosshair(vao: u32, shader: &mut ShaderProgram) {
     gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, 6));
 }
 
+// Quads of the icons of the health and food bars
+pub struct StatusBarVaos {
+    pub heart_container: u32,
+    pub full_heart: u32,
+    pub half_heart: u32,
+    pub food_container: u32,
+    pub full_food: u32,
+    pub half_food: u32,
+}
+
+impl StatusBarVaos {
+    pub fn new() -> Self {
+        Self {
+            heart_container: create_icon_vao(16.0, 0.0),
+            full_heart: create_icon_vao(52.0, 0.0),
+            half_heart: create_icon_vao(61.0, 0.0),
+            food_container: create_icon_vao(16.0, 27.0),
+            full_food: create_icon_vao(52.0, 27.0),
+            half_food: create_icon_vao(61.0, 27.0),
+        }
+    }
+}
+
+// Quad of the 9x9 icon at (x, y) in the pixels of icons.png
+fn create_icon_vao(x: f32, y: f32) -> u32 {
+    create_quad_vao((x / 256.0, y / 256.0, (x + 9.0) / 256.0, (y + 9.0) / 256.0))
+}
+
+// One quad per digit of digits.png, the digits are 6x8 pixels side by side, shadow included
+pub fn create_digit_vaos() -> [u32; 10] {
+    // create_texture flips the images, so v goes up
+    std::array::from_fn(|digit| {
+        let u = |i: usize| (i * DIGIT_WIDTH) as f32 / (10 * DIGIT_WIDTH) as f32;
+        create_quad_vao((u(digit), 1.0, u(digit + 1), 0.0))
+    })
+}
+
+// The whole of inventory.png, flipped by create_texture like the digits
+pub fn create_inventory_vao() -> u32 {
+    create_quad_vao((0.0, 1.0, 1.0, 0.0))
+}
+
+fn create_quad_vao(uv: UVCoords) -> u32 {
+    // Setup VAO
+    let mut vao = 0;
+    gl_call!(gl::CreateVertexArrays(1, &mut vao));
+
+    // Position
+    gl_call!(gl::EnableVertexArrayAttrib(vao, 0));
+    gl_call!(gl::VertexArrayAttribFormat(vao, 0, 3, gl::FLOAT, gl::FALSE, 0));
+    gl_call!(gl::VertexArrayAttribBinding(vao, 0, 0));
+
+    // Texture Coords
+    gl_call!(gl::EnableVertexArrayAttrib(vao, 1));
+    gl_call!(gl::VertexArrayAttribFormat(
+        vao,
+        1,
+        2,
+        gl::FLOAT,
+        gl::FALSE,
+        (3 * std::mem::size_of::<f32>()) as u32
+    ));
+    gl_call!(gl::VertexArrayAttribBinding(vao, 1, 0));
+
+    // VBO
+    let mut vbo = 0;
+    gl_call!(gl::CreateBuffers(1, &mut vbo));
+
+    gl_call!(gl::VertexArrayVertexBuffer(
+        vao,
+        0,
+        vbo,
+        0,
+        (5 * std::mem::size_of::<f32>()) as i32
+    ));
+
+    gl_call!(gl::NamedBufferData(
+        vbo,
+        (5 * 6 * std::mem::size_of::<f32>()) as isize,
+        quad(uv).as_ptr() as *const c_void,
+        gl::STATIC_DRAW
+    ));
+
+    vao
+}
+
 pub fn create_block_outline_vao() -> u32 {
     // Setup VAO
     let mut vao = 0;
@@ -185,6 +276,76 @@ pub fn create_block_outline_vao() -> u32 {
     vao
 }
 
+// Unit cube with the whole texture on every face, the cracks of the block being mined are drawn
+// on one of its faces with the outline shader
+pub fn create_block_cracks_vao() -> u32 {
+    let mut vao = 0;
+    gl_call!(gl::CreateVertexArrays(1, &mut vao));
+
+    // Position
+    gl_call!(gl::EnableVertexArrayAttrib(vao, 0));
+    gl_call!(gl::VertexArrayAttribFormat(
+        vao,
+        0,
+        3,
+        gl::FLOAT,
+        gl::FALSE,
+        0
+    ));
+    gl_call!(gl::VertexArrayAttribBinding(vao, 0, 0));
+
+    // Texture Coords
+    gl_call!(gl::EnableVertexArrayAttrib(vao, 1));
+    gl_call!(gl::VertexArrayAttribFormat(
+        vao,
+        1,
+        2,
+        gl::FLOAT,
+        gl::FALSE,
+        (3 * std::mem::size_of::<f32>()) as u32
+    ));
+    gl_call!(gl::VertexArrayAttribBinding(vao, 1, 0));
+
+    // VBO, the normals are left out of the attributes
+    let mut vbo = 0;
+    gl_call!(gl::CreateBuffers(1, &mut vbo));
+
+    gl_call!(gl::VertexArrayVertexBuffer(
+        vao,
+        0,
+        vbo,
+        0,
+        (8 * std::mem::size_of::<f32>()) as i32
+    ));
+
+    let uv = (0.0, 0.0, 1.0, 1.0);
+    let vbo_data = centered_unit_cube(0.0, 0.0, 0.0, (uv, uv, uv, uv, uv, uv));
+
+    gl_call!(gl::NamedBufferData(
+        vbo,
+        (vbo_data.len() * std::mem::size_of::<f32>()) as isize,
+        vbo_data.as_ptr() as *const c_void,
+        gl::STATIC_DRAW
+    ));
+
+    vao
+}
+
+// First vertex of the face along `normal` in the block cracks VAO
+// The faces of `centered_unit_cube` are front, back, left, right, top and bottom
+pub fn block_face_first_vertex(normal: &IVec3) -> i32 {
+    let face = match (normal.x, normal.y, normal.z) {
+        (_, _, 1) => 0,
+        (_, _, -1) => 1,
+        (-1, _, _) => 2,
+        (1, _, _) => 3,
+        (_, 1, _) => 4,
+        _ => 5,
+    };
+
+    face * 6
+}
+
 pub fn create_widgets_texture() -> u32 {
     let widgets_image = match image::open("textures/gui/widgets.png") {
         Ok(img) => img,
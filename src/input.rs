@@ -1,4 +1,4 @@
-use glfw::{Action, Key, WindowEvent};
+use glfw::{Action, Key, MouseButton, WindowEvent};
 use nalgebra_glm::{vec2, DVec2};
 use std::collections::HashMap;
 
@@ -6,6 +6,7 @@ pub struct InputCache {
     pub last_cursor_pos: DVec2,
     pub cursor_rel_pos: DVec2,
     pub key_states: HashMap<Key, Action>,
+    pub mouse_button_states: HashMap<MouseButton, Action>,
 }
 
 impl Default for InputCache {
@@ -14,6 +15,7 @@ impl Default for InputCache {
             last_cursor_pos: vec2(0.0, 0.0),
             cursor_rel_pos: vec2(0.0, 0.0),
             key_states: HashMap::new(),
+            mouse_button_states: HashMap::new(),
         }
     }
 }
@@ -32,6 +34,9 @@ impl InputCache {
             &glfw::WindowEvent::Key(key, _, action, _) => {
                 self.key_states.insert(key, action);
             }
+            &glfw::WindowEvent::MouseButton(button, action, _) => {
+                self.mouse_button_states.insert(button, action);
+            }
             _ => {}
         }
     }
@@ -42,4 +47,8 @@ impl InputCache {
             None => false,
         }
     }
+
+    pub fn is_mouse_button_pressed(&self, button: MouseButton) -> bool {
+        self.mouse_button_states.get(&button) == Some(&Action::Press)
+    }
 }
@@ -1,37 +1,243 @@
 use crate::chunk::BlockID;
 use crate::constants::{GUI_SCALE, WINDOW_HEIGHT, WINDOW_WIDTH};
 use crate::shader::ShaderProgram;
-use crate::shapes::centered_unit_cube;
+use crate::shapes::{centered_unit_cube, quad};
+use crate::texture::create_texture;
 use crate::types::UVMap;
 
 use nalgebra::{Matrix, Matrix4, Vector3};
 use nalgebra_glm::{pi, vec3, Mat4};
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::collections::HashMap;
 
-#[derive(Copy, Clone)]
+// Most items in a slot of the inventory, tools don't stack
+pub const MAX_STACK_SIZE: u32 = 64;
+
+// Tools and food have their own texture instead of a tile of the atlas
+const ICON_TEXTURE_UNIT: u32 = 5;
+
+thread_local! {
+    // Every stack of an item shares the same VAO, they are only created on the main thread
+    static ITEM_RENDERS: RefCell<HashMap<Item, ItemRender>> = RefCell::new(HashMap::new());
+}
+
+// Blocks a tool mines faster, see mining.rs
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ToolKind {
+    Pickaxe,
+    Axe,
+    Shovel,
+}
+
+// From the weakest to the strongest
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ToolTier {
+    Wooden,
+    Stone,
+    Iron,
+    Diamond,
+}
+
+impl ToolKind {
+    pub const ALL: [ToolKind; 3] = [ToolKind::Pickaxe, ToolKind::Axe, ToolKind::Shovel];
+
+    fn name(&self) -> &'static str {
+        match self {
+            ToolKind::Pickaxe => "pickaxe",
+            ToolKind::Axe => "axe",
+            ToolKind::Shovel => "shovel",
+        }
+    }
+}
+
+impl ToolTier {
+    pub const ALL: [ToolTier; 4] = [
+        ToolTier::Wooden,
+        ToolTier::Stone,
+        ToolTier::Iron,
+        ToolTier::Diamond,
+    ];
+
+    // How many times faster than by hand the tool mines the blocks it is made for
+    pub fn efficiency(&self) -> f32 {
+        match self {
+            ToolTier::Wooden => 2.0,
+            ToolTier::Stone => 4.0,
+            ToolTier::Iron => 6.0,
+            ToolTier::Diamond => 8.0,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            ToolTier::Wooden => "wooden",
+            ToolTier::Stone => "stone",
+            ToolTier::Iron => "iron",
+            ToolTier::Diamond => "diamond",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Tool {
+    pub kind: ToolKind,
+    pub tier: ToolTier,
+}
+
+impl Tool {
+    pub fn new(kind: ToolKind, tier: ToolTier) -> Self {
+        Self { kind, tier }
+    }
+
+    // Like textures/items/iron_pickaxe.png
+    pub fn texture_path(&self) -> String {
+        format!("textures/items/{}_{}.png", self.tier.name(), self.kind.name())
+    }
+}
+
+// Eaten with the right button in survival, see `PlayerStats::eat`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Food {
+    Apple,
+}
+
+impl Food {
+    pub const ALL: [Food; 1] = [Food::Apple];
+
+    // In halves of the icons of the HUD, like the food of the player
+    pub fn food(&self) -> u32 {
+        match self {
+            Food::Apple => 4,
+        }
+    }
+
+    pub fn saturation(&self) -> f32 {
+        match self {
+            Food::Apple => 2.4,
+        }
+    }
+
+    pub fn texture_path(&self) -> &'static str {
+        match self {
+            Food::Apple => "textures/items/apple.png",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Item {
+    Block(BlockID),
+    Tool(Tool),
+    Food(Food),
+}
+
+impl Item {
+    pub fn max_stack_size(&self) -> u32 {
+        match self {
+            Item::Block(_) | Item::Food(_) => MAX_STACK_SIZE,
+            Item::Tool(_) => 1,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct ItemStack {
-    pub item: BlockID,
+    pub item: Item,
     pub amount: u32,
-    pub(crate) item_render: ItemRender,
 }
 
 impl ItemStack {
-    pub fn new(block: BlockID, amount: u32, uv_map: &UVMap) -> Self {
-        Self {
-            item: block,
-            amount,
-            item_render: ItemRender::new(block, uv_map),
+    pub fn new(item: Item, amount: u32) -> Self {
+        Self { item, amount }
+    }
+
+    // Created the first time the item is drawn, so stacks can be made without GL
+    pub fn item_render(&self, uv_map: &UVMap) -> ItemRender {
+        ItemRender::new(self.item, uv_map)
+    }
+
+    // Whether the items of `other` can go in this stack, when it isn't full
+    pub fn stacks_with(&self, other: &ItemStack) -> bool {
+        self.item == other.item && self.item.max_stack_size() > 1
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.amount >= self.item.max_stack_size()
+    }
+
+    // Moves as many items of `other` as fit into this stack, returns what is left of it
+    pub fn merge(&mut self, mut other: ItemStack) -> Option<ItemStack> {
+        if self.stacks_with(&other) {
+            let moved = other
+                .amount
+                .min(self.item.max_stack_size().saturating_sub(self.amount));
+
+            self.amount += moved;
+            other.amount -= moved;
         }
+
+        (other.amount > 0).then_some(other)
+    }
+
+    // Takes `amount` items off the stack, or all of them when there are less
+    // None when nothing was taken, the stack is left empty when everything was
+    pub fn split(&mut self, amount: u32) -> Option<ItemStack> {
+        let taken = amount.min(self.amount);
+        self.amount -= taken;
+
+        (taken > 0).then_some(ItemStack {
+            amount: taken,
+            ..*self
+        })
     }
 }
 
 #[derive(Copy, Clone)]
 pub struct ItemRender {
     vao: u32,
+    // Blocks are cubes textured from the atlas, tools and food are flat icons with a texture
+    icon_texture: Option<u32>,
     projection_matrix: Mat4,
 }
 
 impl ItemRender {
-    pub fn new(block: BlockID, uv_map: &UVMap) -> Self {
+    pub fn new(item: Item, uv_map: &UVMap) -> Self {
+        let cached = ITEM_RENDERS.with(|renders| renders.borrow().get(&item).copied());
+
+        cached.unwrap_or_else(|| {
+            let item_render = ItemRender::create(item, uv_map);
+            ITEM_RENDERS.with(|renders| renders.borrow_mut().insert(item, item_render));
+            item_render
+        })
+    }
+
+    fn create(item: Item, uv_map: &UVMap) -> Self {
+        // create_texture flips the images, so v goes up
+        // The icon faces the screen but it is lit like the top of the blocks
+        let icon = |path: &str| {
+            let vbo_data = quad((0.0, 1.0, 1.0, 0.0))
+                .chunks(5)
+                .flat_map(|vertex| vertex.iter().copied().chain([0.0, 1.0, 0.0]))
+                .collect();
+
+            (vbo_data, Some(create_texture(path)))
+        };
+
+        let (vbo_data, icon_texture) = match item {
+            Item::Block(block) => (
+                centered_unit_cube(
+                    -0.5,
+                    -0.5,
+                    -0.5,
+                    uv_map.get(&block).unwrap().get_uv_of_every_faces(),
+                ),
+                None,
+            ),
+            Item::Tool(tool) => icon(&tool.texture_path()),
+            Item::Food(food) => icon(food.texture_path()),
+        };
+
         let mut vao = 0;
         gl_call!(gl::CreateVertexArrays(1, &mut vao));
 
@@ -74,13 +280,6 @@ impl ItemRender {
         let mut vbo = 0;
         gl_call!(gl::CreateBuffers(1, &mut vbo));
 
-        let vbo_data = centered_unit_cube(
-            -0.5,
-            -0.5,
-            -0.5,
-            uv_map.get(&block).unwrap().get_uv_of_every_faces(),
-        );
-
         gl_call!(gl::NamedBufferData(
             vbo,
             (vbo_data.len() * std::mem::size_of::<f32>()) as isize,
@@ -106,33 +305,136 @@ impl ItemRender {
 
         Self {
             vao,
+            icon_texture,
             projection_matrix,
         }
     }
 
     pub fn draw(&self, x: f32, y: f32, shader: &mut ShaderProgram) {
-        let model_matrix = {
-            let translate_matrix = Matrix4::new_translation(&Vector3::new(x, y, 0.0));
-            let rotate_matrix = {
-                let rotate_y = Matrix::from_euler_angles(0.0, pi::<f32>() / 4.0, 0.0); // 45 degrees
-                let rotate_x = Matrix::from_euler_angles(pi::<f32>() / 6.0, 0.0, 0.0); // 30 degrees
-
-                rotate_y * rotate_x
-            };
-            let scale_matrix =
-                Matrix4::new_nonuniform_scaling(&(GUI_SCALE * vec3(10.0, 10.0, 10.0)));
-
-            translate_matrix * rotate_matrix * scale_matrix
+        let translate_matrix = Matrix4::new_translation(&Vector3::new(x, y, 0.0));
+        let model_matrix = match self.icon_texture {
+            // As big as the slot
+            Some(_) => translate_matrix * Matrix4::new_scaling(GUI_SCALE * 16.0),
+            None => {
+                // 45 degrees around y and 30 degrees around x
+                let rotate_matrix = {
+                    let rotate_y = Matrix::from_euler_angles(0.0, pi::<f32>() / 4.0, 0.0);
+                    let rotate_x = Matrix::from_euler_angles(pi::<f32>() / 6.0, 0.0, 0.0);
+
+                    rotate_y * rotate_x
+                };
+                let scale_matrix =
+                    Matrix4::new_nonuniform_scaling(&(GUI_SCALE * vec3(10.0, 10.0, 10.0)));
+
+                translate_matrix * rotate_matrix * scale_matrix
+            }
         };
 
+        self.draw_with(&model_matrix, &self.projection_matrix, shader);
+    }
+
+    // Draws the item lying in the world, the cube of a block is 1 wide and an icon 1 high
+    pub fn draw_in_world(
+        &self,
+        model_matrix: &Mat4,
+        view_projection: &Mat4,
+        shader: &mut ShaderProgram,
+    ) {
+        self.draw_with(model_matrix, view_projection, shader);
+    }
+
+    fn draw_with(&self, model_matrix: &Mat4, projection_matrix: &Mat4, shader: &mut ShaderProgram) {
         shader.use_program();
         unsafe {
             shader.set_uniform_matrix4fv("model", model_matrix.as_ptr());
-            shader.set_uniform_matrix4fv("projection", self.projection_matrix.as_ptr());
+            shader.set_uniform_matrix4fv("projection", projection_matrix.as_ptr());
         }
-        shader.set_uniform1i("tex", 0);
+
+        let vertex_count = match self.icon_texture {
+            Some(texture) => {
+                gl_call!(gl::BindTextureUnit(ICON_TEXTURE_UNIT, texture));
+                shader.set_uniform1i("tex", ICON_TEXTURE_UNIT as i32);
+                6
+            }
+            None => {
+                shader.set_uniform1i("tex", 0);
+                36
+            }
+        };
 
         gl_call!(gl::BindVertexArray(self.vao));
-        gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, 36));
+        gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, vertex_count));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_registry::install_test_registry;
+
+    fn stone(amount: u32) -> ItemStack {
+        install_test_registry();
+        ItemStack::new(Item::Block(BlockID::named("stone")), amount)
+    }
+
+    fn pickaxe() -> ItemStack {
+        ItemStack::new(Item::Tool(Tool::new(ToolKind::Pickaxe, ToolTier::Iron)), 1)
+    }
+
+    #[test]
+    fn merge_returns_what_overflows() {
+        let mut stack = stone(60);
+
+        assert_eq!(stack.merge(stone(10)), Some(stone(6)));
+        assert_eq!(stack, stone(MAX_STACK_SIZE));
+        assert!(stack.is_full());
+        assert_eq!(stack.merge(stone(1)), Some(stone(1)));
+    }
+
+    #[test]
+    fn merge_takes_everything_that_fits() {
+        let mut stack = stone(10);
+
+        assert_eq!(stack.merge(stone(54)), None);
+        assert_eq!(stack, stone(MAX_STACK_SIZE));
+    }
+
+    #[test]
+    fn merge_of_a_different_item_gives_it_back() {
+        install_test_registry();
+        let mut stack = stone(10);
+        let dirt = ItemStack::new(Item::Block(BlockID::named("dirt")), 5);
+
+        assert_eq!(stack.merge(dirt), Some(dirt));
+        assert_eq!(stack, stone(10));
+    }
+
+    #[test]
+    fn tools_never_stack() {
+        let mut tool = pickaxe();
+
+        assert!(!tool.stacks_with(&pickaxe()));
+        assert!(tool.is_full());
+        assert_eq!(tool.merge(pickaxe()), Some(pickaxe()));
+        assert_eq!(tool, pickaxe());
+    }
+
+    #[test]
+    fn split_takes_the_whole_stack_when_asked_for_more() {
+        let mut stack = stone(10);
+
+        assert_eq!(stack.split(100), Some(stone(10)));
+        assert_eq!(stack.amount, 0);
+        assert_eq!(stack.split(1), None);
+    }
+
+    #[test]
+    fn split_leaves_the_rest() {
+        let mut stack = stone(10);
+
+        assert_eq!(stack.split(3), Some(stone(3)));
+        assert_eq!(stack, stone(7));
+        assert_eq!(stack.split(0), None);
+        assert_eq!(stack, stone(7));
     }
 }
@@ -1,13 +1,16 @@
 pub mod item;
+pub mod screen;
+
+use std::ops::Range;
 
 use glfw::WindowEvent;
 
-use crate::chunk::BlockID;
-use crate::inventory::item::ItemStack;
-use crate::types::UVMap;
+use crate::block_registry::block_registry;
+use crate::inventory::item::{Food, Item, ItemStack, Tool, ToolKind, ToolTier};
 
-const INVENTORY_SIZE: usize = 36;
-const HOTBAR_SIZE: usize = 9;
+pub const INVENTORY_SIZE: usize = 36;
+// The first slots of the inventory
+pub const HOTBAR_SIZE: usize = 9;
 
 pub struct Inventory {
     pub slots: [Option<ItemStack>; INVENTORY_SIZE],
@@ -15,19 +18,24 @@ pub struct Inventory {
 }
 
 impl Inventory {
-    pub fn new(uv_map: &UVMap) -> Self {
+    pub fn new() -> Self {
         Self {
             slots: {
                 let mut slots = [None; INVENTORY_SIZE];
-                slots[0] = Some(ItemStack::new(BlockID::Dirt, 1, uv_map));
-                slots[1] = Some(ItemStack::new(BlockID::Grass, 1, uv_map));
-                slots[2] = Some(ItemStack::new(BlockID::Cobblestone, 1, uv_map));
-                slots[3] = Some(ItemStack::new(BlockID::OakLog, 1, uv_map));
-                slots[4] = Some(ItemStack::new(BlockID::OakPlanks, 1, uv_map));
-                slots[5] = Some(ItemStack::new(BlockID::OakLeaves, 1, uv_map));
-                slots[6] = Some(ItemStack::new(BlockID::Glass, 1, uv_map));
-                slots[7] = Some(ItemStack::new(BlockID::Obsidian, 1, uv_map));
-                slots[8] = Some(ItemStack::new(BlockID::Debug, 1, uv_map));
+
+                // Iron tools, food, then a stack of every block in the order of the block registry
+                let tools = ToolKind::ALL
+                    .iter()
+                    .map(|&kind| Item::Tool(Tool::new(kind, ToolTier::Iron)));
+                let food = Food::ALL.iter().map(|&food| Item::Food(food));
+                let blocks = block_registry()
+                    .blocks()
+                    .filter(|block| block.in_inventory)
+                    .map(|block| Item::Block(block.id));
+
+                for (slot, item) in slots.iter_mut().zip(tools.chain(food).chain(blocks)) {
+                    *slot = Some(ItemStack::new(item, item.max_stack_size()));
+                }
 
                 slots
             },
@@ -35,10 +43,107 @@ impl Inventory {
         }
     }
 
-    pub fn get_selected_item(&self) -> Option<BlockID> {
+    pub fn get_selected_item(&self) -> Option<Item> {
         self.slots[self.selected_hotbar_slot].map(|item_stack| item_stack.item)
     }
 
+    // None when the player mines by hand
+    pub fn get_selected_tool(&self) -> Option<Tool> {
+        match self.get_selected_item() {
+            Some(Item::Tool(tool)) => Some(tool),
+            _ => None,
+        }
+    }
+
+    // Fills the stacks of the same item first, then the empty slots, returns what doesn't fit
+    pub fn add_stack(&mut self, stack: ItemStack) -> Option<ItemStack> {
+        self.add_stack_in(0..INVENTORY_SIZE, stack)
+    }
+
+    // Same as `add_stack`, only using the slots in `range`
+    pub fn add_stack_in(&mut self, range: Range<usize>, mut stack: ItemStack) -> Option<ItemStack> {
+        for slot in self.slots[range.clone()].iter_mut().flatten() {
+            stack = slot.merge(stack)?;
+        }
+
+        for slot in self.slots[range].iter_mut().filter(|slot| slot.is_none()) {
+            *slot = stack.split(stack.item.max_stack_size());
+
+            if stack.amount == 0 {
+                return None;
+            }
+        }
+
+        Some(stack)
+    }
+
+    // Merges the stack into the slot, or puts it there when it is empty, returns what doesn't fit
+    pub fn merge_into_slot(&mut self, slot: usize, mut stack: ItemStack) -> Option<ItemStack> {
+        match &mut self.slots[slot] {
+            Some(slot_stack) => slot_stack.merge(stack),
+            None => {
+                self.slots[slot] = stack.split(stack.item.max_stack_size());
+                (stack.amount > 0).then_some(stack)
+            }
+        }
+    }
+
+    // Takes up to `amount` items from the slot, it is emptied with its last item
+    pub fn remove_from_slot(&mut self, slot: usize, amount: u32) -> Option<ItemStack> {
+        let stack = self.slots[slot].as_mut()?;
+        let taken = stack.split(amount);
+
+        if stack.amount == 0 {
+            self.slots[slot] = None;
+        }
+
+        taken
+    }
+
+    // Takes `amount` of the item from anywhere in the inventory, returns how many were missing
+    pub fn remove_item(&mut self, item: Item, amount: u32) -> u32 {
+        let mut missing = amount;
+
+        for slot in 0..INVENTORY_SIZE {
+            if missing == 0 {
+                break;
+            }
+
+            if self.slots[slot].map_or(false, |stack| stack.item == item) {
+                if let Some(taken) = self.remove_from_slot(slot, missing) {
+                    missing -= taken.amount;
+                }
+            }
+        }
+
+        missing
+    }
+
+    // Moves the stack from the hotbar to the rest of the inventory or the other way around
+    // What doesn't fit stays in the slot
+    pub fn quick_move(&mut self, slot: usize) {
+        let range = if slot < HOTBAR_SIZE {
+            HOTBAR_SIZE..INVENTORY_SIZE
+        } else {
+            0..HOTBAR_SIZE
+        };
+
+        if let Some(stack) = self.slots[slot].take() {
+            self.slots[slot] = self.add_stack_in(range, stack);
+        }
+    }
+
+    // Takes the bigger half of the stack in the slot, a single item is taken whole
+    pub fn split_slot(&mut self, slot: usize) -> Option<ItemStack> {
+        let half = self.slots[slot]?.amount.div_ceil(2);
+        self.remove_from_slot(slot, half)
+    }
+
+    // Empties the selected slot of the hotbar
+    pub fn take_selected_stack(&mut self) -> Option<ItemStack> {
+        self.slots[self.selected_hotbar_slot].take()
+    }
+
     pub fn handle_input_event(&mut self, event: &WindowEvent) {
         use glfw::{Action, Key};
 
@@ -82,3 +187,173 @@ impl Inventory {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_registry::install_test_registry;
+    use crate::chunk::BlockID;
+    use crate::inventory::item::MAX_STACK_SIZE;
+
+    fn empty() -> Inventory {
+        install_test_registry();
+        Inventory {
+            slots: [None; INVENTORY_SIZE],
+            selected_hotbar_slot: 0,
+        }
+    }
+
+    fn block(name: &str, amount: u32) -> ItemStack {
+        ItemStack::new(Item::Block(BlockID::named(name)), amount)
+    }
+
+    fn stone(amount: u32) -> ItemStack {
+        block("stone", amount)
+    }
+
+    fn pickaxe() -> ItemStack {
+        ItemStack::new(Item::Tool(Tool::new(ToolKind::Pickaxe, ToolTier::Iron)), 1)
+    }
+
+    #[test]
+    fn add_stack_fills_the_stacks_of_the_item_first() {
+        let mut inventory = empty();
+        inventory.slots[3] = Some(stone(60));
+        inventory.slots[5] = Some(block("dirt", 60));
+
+        assert_eq!(inventory.add_stack(stone(70)), None);
+        assert_eq!(inventory.slots[3], Some(stone(MAX_STACK_SIZE)));
+        assert_eq!(inventory.slots[5], Some(block("dirt", 60)));
+        assert_eq!(inventory.slots[0], Some(stone(MAX_STACK_SIZE)));
+        assert_eq!(inventory.slots[1], Some(stone(2)));
+        assert_eq!(inventory.slots[2], None);
+    }
+
+    #[test]
+    fn add_stack_in_returns_what_does_not_fit_in_the_range() {
+        let mut inventory = empty();
+        inventory.slots[10] = Some(stone(1));
+
+        assert_eq!(inventory.add_stack_in(0..2, stone(200)), Some(stone(72)));
+        assert_eq!(inventory.slots[0], Some(stone(MAX_STACK_SIZE)));
+        assert_eq!(inventory.slots[1], Some(stone(MAX_STACK_SIZE)));
+        assert_eq!(inventory.slots[2], None);
+        assert_eq!(inventory.slots[10], Some(stone(1)));
+    }
+
+    #[test]
+    fn tools_take_a_slot_each() {
+        let mut inventory = empty();
+        inventory.slots[0] = Some(pickaxe());
+        let tools = ItemStack { amount: 3, ..pickaxe() };
+
+        assert_eq!(inventory.add_stack_in(0..3, tools), Some(pickaxe()));
+        assert!(inventory.slots[..3].iter().all(|slot| *slot == Some(pickaxe())));
+    }
+
+    #[test]
+    fn merge_into_slot_returns_what_overflows() {
+        let mut inventory = empty();
+
+        assert_eq!(inventory.merge_into_slot(0, stone(100)), Some(stone(36)));
+        assert_eq!(inventory.slots[0], Some(stone(MAX_STACK_SIZE)));
+
+        inventory.slots[1] = Some(stone(50));
+        assert_eq!(inventory.merge_into_slot(1, stone(20)), Some(stone(6)));
+        assert_eq!(inventory.slots[1], Some(stone(MAX_STACK_SIZE)));
+
+        assert_eq!(inventory.merge_into_slot(1, block("dirt", 5)), Some(block("dirt", 5)));
+        assert_eq!(inventory.merge_into_slot(2, stone(5)), None);
+        assert_eq!(inventory.slots[2], Some(stone(5)));
+    }
+
+    #[test]
+    fn merge_into_slot_never_stacks_tools() {
+        let mut inventory = empty();
+
+        assert_eq!(inventory.merge_into_slot(0, pickaxe()), None);
+        assert_eq!(inventory.merge_into_slot(0, pickaxe()), Some(pickaxe()));
+        assert_eq!(inventory.slots[0], Some(pickaxe()));
+    }
+
+    #[test]
+    fn remove_from_slot_empties_it_with_its_last_item() {
+        let mut inventory = empty();
+        inventory.slots[0] = Some(stone(5));
+
+        assert_eq!(inventory.remove_from_slot(0, 2), Some(stone(2)));
+        assert_eq!(inventory.slots[0], Some(stone(3)));
+        assert_eq!(inventory.remove_from_slot(0, 3), Some(stone(3)));
+        assert_eq!(inventory.slots[0], None);
+        assert_eq!(inventory.remove_from_slot(0, 1), None);
+    }
+
+    #[test]
+    fn remove_from_slot_takes_what_there_is() {
+        let mut inventory = empty();
+        inventory.slots[0] = Some(stone(5));
+
+        assert_eq!(inventory.remove_from_slot(0, 10), Some(stone(5)));
+        assert_eq!(inventory.slots[0], None);
+    }
+
+    #[test]
+    fn remove_item_takes_from_several_slots() {
+        let mut inventory = empty();
+        inventory.slots[2] = Some(stone(10));
+        inventory.slots[5] = Some(block("dirt", 10));
+        inventory.slots[20] = Some(stone(MAX_STACK_SIZE));
+
+        assert_eq!(inventory.remove_item(stone(1).item, 20), 0);
+        assert_eq!(inventory.slots[2], None);
+        assert_eq!(inventory.slots[20], Some(stone(54)));
+
+        assert_eq!(inventory.remove_item(stone(1).item, 100), 46);
+        assert_eq!(inventory.slots[20], None);
+        assert_eq!(inventory.slots[5], Some(block("dirt", 10)));
+    }
+
+    #[test]
+    fn split_slot_takes_the_bigger_half() {
+        let mut inventory = empty();
+        inventory.slots[0] = Some(stone(5));
+        inventory.slots[1] = Some(stone(MAX_STACK_SIZE));
+        inventory.slots[2] = Some(stone(1));
+
+        assert_eq!(inventory.split_slot(0), Some(stone(3)));
+        assert_eq!(inventory.slots[0], Some(stone(2)));
+        assert_eq!(inventory.split_slot(1), Some(stone(32)));
+        assert_eq!(inventory.slots[1], Some(stone(32)));
+        assert_eq!(inventory.split_slot(2), Some(stone(1)));
+        assert_eq!(inventory.slots[2], None);
+        assert_eq!(inventory.split_slot(3), None);
+    }
+
+    #[test]
+    fn quick_move_goes_between_the_hotbar_and_the_rest() {
+        let mut inventory = empty();
+        inventory.slots[0] = Some(stone(10));
+
+        inventory.quick_move(0);
+        assert_eq!(inventory.slots[0], None);
+        assert_eq!(inventory.slots[HOTBAR_SIZE], Some(stone(10)));
+
+        inventory.quick_move(HOTBAR_SIZE);
+        assert_eq!(inventory.slots[HOTBAR_SIZE], None);
+        assert_eq!(inventory.slots[0], Some(stone(10)));
+    }
+
+    #[test]
+    fn quick_move_leaves_what_does_not_fit() {
+        let mut inventory = empty();
+        for slot in HOTBAR_SIZE..INVENTORY_SIZE {
+            inventory.slots[slot] = Some(block("dirt", 1));
+        }
+        inventory.slots[30] = Some(stone(60));
+        inventory.slots[0] = Some(stone(10));
+
+        inventory.quick_move(0);
+        assert_eq!(inventory.slots[30], Some(stone(MAX_STACK_SIZE)));
+        assert_eq!(inventory.slots[0], Some(stone(6)));
+    }
+}
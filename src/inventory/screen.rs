@@ -0,0 +1,233 @@
+use crate::constants::{GUI_SCALE, WINDOW_HEIGHT, WINDOW_WIDTH};
+use crate::inventory::item::ItemStack;
+use crate::inventory::{Inventory, HOTBAR_SIZE, INVENTORY_SIZE};
+use glfw::{Action, Modifiers, MouseButton, WindowEvent};
+
+/*
+    Screen showing the 36 slots of the inventory, E opens and closes it and the cursor is free while
+    it is open. The slots are laid out like in textures/gui/inventory.png, 18 pixels apart :
+
+         9 10 11 12 13 14 15 16 17
+        18 19 20 21 22 23 24 25 26
+        27 28 29 30 31 32 33 34 35
+
+         0  1  2  3  4  5  6  7  8      the hotbar, a bit lower than the rest
+
+    Left click      takes the stack, or puts the held stack down (swapped with a different item)
+    Right click     takes the bigger half of the stack, or puts one held item down
+    Shift + left    moves the stack between the hotbar and the rest of the inventory
+
+    Dragging the held stack over several slots spreads it evenly between them with the left button,
+    and puts one item in each of them with the right button. What is left stays on the cursor
+*/
+
+// Size of inventory.png, in pixels
+pub const PANEL_WIDTH: f32 = 176.0;
+pub const PANEL_HEIGHT: f32 = 90.0;
+
+const SLOT_SIZE: f32 = 18.0;
+// Top left corner of the first slot, and top of the hotbar
+const SLOTS_MARGIN: f32 = 7.0;
+const HOTBAR_TOP: f32 = 65.0;
+
+// Slots the held stack went over while a button was down
+struct Drag {
+    button: MouseButton,
+    slots: Vec<usize>,
+}
+
+#[derive(Default)]
+pub struct InventoryScreen {
+    pub is_open: bool,
+    // The stack on the cursor
+    pub held: Option<ItemStack>,
+    // In pixels of the window from its top left corner, like the cursor events
+    pub cursor: (f32, f32),
+    drag: Option<Drag>,
+}
+
+// Top left corner of the slot, in pixels of inventory.png
+pub fn slot_position(slot: usize) -> (f32, f32) {
+    let x = SLOTS_MARGIN + (slot % HOTBAR_SIZE) as f32 * SLOT_SIZE;
+    let y = if slot < HOTBAR_SIZE {
+        HOTBAR_TOP
+    } else {
+        SLOTS_MARGIN + (slot / HOTBAR_SIZE - 1) as f32 * SLOT_SIZE
+    };
+
+    (x, y)
+}
+
+// Top left corner of the panel, centered in the window
+pub fn panel_position() -> (f32, f32) {
+    (
+        (WINDOW_WIDTH as f32 - PANEL_WIDTH * GUI_SCALE) / 2.0,
+        (WINDOW_HEIGHT as f32 - PANEL_HEIGHT * GUI_SCALE) / 2.0,
+    )
+}
+
+// Slot under the point of the window, None between the slots and outside of the panel
+pub fn slot_at((x, y): (f32, f32)) -> Option<usize> {
+    let (left, top) = panel_position();
+    let (x, y) = ((x - left) / GUI_SCALE, (y - top) / GUI_SCALE);
+
+    (0..INVENTORY_SIZE).find(|&slot| {
+        let (slot_x, slot_y) = slot_position(slot);
+        (slot_x..slot_x + SLOT_SIZE).contains(&x) && (slot_y..slot_y + SLOT_SIZE).contains(&y)
+    })
+}
+
+impl InventoryScreen {
+    pub fn open(&mut self, cursor: (f32, f32)) {
+        self.is_open = true;
+        self.cursor = cursor;
+    }
+
+    // The held stack goes back into the inventory, returns what doesn't fit so it can be thrown
+    pub fn close(&mut self, inventory: &mut Inventory) -> Option<ItemStack> {
+        self.is_open = false;
+        self.drag = None;
+        self.held
+            .take()
+            .and_then(|stack| inventory.add_stack(stack))
+    }
+
+    pub fn handle_input_event(&mut self, event: &WindowEvent, inventory: &mut Inventory) {
+        match *event {
+            WindowEvent::CursorPos(x, y) => self.move_cursor((x as f32, y as f32), inventory),
+            WindowEvent::MouseButton(button, Action::Press, modifiers) => {
+                if let Some(slot) = slot_at(self.cursor) {
+                    let shift = modifiers.contains(Modifiers::Shift);
+                    self.press(button, shift, slot, inventory);
+                }
+            }
+            WindowEvent::MouseButton(button, Action::Release, _) => {
+                self.release(button, inventory);
+            }
+            _ => {}
+        }
+    }
+
+    pub fn move_cursor(&mut self, cursor: (f32, f32), inventory: &Inventory) {
+        self.cursor = cursor;
+
+        let (drag, held, slot) = match (&mut self.drag, &self.held, slot_at(cursor)) {
+            (Some(drag), Some(held), Some(slot)) => (drag, held, slot),
+            _ => return,
+        };
+
+        // Every slot of the drag gets at least one item
+        let fits = match inventory.slots[slot] {
+            Some(stack) => stack.stacks_with(held) && !stack.is_full(),
+            None => true,
+        };
+        if fits && !drag.slots.contains(&slot) && drag.slots.len() < held.amount as usize {
+            drag.slots.push(slot);
+        }
+    }
+
+    // Clicks with a held stack wait for the button to be released, it may become a drag
+    pub fn press(
+        &mut self,
+        button: MouseButton,
+        shift: bool,
+        slot: usize,
+        inventory: &mut Inventory,
+    ) {
+        if self.held.is_some() {
+            if self.drag.is_none() {
+                self.drag = Some(Drag {
+                    button,
+                    slots: vec![slot],
+                });
+            }
+            return;
+        }
+
+        match button {
+            MouseButton::Button1 if shift => inventory.quick_move(slot),
+            MouseButton::Button1 => self.held = inventory.slots[slot].take(),
+            MouseButton::Button2 => self.held = inventory.split_slot(slot),
+            _ => {}
+        }
+    }
+
+    pub fn release(&mut self, button: MouseButton, inventory: &mut Inventory) {
+        let drag = match self.drag.take() {
+            Some(drag) if drag.button == button => drag,
+            other => {
+                self.drag = other;
+                return;
+            }
+        };
+
+        match (button, drag.slots.as_slice()) {
+            (MouseButton::Button1, &[slot]) => self.put_down(slot, inventory),
+            (MouseButton::Button2, &[slot]) => self.put_one(slot, inventory),
+            _ => self.spread(button, &drag.slots, inventory),
+        }
+    }
+
+    fn put_down(&mut self, slot: usize, inventory: &mut Inventory) {
+        let held = match self.held.take() {
+            Some(held) => held,
+            None => return,
+        };
+
+        self.held = match inventory.slots[slot] {
+            Some(stack) if !stack.stacks_with(&held) => inventory.slots[slot].replace(held),
+            _ => inventory.merge_into_slot(slot, held),
+        };
+    }
+
+    fn put_one(&mut self, slot: usize, inventory: &mut Inventory) {
+        let mut held = match self.held.take() {
+            Some(held) => held,
+            None => return,
+        };
+
+        if inventory.slots[slot].map_or(false, |stack| !stack.stacks_with(&held)) {
+            self.held = inventory.slots[slot].replace(held);
+            return;
+        }
+
+        if let Some(one) = held.split(1) {
+            if let Some(back) = inventory.merge_into_slot(slot, one) {
+                held.merge(back);
+            }
+        }
+        self.held = (held.amount > 0).then_some(held);
+    }
+
+    // The left button spreads the held stack evenly, the right button puts one item per slot
+    fn spread(&mut self, button: MouseButton, slots: &[usize], inventory: &mut Inventory) {
+        let mut held = match self.held.take() {
+            Some(held) => held,
+            None => return,
+        };
+
+        // The slot the drag started on may hold a different item
+        let slots: Vec<usize> = slots
+            .iter()
+            .copied()
+            .filter(|&slot| inventory.slots[slot].map_or(true, |stack| stack.stacks_with(&held)))
+            .collect();
+
+        if !slots.is_empty() {
+            let per_slot = match button {
+                MouseButton::Button2 => 1,
+                _ => held.amount / slots.len() as u32,
+            };
+
+            for slot in slots {
+                if let Some(part) = held.split(per_slot) {
+                    if let Some(back) = inventory.merge_into_slot(slot, part) {
+                        held.merge(back);
+                    }
+                }
+            }
+        }
+
+        self.held = (held.amount > 0).then_some(held);
+    }
+}
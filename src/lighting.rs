@@ -0,0 +1,473 @@
+use crate::block_registry::MAX_LIGHT_LEVEL;
+use crate::chunk::{BlockID, BlockIterator};
+use crate::chunk_manager::{ChunkManager, CHUNK_SIZE};
+use crate::constants::WORLD_HEIGHT_IN_CHUNKS;
+use std::collections::VecDeque;
+
+/*
+    Light is stored per block as 2 levels from 0 to 15 :
+        sky light   : 15 under the open sky, goes down without getting weaker and loses 1 per block sideways
+        block light : emitted by blocks like glowstone, loses 1 per block in every direction
+
+    Light spreads with a flood fill through transparent blocks. When a block changes, the light that
+    came through it is removed with a second flood fill and the surrounding light fills the hole again
+    The flood fills cross chunk borders as long as the other chunk is loaded
+*/
+
+type BlockCoords = (i32, i32, i32);
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum LightChannel {
+    Sky,
+    Block,
+}
+
+const DIRECTIONS: [BlockCoords; 6] = [
+    (1, 0, 0),
+    (-1, 0, 0),
+    (0, 1, 0),
+    (0, -1, 0),
+    (0, 0, 1),
+    (0, 0, -1),
+];
+
+const DOWN: BlockCoords = (0, -1, 0);
+
+fn offset((x, y, z): BlockCoords, (dx, dy, dz): BlockCoords) -> BlockCoords {
+    (x + dx, y + dy, z + dz)
+}
+
+// Light that a block lit at `level` gives to its neighbour in `direction`
+fn spread_light(channel: LightChannel, level: u8, direction: BlockCoords) -> u8 {
+    if channel == LightChannel::Sky && direction == DOWN && level == MAX_LIGHT_LEVEL {
+        MAX_LIGHT_LEVEL
+    } else {
+        level.saturating_sub(1)
+    }
+}
+
+impl ChunkManager {
+    pub fn get_light(&self, position: BlockCoords, channel: LightChannel) -> Option<u8> {
+        self.get_block_and_light(position, channel)
+            .map(|(_, level)| level)
+    }
+
+    // Both with a single chunk lookup, the flood fills spend most of their time looking up chunks
+    fn get_block_and_light(
+        &self,
+        (x, y, z): BlockCoords,
+        channel: LightChannel,
+    ) -> Option<(BlockID, u8)> {
+        let (chunk_x, chunk_y, chunk_z, block_x, block_y, block_z) =
+            ChunkManager::get_chunk_coords(x, y, z);
+
+        self.loaded_chunks
+            .get(&(chunk_x, chunk_y, chunk_z))
+            .map(|chunk| {
+                let level = match channel {
+                    LightChannel::Sky => chunk.get_sky_light(block_x, block_y, block_z),
+                    LightChannel::Block => chunk.get_block_light(block_x, block_y, block_z),
+                };
+
+                (chunk.get_block(block_x, block_y, block_z), level)
+            })
+    }
+
+    fn set_light(&mut self, (x, y, z): BlockCoords, channel: LightChannel, level: u8) {
+        let (chunk_x, chunk_y, chunk_z, block_x, block_y, block_z) =
+            ChunkManager::get_chunk_coords(x, y, z);
+
+        if let Some(chunk) = self.loaded_chunks.get_mut(&(chunk_x, chunk_y, chunk_z)) {
+            match channel {
+                LightChannel::Sky => chunk.set_sky_light(block_x, block_y, block_z, level),
+                LightChannel::Block => chunk.set_block_light(block_x, block_y, block_z, level),
+            }
+        }
+    }
+
+    // Spreads the light of every queued block to its neighbours, and theirs, until it fades out
+    fn propagate_light(&mut self, channel: LightChannel, mut queue: VecDeque<BlockCoords>) {
+        while let Some(position) = queue.pop_front() {
+            let level = self.get_light(position, channel).unwrap_or(0);
+
+            if level <= 1 {
+                continue;
+            }
+
+            for direction in DIRECTIONS {
+                let neighbour = offset(position, direction);
+                let new_level = spread_light(channel, level, direction);
+
+                let (block, neighbour_level) = match self.get_block_and_light(neighbour, channel) {
+                    Some(block_and_light) => block_and_light,
+                    None => continue,
+                };
+
+                if block.is_transparent() && neighbour_level < new_level {
+                    self.set_light(neighbour, channel, new_level);
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+    }
+
+    // Removes the light that came from the queued blocks, `queue` holds the level they had
+    // Returns the blocks that are lit by another source, they need to spread their light again
+    fn unpropagate_light(
+        &mut self,
+        channel: LightChannel,
+        mut queue: VecDeque<(BlockCoords, u8)>,
+    ) -> VecDeque<BlockCoords> {
+        let mut refill = VecDeque::new();
+
+        while let Some((position, level)) = queue.pop_front() {
+            for direction in DIRECTIONS {
+                let neighbour = offset(position, direction);
+                let neighbour_level = match self.get_light(neighbour, channel) {
+                    Some(0) | None => continue,
+                    Some(neighbour_level) => neighbour_level,
+                };
+
+                // Sky light going straight down keeps its level, so it can't be told apart by its level only
+                let lit_by_position = neighbour_level < level
+                    || channel == LightChannel::Sky
+                        && direction == DOWN
+                        && level == MAX_LIGHT_LEVEL
+                        && neighbour_level == MAX_LIGHT_LEVEL;
+
+                if lit_by_position {
+                    self.set_light(neighbour, channel, 0);
+                    queue.push_back((neighbour, neighbour_level));
+
+                    // A light source stays lit
+                    let emission = self.block_light_emission(neighbour);
+                    if channel == LightChannel::Block && emission > 0 {
+                        self.set_light(neighbour, channel, emission);
+                        refill.push_back(neighbour);
+                    }
+                } else {
+                    refill.push_back(neighbour);
+                }
+            }
+        }
+
+        refill
+    }
+
+    fn block_light_emission(&self, (x, y, z): BlockCoords) -> u8 {
+        self.get_block(x, y, z).map_or(0, |block| block.light_emission())
+    }
+
+    // Updates the light around a block that was replaced by `ChunkManager::set_block`
+    pub(crate) fn update_light_at(&mut self, position: BlockCoords, block: BlockID) {
+        let sky_level = self.get_light(position, LightChannel::Sky).unwrap_or(0);
+        let block_level = self.get_light(position, LightChannel::Block).unwrap_or(0);
+        self.set_light(position, LightChannel::Sky, 0);
+        self.set_light(position, LightChannel::Block, 0);
+
+        let mut sky_refill =
+            self.unpropagate_light(LightChannel::Sky, VecDeque::from([(position, sky_level)]));
+        let mut block_refill =
+            self.unpropagate_light(LightChannel::Block, VecDeque::from([(position, block_level)]));
+
+        // The neighbours spread their light into the block if it lets it through
+        if block.is_transparent() {
+            for direction in DIRECTIONS {
+                sky_refill.push_back(offset(position, direction));
+                block_refill.push_back(offset(position, direction));
+            }
+        }
+
+        if block.light_emission() > 0 {
+            self.set_light(position, LightChannel::Block, block.light_emission());
+            block_refill.push_back(position);
+        }
+
+        self.propagate_light(LightChannel::Sky, sky_refill);
+        self.propagate_light(LightChannel::Block, block_refill);
+    }
+
+    // Chunks are lit from the top of the world down, a chunk is only lit once the one above it is
+    // Lighting them in any order would make the sky light flood every chunk below again each time
+    pub(crate) fn light_pending_chunks(&mut self) {
+        loop {
+            let mut ready: Vec<_> = self
+                .unlit_chunks
+                .iter()
+                .filter(|&&(x, y, z)| {
+                    y >= WORLD_HEIGHT_IN_CHUNKS - 1
+                        || self.loaded_chunks.contains_key(&(x, y + 1, z))
+                            && !self.unlit_chunks.contains(&(x, y + 1, z))
+                })
+                .copied()
+                .collect();
+
+            if ready.is_empty() {
+                break;
+            }
+
+            ready.sort_unstable_by_key(|&(_, y, _)| std::cmp::Reverse(y));
+
+            for coords in ready {
+                self.unlit_chunks.remove(&coords);
+                self.light_new_chunk(coords);
+            }
+        }
+    }
+
+    // Lights a chunk that was just loaded and lets the light of its neighbours flow into it
+    fn light_new_chunk(&mut self, coords: (i32, i32, i32)) {
+        let (cx, cy, cz) = coords;
+        let size = CHUNK_SIZE as i32;
+        let origin = (cx * size, cy * size, cz * size);
+
+        let mut sky_queue = VecDeque::new();
+        let mut block_queue = VecDeque::new();
+
+        let above = self.loaded_chunks.get(&(cx, cy + 1, cz)).map(|chunk| {
+            let mut bottom = [[0; CHUNK_SIZE as usize]; CHUNK_SIZE as usize];
+            for (x, row) in bottom.iter_mut().enumerate() {
+                for (z, level) in row.iter_mut().enumerate() {
+                    *level = chunk.get_sky_light(x as u32, 0, z as u32);
+                }
+            }
+            bottom
+        });
+
+        let chunk = self.loaded_chunks.get_mut(&coords).unwrap();
+
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                // The top of the world is always under the open sky
+                let incoming = match above {
+                    _ if cy == WORLD_HEIGHT_IN_CHUNKS - 1 => MAX_LIGHT_LEVEL,
+                    Some(bottom) => bottom[x as usize][z as usize],
+                    None => 0,
+                };
+
+                if incoming == MAX_LIGHT_LEVEL {
+                    for y in (0..CHUNK_SIZE).rev() {
+                        if !chunk.get_block(x, y, z).is_transparent() {
+                            break;
+                        }
+
+                        chunk.set_sky_light(x, y, z, MAX_LIGHT_LEVEL);
+                    }
+                }
+
+                for y in 0..CHUNK_SIZE {
+                    let emission = chunk.get_block(x, y, z).light_emission();
+
+                    if emission > 0 {
+                        chunk.set_block_light(x, y, z, emission);
+                        block_queue.push_back(offset(origin, (x as i32, y as i32, z as i32)));
+                    }
+                }
+            }
+        }
+
+        // Only the sky lit blocks that can light something else are queued,
+        // in the open air most of the chunk is surrounded by blocks that are already fully lit
+        let max = CHUNK_SIZE - 1;
+        let can_spread = |x: u32, y: u32, z: u32| {
+            if x == 0 || x == max || y == 0 || z == 0 || z == max {
+                return true;
+            }
+
+            [(x + 1, z), (x - 1, z), (x, z + 1), (x, z - 1)]
+                .into_iter()
+                .any(|(nx, nz)| {
+                    chunk.get_block(nx, y, nz).is_transparent()
+                        && chunk.get_sky_light(nx, y, nz) < MAX_LIGHT_LEVEL
+                })
+        };
+
+        for (x, y, z) in BlockIterator::new() {
+            if chunk.get_sky_light(x, y, z) == MAX_LIGHT_LEVEL && can_spread(x, y, z) {
+                sky_queue.push_back(offset(origin, (x as i32, y as i32, z as i32)));
+            }
+        }
+
+        // The blocks on the faces of the neighbours spread their light into this chunk
+        for direction in DIRECTIONS {
+            let neighbour_coords = offset(coords, direction);
+            let neighbour = match self.loaded_chunks.get(&neighbour_coords) {
+                Some(neighbour) => neighbour,
+                None => continue,
+            };
+
+            let neighbour_origin = (
+                neighbour_coords.0 * size,
+                neighbour_coords.1 * size,
+                neighbour_coords.2 * size,
+            );
+
+            // Coordinates of the layer of the neighbour that touches this chunk
+            let layer = |i: u32, j: u32| match direction {
+                (1, 0, 0) => (0, i, j),
+                (-1, 0, 0) => (CHUNK_SIZE - 1, i, j),
+                (0, 1, 0) => (i, 0, j),
+                (0, -1, 0) => (i, CHUNK_SIZE - 1, j),
+                (0, 0, 1) => (i, j, 0),
+                _ => (i, j, CHUNK_SIZE - 1),
+            };
+
+            for i in 0..CHUNK_SIZE {
+                for j in 0..CHUNK_SIZE {
+                    let (x, y, z) = layer(i, j);
+                    let position = offset(neighbour_origin, (x as i32, y as i32, z as i32));
+
+                    if neighbour.get_sky_light(x, y, z) > 1 {
+                        sky_queue.push_back(position);
+                    }
+                    if neighbour.get_block_light(x, y, z) > 1 {
+                        block_queue.push_back(position);
+                    }
+                }
+            }
+        }
+
+        self.propagate_light(LightChannel::Sky, sky_queue);
+        self.propagate_light(LightChannel::Block, block_queue);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_registry::install_test_registry;
+    use crate::chunk::Chunk;
+    use crate::types::UVMap;
+    use crate::world_generator::TerrainGenerator;
+    use std::sync::Arc;
+
+    const TOP: i32 = WORLD_HEIGHT_IN_CHUNKS - 1;
+    const SIZE: i32 = CHUNK_SIZE as i32;
+
+    // Empty and unlit chunks around the origin, from `min_y` to `max_y` in chunks
+    fn world(min_y: i32, max_y: i32) -> ChunkManager {
+        install_test_registry();
+
+        let mut chunk_manager =
+            ChunkManager::new(Arc::new(TerrainGenerator::new(1)), &UVMap::default());
+        for x in -1..=1 {
+            for y in min_y..=max_y {
+                for z in -1..=1 {
+                    chunk_manager
+                        .loaded_chunks
+                        .insert((x, y, z), Chunk::full_of_block(BlockID::AIR));
+                }
+            }
+        }
+        chunk_manager
+    }
+
+    fn block_light(chunk_manager: &ChunkManager, position: BlockCoords) -> u8 {
+        chunk_manager.get_light(position, LightChannel::Block).unwrap()
+    }
+
+    fn sky_light(chunk_manager: &ChunkManager, position: BlockCoords) -> u8 {
+        chunk_manager.get_light(position, LightChannel::Sky).unwrap()
+    }
+
+    #[test]
+    fn light_drops_by_one_per_block() {
+        let mut chunk_manager = world(0, 1);
+        chunk_manager.set_block(4, 8, 4, BlockID::named("glowstone"));
+
+        for distance in 0..=15 {
+            let expected = MAX_LIGHT_LEVEL - distance as u8;
+            assert_eq!(block_light(&chunk_manager, (4, 8 + distance, 4)), expected);
+            assert_eq!(block_light(&chunk_manager, (4, 8, 4 - distance)), expected);
+        }
+
+        // Around corners too, it is the number of blocks walked
+        assert_eq!(block_light(&chunk_manager, (5, 9, 4)), 13);
+        assert_eq!(block_light(&chunk_manager, (6, 6, 7)), 8);
+        assert_eq!(block_light(&chunk_manager, (4, 8, 20)), 0);
+    }
+
+    #[test]
+    fn light_goes_around_opaque_blocks() {
+        let mut chunk_manager = world(0, 1);
+        chunk_manager.set_block(5, 8, 4, BlockID::named("stone"));
+        chunk_manager.set_block(4, 8, 4, BlockID::named("glowstone"));
+
+        assert_eq!(block_light(&chunk_manager, (5, 8, 4)), 0);
+        assert_eq!(block_light(&chunk_manager, (6, 8, 4)), 11);
+    }
+
+    #[test]
+    fn light_crosses_chunk_borders() {
+        let mut chunk_manager = world(0, 1);
+        chunk_manager.set_block(14, 8, 1, BlockID::named("glowstone"));
+
+        assert_eq!(block_light(&chunk_manager, (16, 8, 1)), 13);
+        assert_eq!(block_light(&chunk_manager, (20, 8, 1)), 9);
+        assert_eq!(block_light(&chunk_manager, (14, 16, 1)), 7);
+        assert_eq!(block_light(&chunk_manager, (14, 8, -2)), 12);
+        assert_eq!(block_light(&chunk_manager, (16, 8, -2)), 10);
+    }
+
+    #[test]
+    fn removing_the_light_brings_the_levels_back_to_0() {
+        let mut chunk_manager = world(0, 1);
+        chunk_manager.set_block(14, 8, 1, BlockID::named("glowstone"));
+        chunk_manager.set_block(14, 8, 1, BlockID::AIR);
+
+        for x in -16..32 {
+            for y in 0..32 {
+                for z in -16..32 {
+                    assert_eq!(block_light(&chunk_manager, (x, y, z)), 0, "{x} {y} {z}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn removing_a_light_keeps_the_other_ones() {
+        let mut chunk_manager = world(0, 1);
+        chunk_manager.set_block(0, 8, 0, BlockID::named("glowstone"));
+        chunk_manager.set_block(6, 8, 0, BlockID::named("glowstone"));
+        chunk_manager.set_block(6, 8, 0, BlockID::AIR);
+
+        assert_eq!(block_light(&chunk_manager, (6, 8, 0)), 9);
+        assert_eq!(block_light(&chunk_manager, (12, 8, 0)), 3);
+        assert_eq!(block_light(&chunk_manager, (-3, 8, 0)), 12);
+    }
+
+    #[test]
+    fn new_chunks_are_lit_from_the_sky_and_their_lights() {
+        let mut chunk_manager = world(TOP - 1, TOP);
+        let roof = TOP * SIZE + 4;
+
+        // A roof over the origin in the top chunks, and a light further down
+        for x in -2..=2 {
+            for z in -2..=2 {
+                let (cx, _, cz, bx, by, bz) = ChunkManager::get_chunk_coords(x, roof, z);
+                let chunk = chunk_manager.loaded_chunks.get_mut(&(cx, TOP, cz)).unwrap();
+                chunk.set_block(bx, by, bz, BlockID::named("stone"));
+            }
+        }
+        let (cx, cy, cz, bx, by, bz) = ChunkManager::get_chunk_coords(20, roof - 20, 0);
+        let chunk = chunk_manager.loaded_chunks.get_mut(&(cx, cy, cz)).unwrap();
+        chunk.set_block(bx, by, bz, BlockID::named("glowstone"));
+
+        let coords: Vec<_> = chunk_manager.loaded_chunks.keys().copied().collect();
+        chunk_manager.unlit_chunks.extend(coords);
+        chunk_manager.light_pending_chunks();
+        assert!(chunk_manager.unlit_chunks.is_empty());
+
+        // The sky light goes down through both chunks, and sideways under the roof
+        assert_eq!(sky_light(&chunk_manager, (0, roof + 1, 0)), 15);
+        assert_eq!(sky_light(&chunk_manager, (8, (TOP - 1) * SIZE, 8)), 15);
+        assert_eq!(sky_light(&chunk_manager, (0, roof - 1, 0)), 12);
+        assert_eq!(sky_light(&chunk_manager, (0, roof - 20, 0)), 12);
+        assert_eq!(sky_light(&chunk_manager, (2, roof - 1, 2)), 14);
+
+        // The light of the glowstone reaches the chunks next to it
+        assert_eq!(block_light(&chunk_manager, (20, roof - 20, 0)), 15);
+        assert_eq!(block_light(&chunk_manager, (15, roof - 20, 0)), 10);
+        assert_eq!(block_light(&chunk_manager, (20, roof - 20, -3)), 12);
+    }
+}
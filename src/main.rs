@@ -6,32 +6,52 @@ pub mod debugging;
 
 pub mod aabb;
 pub mod ambient_occlusion;
+pub mod block_registry;
+pub mod block_shape;
+pub mod block_state;
+pub mod block_storage;
 pub mod block_texture_sides;
 pub mod chunk;
 pub mod chunk_manager;
+pub mod chunk_visibility;
+pub mod chunk_workers;
 pub mod constants;
 pub mod drawing;
+pub mod dropped_items;
 pub mod ecs;
+pub mod fluids;
+pub mod frustum;
 pub mod gui;
 pub mod input;
 pub mod inventory;
+pub mod lighting;
+pub mod meshing;
+pub mod mining;
+pub mod packed_vertex;
 pub mod physics;
 pub mod player;
 pub mod raycast;
 pub mod renderer;
 pub mod shader;
 pub mod shapes;
+pub mod sky;
+pub mod survival;
 pub mod texture;
 pub mod texture_pack;
 pub mod types;
 pub mod util;
 pub mod window;
+pub mod world_clock;
+pub mod world_generator;
+pub mod world_save;
 pub mod timer;
 pub mod particle_system;
 
 use crate::aabb::get_block_aabb;
 use crate::constants::*;
 use crate::gui::create_block_outline_vao;
+use crate::gui::{block_face_first_vertex, create_block_cracks_vao, create_digit_vaos};
+use crate::gui::create_inventory_vao;
 use crate::gui::create_hotbar_vao;
 use crate::gui::create_widgets_texture;
 use crate::input::InputCache;
@@ -42,17 +62,25 @@ use crate::debugging::*;
 use crate::shader::ShaderProgram;
 use crate::util::Forward;
 
+use crate::block_registry::{known_blocks, BlockRegistry};
+use crate::block_state::{Axis, BlockState, Facing, Property};
 use crate::chunk::BlockID;
-use crate::chunk_manager::ChunkManager;
+use crate::chunk_manager::{ChunkManager, CHUNK_SIZE};
+use crate::frustum::Frustum;
 
+use crate::dropped_items::DroppedItems;
+use crate::inventory::item::{Food, Item, ItemStack};
+use crate::inventory::screen::InventoryScreen;
 use crate::inventory::Inventory;
+use crate::mining::{can_harvest, mining_time, BlockMining};
 use glfw::ffi::glfwSwapInterval;
 use glfw::{Action, Context, Key, MouseButton, WindowHint};
 use nalgebra::Matrix4;
 use nalgebra::Vector3;
-use nalgebra_glm::{pi, vec3, IVec3, Vec2, Vec3};
+use nalgebra_glm::{pi, vec2, vec3, IVec3, Vec2, Vec3};
 use std::collections::HashMap;
 use std::ffi::CString;
+use std::sync::Arc;
 use std::os::raw::c_void;
 use std::time::Duration;
 use std::time::Instant;
@@ -61,13 +89,68 @@ use crate::particle_system::ParticleSystem;
 use crate::physics::Interpolator;
 use crate::player::{PlayerPhysicsState, PlayerProperties};
 use std::time;
+use crate::sky::Sky;
+use crate::survival::{GameMode, PlayerStats};
+use crate::texture::create_texture;
 use crate::timer::Timer;
+use crate::world_clock::WorldClock;
+use crate::world_generator::{TerrainGenerator, WorldGenerator};
+use crate::world_save::{WorldMetadata, WorldSave};
 
 use crate::gui::{
     create_crosshair_vao, create_gui_icons_texture, create_hotbar_selection_vao, draw_crosshair,
+    StatusBarVaos,
 };
 
+// `--seed <number>` picks the seed of a new world
+fn seed_from_args() -> u32 {
+    let args: Vec<String> = std::env::args().collect();
+
+    match args.iter().position(|arg| arg == "--seed") {
+        Some(i) => match args.get(i + 1).map(|seed| seed.parse()) {
+            Some(Ok(seed)) => seed,
+            _ => {
+                eprintln!("--seed expects a number between 0 and {}", u32::MAX);
+                std::process::exit(1);
+            }
+        },
+        None => WORLD_SEED,
+    }
+}
+
+// Replaces the block with air in a burst of particles, returns the block that was there
+fn break_block(
+    chunk_manager: &mut ChunkManager,
+    particle_systems: &mut Vec<ParticleSystem>,
+    (x, y, z): (i32, i32, i32),
+) -> Option<BlockID> {
+    let block = chunk_manager.get_block(x, y, z);
+
+    chunk_manager.set_block(x, y, z, BlockID::AIR);
+    println!("Destroyed block at ({x} {y} {z})");
+    particle_systems.push(ParticleSystem::new(vec3(
+        x as f32 + 0.5,
+        y as f32 + 0.5,
+        z as f32 + 0.5,
+    )));
+
+    block
+}
+
 fn main() {
+    match BlockRegistry::load(BLOCK_REGISTRY_PATH) {
+        Ok(registry) => registry.install(),
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+    }
+
+    if std::env::args().any(|arg| arg == "--mesh-benchmark") {
+        meshing::run_meshing_benchmark(&TerrainGenerator::new(seed_from_args()), 10);
+        return;
+    }
+
     let (mut glfw, mut window, events) = create_window(WINDOW_WIDTH, WINDOW_HEIGHT, WINDOW_NAME);
 
     gl_call!(gl::Enable(gl::DEBUG_OUTPUT));
@@ -100,7 +183,13 @@ fn main() {
     ));
 
     // Generate Texture Atlas
-    let (atlas, uv_map) = generate_texture_atlas();
+    let (atlas, uv_map) = match generate_texture_atlas() {
+        Ok(atlas) => atlas,
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+    };
     gl_call!(gl::ActiveTexture(gl::TEXTURE0));
     gl_call!(gl::BindTexture(gl::TEXTURE_2D, atlas));
 
@@ -112,6 +201,18 @@ fn main() {
     gl_call!(gl::ActiveTexture(gl::TEXTURE2));
     gl_call!(gl::BindTexture(gl::TEXTURE_2D, gui_widgets_texture));
 
+    let destroy_stages_texture = create_texture("textures/misc/destroy_stages.png");
+    gl_call!(gl::ActiveTexture(gl::TEXTURE6));
+    gl_call!(gl::BindTexture(gl::TEXTURE_2D, destroy_stages_texture));
+
+    let digits_texture = create_texture("textures/gui/digits.png");
+    gl_call!(gl::ActiveTexture(gl::TEXTURE7));
+    gl_call!(gl::BindTexture(gl::TEXTURE_2D, digits_texture));
+
+    let inventory_texture = create_texture("textures/gui/inventory.png");
+    gl_call!(gl::ActiveTexture(gl::TEXTURE8));
+    gl_call!(gl::BindTexture(gl::TEXTURE_2D, inventory_texture));
+
     let mut voxel_shader =
         ShaderProgram::compile("src/shaders/voxel.vert", "src/shaders/voxel.frag");
 
@@ -123,44 +224,76 @@ fn main() {
 
     let crosshair_vao = create_crosshair_vao();
     let block_outline_vao = create_block_outline_vao();
+    let block_cracks_vao = create_block_cracks_vao();
     let hotbar_vao = create_hotbar_vao();
     let hotbar_selection_vao = create_hotbar_selection_vao();
-
-    let mut inventory = Inventory::new(&uv_map);
+    let status_bar_vaos = StatusBarVaos::new();
+    let digit_vaos = create_digit_vaos();
+    let inventory_vao = create_inventory_vao();
+    let sky = Sky::new();
+
+    let mut world_save = WorldSave::new(SAVE_DIRECTORY);
+    let saved_metadata = world_save.exists().then(|| {
+        world_save
+            .load_metadata()
+            .expect("Failed to load the world metadata")
+    });
+
+    // An existing world keeps the seed it was created with
+    let seed = saved_metadata
+        .as_ref()
+        .map_or_else(seed_from_args, |metadata| metadata.seed);
+    let generator = Arc::new(TerrainGenerator::new(seed));
+
+    let world_metadata = saved_metadata.unwrap_or_else(|| {
+        let spawn_height = generator.surface_height(0, 0).max(SEA_LEVEL) + 2;
+        WorldMetadata::new(seed, vec3(0.5, spawn_height as f32, 0.5))
+    });
+
+    let mut inventory = Inventory::new();
+    let mut inventory_screen = InventoryScreen::default();
     let mut player_properties = PlayerProperties::new();
+    player_properties.rotation = world_metadata.player_rotation;
+    let mut player_stats = PlayerStats::new();
     let mut player_interpolator = Interpolator::new(
         1.0 / 60.0,
-        PlayerPhysicsState::new_at_position(vec3(0.0f32, 30.0, 0.0)),
+        PlayerPhysicsState::new_at_position(world_metadata.player_position),
     );
 
     let mut global_timer = Timer::new();
+    let mut world_clock = WorldClock::new(world_metadata.time_of_day);
     let mut player_fov_interpolator = Interpolator::new(1.0 / 30.0, FOV);
 
     gl_call!(gl::ActiveTexture(gl::TEXTURE0 + 0));
     gl_call!(gl::BindTexture(gl::TEXTURE_2D, atlas));
 
-    let mut chunk_manager = ChunkManager::new();
-    chunk_manager.generate_terrain();
+    let mut chunk_manager = ChunkManager::new(generator, &uv_map);
     // chunk_manager.preload_some_chunks();
 
     let mut input_cache = InputCache::default();
     let mut flying_trigger_interval = Duration::from_millis(250);
     let mut last_space = Instant::now();
     let mut space_throttle = false;
+    let sprint_trigger_interval = Duration::from_millis(250);
+    let mut last_forward = Instant::now();
     
     let mut particle_systems: Vec<ParticleSystem> = Vec::new();
+    let mut block_mining = BlockMining::default();
+    let mut dropped_items = DroppedItems::default();
+    let mut last_frame = global_timer.time();
 
     // 메인 루프
     while !window.should_close() {
-        // Get looking block coords
-        let looking_block = {
-            let is_solid_block_at =
-                |x: i32, y: i32, z: i32| chunk_manager.is_solid_block_at(x, y, z);
+        // Get looking block coords, spectators can't interact with the blocks
+        let looking_block = if player_properties.game_mode == GameMode::Spectator {
+            None
+        } else {
+            let get_boxes = |x: i32, y: i32, z: i32| chunk_manager.get_selection_boxes(x, y, z);
             let forward = player_properties.rotation.forward();
             let player: &mut PlayerPhysicsState = player_interpolator.get_current_state();
 
             raycast::raycast(
-                &is_solid_block_at,
+                &get_boxes,
                 &player.get_camera_position(),
                 &forward.normalize(),
                 REACH_DISTANCE,
@@ -172,7 +305,39 @@ fn main() {
 
         for (_, event) in glfw::flush_messages(&events) {
             input_cache.handle_event(&event);
-            inventory.handle_input_event(&event);
+
+            // The inventory screen takes every event until it is closed, the camera doesn't turn
+            if inventory_screen.is_open {
+                match event {
+                    glfw::WindowEvent::Key(Key::E | Key::Escape, _, Action::Press, _) => {
+                        if let Some(stack) = inventory_screen.close(&mut inventory) {
+                            let player = player_interpolator.get_current_state();
+                            dropped_items.throw(
+                                stack,
+                                player.get_camera_position(),
+                                player_properties.rotation.forward(),
+                            );
+                        }
+
+                        // The cursor jumps back where it was captured, the camera must not follow
+                        set_cursor_captured(&mut window, true);
+                        let (x, y) = window.get_cursor_pos();
+                        input_cache.last_cursor_pos = vec2(x, y);
+                    }
+                    _ => inventory_screen.handle_input_event(&event, &mut inventory),
+                }
+                continue;
+            }
+
+            // The scroll wheel changes the speed of the spectators instead of the selected item
+            if player_properties.game_mode == GameMode::Spectator {
+                if let glfw::WindowEvent::Scroll(_, y) = event {
+                    player_properties.change_spectator_speed(y as f32);
+                    println!("Spectator speed: {:.1}", player_properties.spectator_speed);
+                }
+            } else {
+                inventory.handle_input_event(&event);
+            }
 
             match event {
                 glfw::WindowEvent::Key(Key::Escape, _, Action::Press, _) => {
@@ -187,10 +352,69 @@ fn main() {
                     }
                 }
 
+                glfw::WindowEvent::Key(Key::T, _, Action::Press, _) => {
+                    println!(
+                        "Time of day: {:.3} ({})",
+                        world_clock.time_of_day(),
+                        world_clock.formatted_time()
+                    );
+                }
+
+                // Jumps to the next sunrise, noon, sunset or midnight
+                glfw::WindowEvent::Key(Key::N, _, Action::Press, _) => {
+                    let quarter = (world_clock.time_of_day() * 4.0).floor() + 1.0;
+                    world_clock.set_time_of_day(quarter / 4.0);
+                    println!("Time set to {}", world_clock.formatted_time());
+                }
+
+                glfw::WindowEvent::Key(Key::F, _, Action::Press, _) => {
+                    world_clock.speed = if world_clock.speed == 1.0 {
+                        FAST_FORWARD_SPEED
+                    } else {
+                        1.0
+                    };
+                    println!("Time speed: x{}", world_clock.speed);
+                }
+
+                glfw::WindowEvent::Key(Key::F3, _, Action::Press, _) => {
+                    let stats = chunk_manager.render_stats;
+                    println!(
+                        "Chunks drawn: {} / {} considered ({} occluded)",
+                        stats.chunks_drawn, stats.chunks_considered, stats.chunks_occluded
+                    );
+
+                    let memory = chunk_manager.memory_stats();
+                    let kib = |bytes: usize| bytes / 1024;
+                    println!(
+                        "Chunks loaded: {} ({} of a single block, {} with geometry)",
+                        memory.chunks, memory.single_state_chunks, memory.chunks_with_geometry
+                    );
+                    println!(
+                        "Chunk memory: blocks {} KiB (flat arrays: {} KiB), other {} KiB, vertices {} KiB",
+                        kib(memory.block_bytes),
+                        kib(memory.flat_block_bytes),
+                        kib(memory.other_bytes),
+                        kib(memory.vertex_bytes)
+                    );
+                }
+
+                glfw::WindowEvent::Key(Key::O, _, Action::Press, _) => {
+                    chunk_manager.occlusion_culling = !chunk_manager.occlusion_culling;
+                    println!("Occlusion culling: {}", chunk_manager.occlusion_culling);
+                }
+
+                glfw::WindowEvent::Key(Key::M, _, Action::Press, _) => {
+                    let method = chunk_manager.meshing_method.next();
+                    chunk_manager.set_meshing_method(method);
+                    println!("Meshing method: {:?}", method);
+                }
+
                 glfw::WindowEvent::Key(Key::Space, _, Action::Press, _) => {
                     if space_throttle {
                         space_throttle = false;
-                    } else if Instant::now().duration_since(last_space) < flying_trigger_interval {
+                    } else if Instant::now().duration_since(last_space) < flying_trigger_interval
+                        && player_properties.game_mode.can_fly()
+                    {
                         player_properties.is_flying = !player_properties.is_flying;
                         println!("Flying: {}", player_properties.is_flying);
                         space_throttle = true;
@@ -198,6 +422,47 @@ fn main() {
                     last_space = Instant::now();
                 }
 
+                glfw::WindowEvent::Key(Key::G, _, Action::Press, _) => {
+                    let game_mode = player_properties.game_mode.next();
+                    player_properties.game_mode = game_mode;
+                    if !game_mode.can_fly() {
+                        player_properties.is_flying = false;
+                    }
+
+                    // Keep the position and rotation, but not the speed of the spectator
+                    player_interpolator.get_current_state().velocity = vec3(0.0, 0.0, 0.0);
+                    println!("Game mode: {:?}", game_mode);
+                }
+
+                glfw::WindowEvent::Key(Key::W, _, Action::Press, _) => {
+                    if Instant::now().duration_since(last_forward) < sprint_trigger_interval {
+                        player_properties.is_sprinting = true;
+                    }
+                    last_forward = Instant::now();
+                }
+
+                glfw::WindowEvent::Key(Key::E, _, Action::Press, _)
+                    if player_properties.game_mode != GameMode::Spectator =>
+                {
+                    set_cursor_captured(&mut window, false);
+                    let (x, y) = window.get_cursor_pos();
+                    inventory_screen.open((x as f32, y as f32));
+                }
+
+                // Throws the whole selected stack
+                glfw::WindowEvent::Key(Key::Q, _, Action::Press, _)
+                    if player_properties.game_mode != GameMode::Spectator =>
+                {
+                    if let Some(stack) = inventory.take_selected_stack() {
+                        let player = player_interpolator.get_current_state();
+                        dropped_items.throw(
+                            stack,
+                            player.get_camera_position(),
+                            player_properties.rotation.forward(),
+                        );
+                    }
+                }
+
                 glfw::WindowEvent::CursorPos(_, _) => {
                     player_properties.rotate_camera(
                         input_cache.cursor_rel_pos.x as f32,
@@ -205,15 +470,36 @@ fn main() {
                     );
                 }
 
+                // Survival players eat the selected food, even without looking at a block
+                glfw::WindowEvent::MouseButton(MouseButton::Button2, Action::Press, _)
+                    if player_properties.game_mode.is_survival()
+                        && matches!(inventory.get_selected_item(), Some(Item::Food(_))) =>
+                {
+                    if let Some(Item::Food(food)) = inventory.get_selected_item() {
+                        if player_stats.eat(food) {
+                            inventory.remove_from_slot(inventory.selected_hotbar_slot, 1);
+                        }
+                    }
+                }
+
                 glfw::WindowEvent::MouseButton(button, Action::Press, _) => {
                     // [TODO] - &looking_block
 
                     if let &Some(((x, y, z), normal)) = &looking_block {
                         match button {
-                            MouseButton::Button1 => {
-                                chunk_manager.set_block(x, y, z, BlockID::Air);
-                                println!("Destroyed block at ({x} {y} {z})");
-                                particle_systems.push(ParticleSystem::new(vec3(x as f32 + 0.5, y as f32 + 0.5, z as f32 + 0.5)));
+                            // Survival players hold the button instead, see below
+                            MouseButton::Button1
+                                if !player_properties.game_mode.is_survival() =>
+                            {
+                                let breakable = chunk_manager
+                                    .get_block(x, y, z)
+                                    .map_or(false, |block| block.is_breakable());
+                                if !breakable {
+                                    continue;
+                                }
+
+                                // Creative players don't get the blocks they break
+                                break_block(&mut chunk_manager, &mut particle_systems, (x, y, z));
                             }
                             MouseButton::Button2 => {
                                 let adjacent_block = IVec3::new(x, y, z) + normal;
@@ -226,13 +512,30 @@ fn main() {
                                 let player = player_interpolator.get_current_state();
 
                                 if !player.aabb.intersects(&adjacent_block_aabb) {
-                                    if let Some(block) = inventory.get_selected_item() {
-                                        chunk_manager.set_block(
+                                    if let Some(Item::Block(block)) =
+                                        inventory.get_selected_item()
+                                    {
+                                        // Logs lie along the axis of the face they are placed against,
+                                        // stairs face away from the player
+                                        let axis = Axis::from_normal(&normal);
+                                        let facing = Facing::from_direction(
+                                            &player_properties.rotation.forward(),
+                                        );
+                                        let state = BlockState::new(block)
+                                            .with(Property::Axis, axis as u8)
+                                            .with(Property::Facing, facing as u8);
+
+                                        chunk_manager.set_block_state(
                                             adjacent_block.x,
                                             adjacent_block.y,
                                             adjacent_block.z,
-                                            block,
+                                            state,
                                         );
+
+                                        if player_properties.game_mode.is_survival() {
+                                            let slot = inventory.selected_hotbar_slot;
+                                            inventory.remove_from_slot(slot, 1);
+                                        }
                                     }
                                 }
                             }
@@ -245,10 +548,84 @@ fn main() {
             }
         }
 
-        let player_physics_state =
-            player_interpolator.update_player_physics(global_timer.time(), &input_cache,&chunk_manager,&mut player_properties);
+        let now = global_timer.time();
+        let frame_time = now.duration_since(last_frame).as_secs_f32();
+        last_frame = now;
+
+        // Survival players break the block they look at by holding the left button
+        match looking_block {
+            Some(((x, y, z), _))
+                if player_properties.game_mode.is_survival()
+                    && !inventory_screen.is_open
+                    && input_cache.is_mouse_button_pressed(MouseButton::Button1) =>
+            {
+                let duration = chunk_manager
+                    .get_block(x, y, z)
+                    .and_then(|block| mining_time(block, inventory.get_selected_tool()));
+
+                if block_mining.update((x, y, z), duration, frame_time) {
+                    let block = break_block(&mut chunk_manager, &mut particle_systems, (x, y, z));
+
+                    if let Some(block) = block {
+                        if can_harvest(block, inventory.get_selected_tool()) {
+                            let stack = ItemStack::new(Item::Block(block), 1);
+                            dropped_items.drop_from_block(stack, (x, y, z));
+                        }
+
+                        // Leaves sometimes drop an apple as well
+                        if block == known_blocks().oak_leaves
+                            && rand::random::<f32>() < APPLE_DROP_CHANCE
+                        {
+                            let stack = ItemStack::new(Item::Food(Food::Apple), 1);
+                            dropped_items.drop_from_block(stack, (x, y, z));
+                        }
+                    }
+                }
+            }
+            _ => block_mining.stop(),
+        }
 
-        chunk_manager.rebuild_dirty_chunks(&uv_map);
+        if player_stats.is_dead() {
+            println!("You died");
+            player_stats = PlayerStats::new();
+            player_interpolator.reset(PlayerPhysicsState::new_at_position(world_metadata.spawn_point));
+        }
+
+        // The player stands still while the inventory screen is open
+        let no_input = InputCache::default();
+        let physics_input = if inventory_screen.is_open {
+            &no_input
+        } else {
+            &input_cache
+        };
+
+        let player_physics_state = player_interpolator.update_player_physics(
+            global_timer.time(),
+            physics_input,
+            &chunk_manager,
+            &mut player_properties,
+            &mut player_stats,
+        );
+
+        dropped_items.update(&chunk_manager, frame_time);
+        if player_properties.game_mode != GameMode::Spectator {
+            let player_aabb = player_interpolator.get_current_state().aabb;
+            dropped_items.pick_up(&player_aabb, &mut inventory);
+        }
+
+        let player_chunk = {
+            let position = player_physics_state.get_camera_position();
+            let (x, y, z, _, _, _) = ChunkManager::get_chunk_coords(
+                position.x.floor() as i32,
+                position.y.floor() as i32,
+                position.z.floor() as i32,
+            );
+            (x, y, z)
+        };
+        world_clock.update(global_timer.time());
+        chunk_manager.update_fluids(global_timer.time());
+        chunk_manager.update_streaming(player_chunk, &mut world_save);
+        chunk_manager.rebuild_dirty_chunks();
 
         let view_matrix = {
             let camera_position = player_physics_state.get_camera_position();
@@ -261,19 +638,29 @@ fn main() {
             )
         };
 
-        let target_fov = if !player_properties.is_flying {
-            FOV
+        let target_fov = if player_properties.is_flying || player_properties.is_sprinting {
+            FOV * SPRINT_FOV_FACTOR
         } else {
-            FOV + FOV * 0.15
+            FOV
         };
+        let fov = player_fov_interpolator.interpolate_fov(global_timer.time(), target_fov);
 
         let projection_matrix = nalgebra_glm::perspective(
             WINDOW_WIDTH as f32 / WINDOW_HEIGHT as f32,
-            FOV,
+            fov,
             NEAR_PLANE,
             FAR_PLANE,
         );
 
+        // Draw the sky
+        {
+            let sky_color = world_clock.sky_color();
+            gl_call!(gl::ClearColor(sky_color.x, sky_color.y, sky_color.z, 1.0));
+            gl_call!(gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT));
+
+            sky.draw(&mut gui_shader, &world_clock, &view_matrix, &projection_matrix);
+        }
+
         // Draw chunks
         {
             voxel_shader.use_program();
@@ -281,13 +668,24 @@ fn main() {
             voxel_shader.set_uniform_matrix4fv("view", view_matrix.as_ptr());
             voxel_shader.set_uniform_matrix4fv("projection", projection_matrix.as_ptr());
             voxel_shader.set_uniform1i("atlas", 0);
-
-            let (r, g, b, a) = BACKGROUND_COLOR;
-            gl_call!(gl::ClearColor(r, g, b, a));
-            gl_call!(gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT));
+            voxel_shader.set_uniform1f(
+                "tile_size",
+                BLOCK_TEXTURE_SIZE as f32 / TEXTURE_ATLAS_SIZE as f32,
+            );
+
+            voxel_shader.set_uniform1f("sky_light_multiplier", world_clock.sky_light_multiplier());
+            voxel_shader.set_uniform3f("fog_color", world_clock.fog_color().as_slice());
+            let fog_end = (chunk_manager.render_distance * CHUNK_SIZE as i32) as f32;
+            voxel_shader.set_uniform1f("fog_start", fog_end * FOG_START);
+            voxel_shader.set_uniform1f("fog_end", fog_end);
             gl_call!(gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA));
 
-            chunk_manager.render_loaded_chunks(&mut voxel_shader);
+            let frustum = Frustum::from_matrix(&(projection_matrix * view_matrix));
+            chunk_manager.render_loaded_chunks(
+                &mut voxel_shader,
+                &frustum,
+                &player_physics_state.get_camera_position(),
+            );
         }
 
         // Particles
@@ -306,10 +704,23 @@ fn main() {
             gl_call!(gl::Enable(gl::CULL_FACE));
         }
 
+        dropped_items.draw(&mut item_shader, &(projection_matrix * view_matrix), &uv_map);
+
         {
-            if let Some(((x, y, z), _)) = looking_block {
-                let (x, y, z) = (x as f32, y as f32, z as f32);
-                let model_matrix = Matrix4::new_translation(&vec3(x, y, z));
+            // The outline goes around every box of the block, it may have been broken this frame
+            let outlined_boxes = looking_block
+                .map(|((x, y, z), _)| chunk_manager.get_selection_boxes(x, y, z))
+                .filter(|boxes| !boxes.is_empty());
+
+            if let Some(boxes) = outlined_boxes {
+                let mins = boxes.iter().fold(vec3(f32::MAX, f32::MAX, f32::MAX), |mins, aabb| {
+                    mins.inf(&aabb.mins)
+                });
+                let maxs = boxes.iter().fold(vec3(f32::MIN, f32::MIN, f32::MIN), |maxs, aabb| {
+                    maxs.sup(&aabb.maxs)
+                });
+                let model_matrix = Matrix4::new_translation(&mins)
+                    * Matrix4::new_nonuniform_scaling(&(maxs - mins));
 
                 outline_shader.use_program();
                 unsafe {
@@ -317,10 +728,23 @@ fn main() {
                     outline_shader.set_uniform_matrix4fv("view", view_matrix.as_ptr());
                     outline_shader.set_uniform_matrix4fv("projection", projection_matrix.as_ptr());
                 }
+                outline_shader.set_uniform1i("crack_stage", -1);
 
                 gl_call!(gl::LineWidth(BLOCK_OUTLINE_WIDTH));
                 gl_call!(gl::BindVertexArray(block_outline_vao));
                 gl_call!(gl::DrawArrays(gl::LINES, 0, 24));
+
+                // The cracks cover the face the player is mining
+                let (coords, normal) = looking_block.unwrap();
+                if let Some(stage) = block_mining.crack_stage(coords) {
+                    outline_shader.set_uniform1i("crack_stage", stage as i32);
+                    outline_shader.set_uniform1i("destroy_stages", 6);
+
+                    gl_call!(gl::Disable(gl::CULL_FACE));
+                    gl_call!(gl::BindVertexArray(block_cracks_vao));
+                    gl_call!(gl::DrawArrays(gl::TRIANGLES, block_face_first_vertex(&normal), 6));
+                    gl_call!(gl::Enable(gl::CULL_FACE));
+                }
             }
         }
 
@@ -331,11 +755,41 @@ fn main() {
             gl_call!(gl::Disable(gl::DEPTH_TEST));
             inventory.draw_hotbar(hotbar_vao, &mut gui_shader);
             inventory.draw_hotbar_selection_box(hotbar_selection_vao, &mut gui_shader);
-            inventory.draw_hotbar_items(&mut item_shader);
+            inventory.draw_hotbar_items(&mut item_shader, &mut gui_shader, &digit_vaos, &uv_map);
+            if player_properties.game_mode.is_survival() {
+                player_stats.draw_status_bars(&status_bar_vaos, &mut gui_shader);
+            }
+            if inventory_screen.is_open {
+                inventory_screen.draw(
+                    &inventory,
+                    inventory_vao,
+                    &mut item_shader,
+                    &mut gui_shader,
+                    &digit_vaos,
+                    &uv_map,
+                );
+            }
             gl_call!(gl::Enable(gl::DEPTH_TEST));
         }
 
         // 프론트 버퍼와 백 버퍼 교체 - 프리징 방지
         window.swap_buffers();
     }
+
+    // Save the world before exiting
+    let world_metadata = WorldMetadata {
+        seed: chunk_manager.generator.seed(),
+        spawn_point: world_metadata.spawn_point,
+        player_position: player_interpolator.get_current_state().position,
+        player_rotation: player_properties.rotation,
+        time_of_day: world_clock.time_of_day(),
+    };
+
+    chunk_manager
+        .save_world(&mut world_save)
+        .expect("Failed to save the world");
+    world_save
+        .save_metadata(&world_metadata)
+        .expect("Failed to save the world metadata");
+    println!("World saved to {SAVE_DIRECTORY}");
 }
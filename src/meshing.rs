@@ -0,0 +1,785 @@
+use crate::ambient_occlusion::compute_ao_of_block;
+use crate::block_registry::{RenderLayer, MAX_LIGHT_LEVEL};
+use crate::block_shape::{fence_connections, BlockShape, ShapeBox};
+use crate::block_state::{BlockState, Property};
+use crate::block_texture_sides::BlockFaces;
+use crate::chunk::{BlockArray, BlockID, BlockIterator, Chunk};
+use crate::chunk_manager::CHUNK_SIZE;
+use crate::constants::WORLD_HEIGHT_IN_CHUNKS;
+use crate::fluids::fluid_height;
+use crate::packed_vertex::{tile_index_of_uv, PackedVertex, VERTEX_SIZE};
+use crate::types::{UVCoords, UVMap};
+use crate::world_generator::WorldGenerator;
+use nalgebra_glm::{vec3, Vec3};
+use std::collections::HashMap;
+use std::time::Instant;
+
+const PADDED_SIZE: i32 = CHUNK_SIZE as i32 + 2;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MeshingMethod {
+    // One quad per visible face
+    Naive,
+    // Coplanar neighbouring faces that look the same are merged into bigger quads
+    Greedy,
+}
+
+impl MeshingMethod {
+    pub fn next(&self) -> MeshingMethod {
+        match self {
+            MeshingMethod::Naive => MeshingMethod::Greedy,
+            MeshingMethod::Greedy => MeshingMethod::Naive,
+        }
+    }
+}
+
+// Vertices of a chunk, one list per render pass
+#[derive(Default)]
+pub struct ChunkMesh {
+    pub opaque: Vec<u32>,
+    pub cutout: Vec<u32>,
+    pub translucent: Vec<u32>,
+}
+
+impl ChunkMesh {
+    fn layer_mut(&mut self, layer: RenderLayer) -> &mut Vec<u32> {
+        match layer {
+            RenderLayer::Opaque => &mut self.opaque,
+            RenderLayer::Cutout => &mut self.cutout,
+            RenderLayer::Translucent => &mut self.translucent,
+        }
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        (self.opaque.len() + self.cutout.len() + self.translucent.len()) / VERTEX_SIZE
+    }
+}
+
+// Copy of a chunk and a one block border taken from its neighbours
+// The border is needed for face culling, lighting and for the ambient occlusion of the blocks on the edges
+// Blocks of unloaded neighbours are treated as air under the open sky
+pub struct ChunkNeighbourhood {
+    blocks: Vec<BlockState>,
+    // Packed like in the chunks, sky light in the high nibble
+    light: Vec<u8>,
+}
+
+const UNLOADED_LIGHT: u8 = MAX_LIGHT_LEVEL << 4;
+
+impl ChunkNeighbourhood {
+    // `chunks` holds the chunk and its 26 neighbours, indexed by `neighbour_index`
+    pub fn new(chunks: &[Option<&Chunk>; 27]) -> Self {
+        ChunkNeighbourhood::from_fn(|neighbour, x, y, z| {
+            chunks[neighbour].map_or((BlockState::AIR, UNLOADED_LIGHT), |chunk| {
+                (chunk.get_block_state(x, y, z), chunk.get_light(x, y, z))
+            })
+        })
+    }
+
+    // Without light, every block is lit by the sky and is in its default state
+    pub fn from_block_arrays(chunks: &[Option<&BlockArray>; 27]) -> Self {
+        ChunkNeighbourhood::from_fn(|neighbour, x, y, z| {
+            let block = chunks[neighbour].map_or(BlockID::AIR, |blocks| {
+                blocks[Chunk::coords_to_index(x, y, z)]
+            });
+
+            (BlockState::new(block), UNLOADED_LIGHT)
+        })
+    }
+
+    // `get_block` receives the index of the neighbour and the block coordinates inside of it
+    // and returns the state of the block and its light
+    fn from_fn(get_block: impl Fn(usize, u32, u32, u32) -> (BlockState, u8)) -> Self {
+        let size = CHUNK_SIZE as i32;
+        let volume = (PADDED_SIZE * PADDED_SIZE * PADDED_SIZE) as usize;
+        let mut blocks = Vec::with_capacity(volume);
+        let mut light = Vec::with_capacity(volume);
+
+        for y in -1..=size {
+            for z in -1..=size {
+                for x in -1..=size {
+                    let neighbour = ChunkNeighbourhood::neighbour_index(
+                        x.div_euclid(size),
+                        y.div_euclid(size),
+                        z.div_euclid(size),
+                    );
+
+                    let (block, block_light) = get_block(
+                        neighbour,
+                        x.rem_euclid(size) as u32,
+                        y.rem_euclid(size) as u32,
+                        z.rem_euclid(size) as u32,
+                    );
+
+                    blocks.push(block);
+                    light.push(block_light);
+                }
+            }
+        }
+
+        Self { blocks, light }
+    }
+
+    // Index of the neighbour at offset (dx, dy, dz), each between -1 and 1
+    pub fn neighbour_index(dx: i32, dy: i32, dz: i32) -> usize {
+        ((dy + 1) * 9 + (dz + 1) * 3 + (dx + 1)) as usize
+    }
+
+    #[inline]
+    fn index(x: i32, y: i32, z: i32) -> usize {
+        ((y + 1) * PADDED_SIZE * PADDED_SIZE + (z + 1) * PADDED_SIZE + (x + 1)) as usize
+    }
+
+    // Coordinates are local to the chunk and range from -1 to CHUNK_SIZE
+    #[inline]
+    pub fn get(&self, x: i32, y: i32, z: i32) -> BlockID {
+        self.get_state(x, y, z).block()
+    }
+
+    #[inline]
+    pub fn get_state(&self, x: i32, y: i32, z: i32) -> BlockState {
+        self.blocks[ChunkNeighbourhood::index(x, y, z)]
+    }
+
+    fn light_at(&self, [x, y, z]: [i32; 3]) -> u8 {
+        self.light[ChunkNeighbourhood::index(x, y, z)]
+    }
+
+    // A face is lit by the light of the block in front of it
+    fn light_of_face(&self, [x, y, z]: [i32; 3], face: &FaceDirection) -> u8 {
+        let [nx, ny, nz] = face.normal;
+        self.light_at([x + nx, y + ny, z + nz])
+    }
+
+    // Height of the block in 16ths, fluids are lower the further they flowed
+    fn height(&self, [x, y, z]: [i32; 3]) -> u8 {
+        let block = self.get(x, y, z);
+
+        // Fluids under the same fluid fill the whole block
+        if !block.is_fluid() || self.get(x, y + 1, z) == block {
+            16
+        } else {
+            fluid_height(self.get_state(x, y, z).get(Property::Level))
+        }
+    }
+
+    fn is_face_visible(&self, [x, y, z]: [i32; 3], face: &FaceDirection) -> bool {
+        let [nx, ny, nz] = face.normal;
+        let neighbour = self.get(x + nx, y + ny, z + nz);
+        let block = self.get(x, y, z);
+
+        // The top of a flowing fluid doesn't touch the block above
+        if face.index == 2 && self.height([x, y, z]) < 16 {
+            return true;
+        }
+
+        // A body of water or a wall of glass only has faces on its outside
+        let merges_with_neighbour = neighbour == block
+            && (block.is_fluid() || block.render_layer() == RenderLayer::Translucent);
+
+        !self.is_face_hidden([x, y, z], face) && !merges_with_neighbour
+    }
+
+    // Whether the neighbour in front of the face covers it completely
+    fn is_face_hidden(&self, [x, y, z]: [i32; 3], face: &FaceDirection) -> bool {
+        let [nx, ny, nz] = face.normal;
+        let neighbour = self.get_state(x + nx, y + ny, z + nz);
+        let shape = neighbour.block().shape();
+
+        if shape.is_cube() {
+            !neighbour.block().is_transparent()
+        } else {
+            // Faces come in pairs, the opposite face only differs by the lowest bit
+            shape.full_faces(neighbour) & (1 << (face.index ^ 1)) != 0
+        }
+    }
+
+    fn ao_of_block(&self, [x, y, z]: [i32; 3]) -> [[u8; 4]; 6] {
+        let does_occlude = |dx: i32, dy: i32, dz: i32| {
+            self.get(x + dx, y + dy, z + dz).casts_ambient_occlusion()
+        };
+
+        compute_ao_of_block(&does_occlude)
+    }
+}
+
+/*
+    A face is drawn as a quad going from its first corner `origin` along `s` then `t`
+    The texture is mapped so that `s` is the u axis and `t` the v axis
+    The corners are in the same order as the ones used by the ambient occlusion
+*/
+struct FaceDirection {
+    // Index of the face, same order as the active sides and the ambient occlusion
+    index: usize,
+    normal: [i32; 3],
+    origin: [i32; 3],
+    s: [i32; 3],
+    t: [i32; 3],
+}
+
+#[rustfmt::skip]
+const FACE_DIRECTIONS: [FaceDirection; 6] = [
+    // Right
+    FaceDirection { index: 0, normal: [1, 0, 0], origin: [1, 0, 1], s: [0, 0, -1], t: [0, 1, 0] },
+    // Left
+    FaceDirection { index: 1, normal: [-1, 0, 0], origin: [0, 0, 0], s: [0, 0, 1], t: [0, 1, 0] },
+    // Top
+    FaceDirection { index: 2, normal: [0, 1, 0], origin: [0, 1, 1], s: [1, 0, 0], t: [0, 0, -1] },
+    // Bottom
+    FaceDirection { index: 3, normal: [0, -1, 0], origin: [0, 0, 0], s: [1, 0, 0], t: [0, 0, 1] },
+    // Front
+    FaceDirection { index: 4, normal: [0, 0, 1], origin: [0, 0, 1], s: [1, 0, 0], t: [0, 1, 0] },
+    // Back
+    FaceDirection { index: 5, normal: [0, 0, -1], origin: [1, 0, 0], s: [-1, 0, 0], t: [0, 1, 0] },
+];
+
+impl FaceDirection {
+    fn axis(v: [i32; 3]) -> usize {
+        v.iter().position(|&c| c != 0).unwrap()
+    }
+
+    // Block coordinates of the face at `slice` along the normal and (si, ti) along s and t
+    fn block_at(&self, slice: i32, si: i32, ti: i32) -> [i32; 3] {
+        let max = CHUNK_SIZE as i32 - 1;
+        let along = |v: [i32; 3], i: i32| if v[FaceDirection::axis(v)] > 0 { i } else { max - i };
+
+        let mut block = [0; 3];
+        block[FaceDirection::axis(self.normal)] = slice;
+        block[FaceDirection::axis(self.s)] = along(self.s, si);
+        block[FaceDirection::axis(self.t)] = along(self.t, ti);
+        block
+    }
+}
+
+// Texture of the face and how many quarter turns it is rotated by, logs lying down have turned sides
+fn uv_of_face(state: BlockState, face: usize, uv_map: &UVMap) -> (UVCoords, u8) {
+    uv_map.get(&state.block()).unwrap().oriented_faces(state.axis())[face]
+}
+
+// Quarter turns of the texture of a quad of `w` by `h`, counterclockwise
+fn rotate_tile_coords((s, t): (i32, i32), (w, h): (i32, i32), rotation: u8) -> (i32, i32) {
+    match rotation {
+        0 => (s, t),
+        1 => (t, w - s),
+        2 => (w - s, h - t),
+        _ => (h - t, s),
+    }
+}
+
+// Appends a quad of `w` by `h` faces, the texture is repeated once per face
+fn push_quad(
+    vertices: &mut Vec<u32>,
+    face: &FaceDirection,
+    block: [i32; 3],
+    (w, h): (i32, i32),
+    (uv, rotation): (UVCoords, u8),
+    key: FaceKey,
+) {
+    let corners = [(0, 0), (w, 0), (w, h), (0, h)];
+    let tile = tile_index_of_uv(uv);
+    let FaceKey { ao, light, lowered, .. } = key;
+
+    // 2 triangles
+    for corner in [0, 1, 2, 2, 3, 0] {
+        let (s, t) = corners[corner];
+        let (u, v) = rotate_tile_coords((s, t), (w, h), rotation);
+
+        let vertex = PackedVertex {
+            position: std::array::from_fn(|i| {
+                (block[i] + face.origin[i] + face.s[i] * s + face.t[i] * t) as u8
+            }),
+            face: face.index as u8,
+            // Leaves in front of a face also count as an occluder, which can make it 4
+            ao: ao[corner].min(3),
+            tile,
+            tile_coords: [u as u8, v as u8],
+            sky_light: light >> 4,
+            block_light: light & 0xF,
+            // The whole top face and the top edge of the side faces
+            lowered: match face.index {
+                2 => lowered,
+                3 => 0,
+                _ if t == h => lowered,
+                _ => 0,
+            },
+            inset: [0, 0],
+            fine_tile_coords: false,
+        };
+
+        let packed = vertex.encode();
+        debug_assert_eq!(PackedVertex::decode(packed), vertex);
+
+        vertices.extend_from_slice(&packed);
+    }
+}
+
+/*
+   Builds the vertices of a chunk without touching OpenGL, so it can run on a worker thread
+
+   Optimization :
+       If 2 solid blocks are touching, don't render the faces where they touch.
+       Render only the faces that are next to a transparent block (AIR for example)
+*/
+pub fn build_chunk_mesh(
+    neighbourhood: &ChunkNeighbourhood,
+    uv_map: &UVMap,
+    method: MeshingMethod,
+) -> ChunkMesh {
+    let mut mesh = match method {
+        MeshingMethod::Naive => build_naive_mesh(neighbourhood, uv_map),
+        MeshingMethod::Greedy => build_greedy_mesh(neighbourhood, uv_map),
+    };
+
+    build_shaped_blocks(neighbourhood, uv_map, &mut mesh);
+    mesh
+}
+
+fn build_naive_mesh(neighbourhood: &ChunkNeighbourhood, uv_map: &UVMap) -> ChunkMesh {
+    let mut mesh = ChunkMesh::default();
+
+    for (x, y, z) in BlockIterator::new() {
+        let block_coords = [x as i32, y as i32, z as i32];
+        let block = neighbourhood.get_state(block_coords[0], block_coords[1], block_coords[2]);
+
+        if block.block().is_air() || !block.block().shape().is_cube() {
+            continue;
+        }
+
+        let mut ao_block = None;
+
+        for face in FACE_DIRECTIONS.iter() {
+            if neighbourhood.is_face_visible(block_coords, face) {
+                let ao = ao_block.get_or_insert_with(|| neighbourhood.ao_of_block(block_coords));
+                let uv = uv_of_face(block, face.index, uv_map);
+
+                let key = FaceKey {
+                    block,
+                    ao: ao[face.index],
+                    light: neighbourhood.light_of_face(block_coords, face),
+                    lowered: 16 - neighbourhood.height(block_coords),
+                };
+
+                let vertices = mesh.layer_mut(block.block().render_layer());
+                push_quad(vertices, face, block_coords, (1, 1), uv, key);
+            }
+        }
+    }
+
+    mesh
+}
+
+// Faces can only be merged if they look exactly the same
+#[derive(Copy, Clone, PartialEq, Eq)]
+struct FaceKey {
+    block: BlockState,
+    ao: [u8; 4],
+    light: u8,
+    // Top of the flowing fluids, see `PackedVertex::lowered`
+    lowered: u8,
+}
+
+fn build_greedy_mesh(neighbourhood: &ChunkNeighbourhood, uv_map: &UVMap) -> ChunkMesh {
+    const SIZE: usize = CHUNK_SIZE as usize;
+
+    let mut mesh = ChunkMesh::default();
+    let mut ao_cache: Vec<Option<[[u8; 4]; 6]>> = vec![None; SIZE * SIZE * SIZE];
+
+    for face in FACE_DIRECTIONS.iter() {
+        for slice in 0..SIZE as i32 {
+            // Visible faces of this slice, indexed by [ti][si]
+            let mut mask = [[None; SIZE]; SIZE];
+
+            for ti in 0..SIZE {
+                for si in 0..SIZE {
+                    let [x, y, z] = face.block_at(slice, si as i32, ti as i32);
+                    let block = neighbourhood.get_state(x, y, z);
+
+                    if block.block().is_air()
+                        || !block.block().shape().is_cube()
+                        || !neighbourhood.is_face_visible([x, y, z], face)
+                    {
+                        continue;
+                    }
+
+                    let ao = ao_cache[Chunk::coords_to_index(x as u32, y as u32, z as u32)]
+                        .get_or_insert_with(|| neighbourhood.ao_of_block([x, y, z]));
+
+                    mask[ti][si] = Some(FaceKey {
+                        block,
+                        ao: ao[face.index],
+                        light: neighbourhood.light_of_face([x, y, z], face),
+                        lowered: 16 - neighbourhood.height([x, y, z]),
+                    });
+                }
+            }
+
+            // Grow each quad along s first, then along t as long as the whole row matches
+            for ti in 0..SIZE {
+                let mut si = 0;
+
+                while si < SIZE {
+                    let key = match mask[ti][si] {
+                        Some(key) => key,
+                        None => {
+                            si += 1;
+                            continue;
+                        }
+                    };
+
+                    let mut w = 1;
+                    while si + w < SIZE && mask[ti][si + w] == Some(key) {
+                        w += 1;
+                    }
+
+                    // The side faces of a flowing fluid are lowered at the top, they can't be stacked
+                    let can_stack = key.lowered == 0 || face.index == 2 || face.index == 3;
+
+                    let mut h = 1;
+                    while can_stack
+                        && ti + h < SIZE
+                        && mask[ti + h][si..si + w].iter().all(|&k| k == Some(key))
+                    {
+                        h += 1;
+                    }
+
+                    for row in mask[ti..ti + h].iter_mut() {
+                        row[si..si + w].fill(None);
+                    }
+
+                    let uv = uv_of_face(key.block, face.index, uv_map);
+                    let block = face.block_at(slice, si as i32, ti as i32);
+                    let size = (w as i32, h as i32);
+                    let vertices = mesh.layer_mut(key.block.block().render_layer());
+                    push_quad(vertices, face, block, size, uv, key);
+
+                    si += w;
+                }
+            }
+        }
+    }
+
+    mesh
+}
+
+// Appends a quad with corners in 16ths of a block, relative to the chunk
+// `tile_coords` are in 16ths of a tile, the corners go counterclockwise when seen from the front
+fn push_fine_quad(
+    vertices: &mut Vec<u32>,
+    face: usize,
+    corners: [[i32; 3]; 4],
+    tile: u16,
+    tile_coords: [(i32, i32); 4],
+    light: u8,
+) {
+    for corner in [0, 1, 2, 2, 3, 0] {
+        // Rounded up to a whole block, then moved back by the remainder
+        let position = corners[corner].map(|c| (c + 15).div_euclid(16));
+        let inset = |axis: usize| (position[axis] * 16 - corners[corner][axis]) as u8;
+        let (u, v) = tile_coords[corner];
+
+        let vertex = PackedVertex {
+            position: position.map(|c| c as u8),
+            face: face as u8,
+            ao: 0,
+            tile,
+            tile_coords: [u as u8, v as u8],
+            sky_light: light >> 4,
+            block_light: light & 0xF,
+            lowered: inset(1),
+            inset: [inset(0), inset(2)],
+            fine_tile_coords: true,
+        };
+
+        let packed = vertex.encode();
+        debug_assert_eq!(PackedVertex::decode(packed), vertex);
+
+        vertices.extend_from_slice(&packed);
+    }
+}
+
+// The face of a box of a shape, its texture is cut like the face of the cube it is part of
+fn push_box_face(
+    vertices: &mut Vec<u32>,
+    face: &FaceDirection,
+    block: [i32; 3],
+    shape_box: &ShapeBox,
+    (uv, rotation): (UVCoords, u8),
+    light: u8,
+) {
+    let normal_axis = FaceDirection::axis(face.normal);
+    let plane = if face.normal[normal_axis] > 0 {
+        shape_box.maxs[normal_axis]
+    } else {
+        shape_box.mins[normal_axis]
+    };
+
+    // Range of the box along a direction of the face, measured from the origin of the face
+    let origin = face.origin.map(|c| c * 16);
+    let range = |direction: [i32; 3]| {
+        let axis = FaceDirection::axis(direction);
+        let (a, b) = (shape_box.mins[axis] - origin[axis], shape_box.maxs[axis] - origin[axis]);
+        if direction[axis] > 0 {
+            (a, b)
+        } else {
+            (-b, -a)
+        }
+    };
+    let (s0, s1) = range(face.s);
+    let (t0, t1) = range(face.t);
+
+    let corner_coords = [(s0, t0), (s1, t0), (s1, t1), (s0, t1)];
+    let corners = corner_coords.map(|(s, t)| {
+        let mut corner: [i32; 3] =
+            std::array::from_fn(|i| block[i] * 16 + origin[i] + face.s[i] * s + face.t[i] * t);
+        corner[normal_axis] = block[normal_axis] * 16 + plane;
+        corner
+    });
+    let tile_coords = corner_coords.map(|st| rotate_tile_coords(st, (16, 16), rotation));
+
+    push_fine_quad(vertices, face.index, corners, tile_index_of_uv(uv), tile_coords, light);
+}
+
+// 2 diagonal quads, drawn from both sides
+fn push_cross(vertices: &mut Vec<u32>, block: [i32; 3], uv: UVCoords, light: u8) {
+    let [x, y, z] = block.map(|c| c * 16);
+    let tile = tile_index_of_uv(uv);
+    let tile_coords = [(0, 0), (16, 0), (16, 16), (0, 16)];
+
+    for (x0, z0, x1, z1) in [(0, 0, 16, 16), (0, 16, 16, 0)] {
+        let corners = [
+            [x + x0, y, z + z0],
+            [x + x1, y, z + z1],
+            [x + x1, y + 16, z + z1],
+            [x + x0, y + 16, z + z0],
+        ];
+        let [a, b, c, d] = corners;
+        let [ta, tb, tc, td] = tile_coords;
+
+        // Lit like a top face, the plants shouldn't look darker from some angles
+        push_fine_quad(vertices, 2, corners, tile, tile_coords, light);
+        push_fine_quad(vertices, 2, [b, a, d, c], tile, [tb, ta, td, tc], light);
+    }
+}
+
+// Blocks that aren't cubes, each box of their shape is meshed on its own without ambient occlusion
+fn build_shaped_blocks(neighbourhood: &ChunkNeighbourhood, uv_map: &UVMap, mesh: &mut ChunkMesh) {
+    for (x, y, z) in BlockIterator::new() {
+        let block_coords = [x as i32, y as i32, z as i32];
+        let [x, y, z] = block_coords;
+        let state = neighbourhood.get_state(x, y, z);
+        let shape = state.block().shape();
+
+        if shape.is_cube() {
+            continue;
+        }
+
+        // Shapes let the light in, so they are lit by their own light
+        let light = neighbourhood.light_at(block_coords);
+        let vertices = mesh.layer_mut(state.block().render_layer());
+
+        if shape == BlockShape::Cross {
+            let (uv, _) = uv_of_face(state, 0, uv_map);
+            push_cross(vertices, block_coords, uv, light);
+            continue;
+        }
+
+        let connections = fence_connections(|dx, dz| Some(neighbourhood.get(x + dx, y, z + dz)));
+
+        for shape_box in shape.model_boxes(state, connections) {
+            for face in FACE_DIRECTIONS.iter() {
+                // The sides of the block are hidden by their neighbours like the faces of the cubes
+                if shape_box.touches_side(face.normal)
+                    && neighbourhood.is_face_hidden(block_coords, face)
+                {
+                    continue;
+                }
+
+                let uv = uv_of_face(state, face.index, uv_map);
+                push_box_face(vertices, face, block_coords, &shape_box, uv, light);
+            }
+        }
+    }
+}
+
+/*
+    Translucent faces are blended with what is already drawn, so they must be drawn from the farthest to the nearest
+    Sorts the quads of `vertices` by the distance between their centre and `camera`, both relative to the chunk
+*/
+pub fn sort_translucent_quads(vertices: &mut [u32], camera: Vec3) {
+    const QUAD_SIZE: usize = 6 * VERTEX_SIZE;
+
+    let mut quads: Vec<(f32, [u32; QUAD_SIZE])> = vertices
+        .chunks_exact(QUAD_SIZE)
+        .map(|quad| {
+            // The first and third vertices are opposite corners of the quad
+            let corner = |i: usize| {
+                let [x, y, z] =
+                    PackedVertex::decode([quad[i * VERTEX_SIZE], quad[i * VERTEX_SIZE + 1]])
+                        .position;
+                vec3(x as f32, y as f32, z as f32)
+            };
+            let centre = (corner(0) + corner(2)) * 0.5;
+
+            (nalgebra_glm::distance2(&centre, &camera), quad.try_into().unwrap())
+        })
+        .collect();
+
+    quads.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+    for (dst, (_, quad)) in vertices.chunks_exact_mut(QUAD_SIZE).zip(quads) {
+        dst.copy_from_slice(&quad);
+    }
+}
+
+// Generated chunks within `radius` columns of the origin, and a border of neighbours around them
+fn generate_area(
+    generator: &dyn WorldGenerator,
+    radius: i32,
+) -> HashMap<(i32, i32, i32), Box<BlockArray>> {
+    let mut chunks = HashMap::new();
+
+    for x in -radius - 1..=radius + 1 {
+        for z in -radius - 1..=radius + 1 {
+            for y in 0..WORLD_HEIGHT_IN_CHUNKS {
+                chunks.insert((x, y, z), generator.generate_chunk((x, y, z)));
+            }
+        }
+    }
+
+    chunks
+}
+
+// Only the number of vertices matters, every block gets the same texture
+fn single_tile_uv_map() -> UVMap {
+    (0..=u8::MAX)
+        .filter_map(BlockID::from_u8)
+        .map(|block| (block, BlockFaces::All((0.0, 0.0, 1.0, 1.0))))
+        .collect()
+}
+
+fn neighbourhood_in(
+    chunks: &HashMap<(i32, i32, i32), Box<BlockArray>>,
+    (x, y, z): (i32, i32, i32),
+) -> ChunkNeighbourhood {
+    let mut neighbours = [None; 27];
+
+    for dy in -1..=1 {
+        for dz in -1..=1 {
+            for dx in -1..=1 {
+                neighbours[ChunkNeighbourhood::neighbour_index(dx, dy, dz)] =
+                    chunks.get(&(x + dx, y + dy, z + dz)).map(|c| c.as_ref());
+            }
+        }
+    }
+
+    ChunkNeighbourhood::from_block_arrays(&neighbours)
+}
+
+// Compares the vertex count of both meshing methods on generated terrain
+// Run the game with `--mesh-benchmark` to print the results
+pub fn run_meshing_benchmark(generator: &dyn WorldGenerator, radius: i32) {
+    let chunks = generate_area(generator, radius);
+    let uv_map = single_tile_uv_map();
+
+    println!("Meshing {} chunks", (2 * radius + 1).pow(2) * WORLD_HEIGHT_IN_CHUNKS);
+
+    for method in [MeshingMethod::Naive, MeshingMethod::Greedy] {
+        let start = Instant::now();
+        let mut vertices = 0;
+
+        for x in -radius..=radius {
+            for z in -radius..=radius {
+                for y in 0..WORLD_HEIGHT_IN_CHUNKS {
+                    let neighbourhood = neighbourhood_in(&chunks, (x, y, z));
+                    vertices += build_chunk_mesh(&neighbourhood, &uv_map, method).vertex_count();
+                }
+            }
+        }
+
+        println!(
+            "{:?}: {} vertices ({} quads, {} KiB) in {:?}",
+            method,
+            vertices,
+            vertices / 6,
+            vertices * VERTEX_SIZE * std::mem::size_of::<u32>() / 1024,
+            start.elapsed()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_registry::install_test_registry;
+    use crate::world_generator::TerrainGenerator;
+
+    // Lighting and ambient occlusion of the corners, sky and block light and lowered top
+    type FaceLook = ([u8; 4], u8, u8, u8);
+
+    // Splits the quads of the cubes into the faces of single blocks, by layer, face and block
+    fn unit_faces(mesh: &ChunkMesh) -> HashMap<(usize, u8, [i32; 3]), FaceLook> {
+        let mut faces = HashMap::new();
+
+        let layers = [&mesh.opaque, &mesh.cutout, &mesh.translucent];
+
+        for (layer, vertices) in layers.iter().enumerate() {
+            for quad in vertices.chunks_exact(6 * VERTEX_SIZE) {
+                let vertex = |i: usize| {
+                    PackedVertex::decode([quad[i * VERTEX_SIZE], quad[i * VERTEX_SIZE + 1]])
+                };
+                // The first and third vertices are opposite corners, the fifth is the last one
+                let (first, opposite) = (vertex(0), vertex(2));
+                let look = (
+                    [0, 1, 2, 4].map(|i| vertex(i).ao),
+                    first.sky_light,
+                    first.block_light,
+                    first.lowered,
+                );
+
+                let mins = [0, 1, 2].map(|i| first.position[i].min(opposite.position[i]) as i32);
+                let maxs = [0, 1, 2].map(|i| first.position[i].max(opposite.position[i]) as i32);
+                // The quad is flat along its normal, it covers one row of blocks on that axis
+                let ends: [i32; 3] =
+                    std::array::from_fn(|i| maxs[i] + (mins[i] == maxs[i]) as i32);
+
+                for x in mins[0]..ends[0] {
+                    for y in mins[1]..ends[1] {
+                        for z in mins[2]..ends[2] {
+                            let previous = faces.insert((layer, first.face, [x, y, z]), look);
+                            assert!(previous.is_none(), "overlapping quads at {x} {y} {z}");
+                        }
+                    }
+                }
+            }
+        }
+
+        faces
+    }
+
+    #[test]
+    fn greedy_meshes_cover_the_same_faces_with_fewer_vertices() {
+        install_test_registry();
+        let radius = 1;
+        let chunks = generate_area(&TerrainGenerator::new(42), radius);
+        let uv_map = single_tile_uv_map();
+        let (mut naive_vertices, mut greedy_vertices) = (0, 0);
+
+        for x in -radius..=radius {
+            for z in -radius..=radius {
+                for y in 0..WORLD_HEIGHT_IN_CHUNKS {
+                    let neighbourhood = neighbourhood_in(&chunks, (x, y, z));
+                    let naive = build_naive_mesh(&neighbourhood, &uv_map);
+                    let greedy = build_greedy_mesh(&neighbourhood, &uv_map);
+
+                    assert!(greedy.vertex_count() <= naive.vertex_count(), "{:?}", (x, y, z));
+                    assert!(unit_faces(&greedy) == unit_faces(&naive), "{:?}", (x, y, z));
+
+                    naive_vertices += naive.vertex_count();
+                    greedy_vertices += greedy.vertex_count();
+                }
+            }
+        }
+
+        assert!(greedy_vertices < naive_vertices);
+    }
+}
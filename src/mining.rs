@@ -0,0 +1,269 @@
+use crate::chunk::BlockID;
+use crate::inventory::item::Tool;
+
+/*
+    Survival players hold the left button to break a block, creative players break it at once
+
+    The time it takes depends on the hardness of the block and on the tool in the hand :
+
+        seconds = hardness * 1.5 / speed    when the block can be harvested, it will drop
+        seconds = hardness * 5.0 / speed    otherwise, like stone mined by hand
+
+    where speed is the efficiency of the tier of the tool when the block is made for it, 1 otherwise
+    A block with a `tool_tier` can only be harvested with its tool, of that tier or a better one
+
+    Looking away or releasing the button starts over, and there is a short cooldown after each block
+    The cracks drawn on the block show the progress in DESTROY_STAGES steps
+*/
+
+pub const DESTROY_STAGES: u32 = 10;
+
+const HARVEST_FACTOR: f32 = 1.5;
+const NO_HARVEST_FACTOR: f32 = 5.0;
+// Seconds before the player starts mining the next block
+const MINING_COOLDOWN: f32 = 0.25;
+
+type BlockCoords = (i32, i32, i32);
+
+// Whether the block drops something when it is broken with `tool`
+pub fn can_harvest(block: BlockID, tool: Option<Tool>) -> bool {
+    let definition = block.definition();
+
+    match definition.tool_tier {
+        None => true,
+        Some(tier) => tool.map_or(false, |tool| {
+            Some(tool.kind) == definition.tool && tool.tier >= tier
+        }),
+    }
+}
+
+// Seconds it takes to break the block, None when it can't be broken
+pub fn mining_time(block: BlockID, tool: Option<Tool>) -> Option<f32> {
+    if !block.is_breakable() {
+        return None;
+    }
+
+    let definition = block.definition();
+    let speed = match tool {
+        Some(tool) if Some(tool.kind) == definition.tool => tool.tier.efficiency(),
+        _ => 1.0,
+    };
+    let factor = if can_harvest(block, tool) {
+        HARVEST_FACTOR
+    } else {
+        NO_HARVEST_FACTOR
+    };
+
+    Some(definition.hardness * factor / speed)
+}
+
+// Progress of the block the player is mining
+#[derive(Default)]
+pub struct BlockMining {
+    target: Option<BlockCoords>,
+    // Seconds spent on the target, and seconds needed to break it
+    elapsed: f32,
+    duration: f32,
+    // Seconds left before the player can mine again
+    cooldown: f32,
+}
+
+impl BlockMining {
+    // Mines `target` for `dt` more seconds, returns true when it breaks
+    // `duration` comes from `mining_time`, a different target starts over
+    pub fn update(&mut self, target: BlockCoords, duration: Option<f32>, dt: f32) -> bool {
+        self.cooldown = (self.cooldown - dt).max(0.0);
+
+        if self.target != Some(target) {
+            self.target = Some(target);
+            self.elapsed = 0.0;
+        }
+
+        let duration = match duration {
+            Some(duration) => duration,
+            None => {
+                self.stop();
+                return false;
+            }
+        };
+        self.duration = duration;
+
+        if self.cooldown > 0.0 {
+            return false;
+        }
+
+        self.elapsed += dt;
+        if self.elapsed < duration {
+            return false;
+        }
+
+        self.stop();
+        self.cooldown = MINING_COOLDOWN;
+        true
+    }
+
+    // The button was released or the player doesn't look at a block anymore
+    pub fn stop(&mut self) {
+        self.target = None;
+        self.elapsed = 0.0;
+    }
+
+    // From 0 to DESTROY_STAGES - 1, None when the block at `coords` isn't being mined
+    pub fn crack_stage(&self, coords: BlockCoords) -> Option<u32> {
+        if self.target != Some(coords) || self.elapsed <= 0.0 {
+            return None;
+        }
+
+        let progress = self.elapsed / self.duration;
+        Some(((progress * DESTROY_STAGES as f32) as u32).min(DESTROY_STAGES - 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_registry::install_test_registry;
+    use crate::inventory::item::{ToolKind, ToolTier};
+
+    fn block(name: &str) -> BlockID {
+        install_test_registry();
+        BlockID::named(name)
+    }
+
+    fn tool(kind: ToolKind, tier: ToolTier) -> Option<Tool> {
+        Some(Tool::new(kind, tier))
+    }
+
+    fn assert_time(time: Option<f32>, expected: f32) {
+        let time = time.expect("the block should be breakable");
+        assert!((time - expected).abs() < 1e-5, "{time} instead of {expected}");
+    }
+
+    #[test]
+    fn mining_by_hand() {
+        assert_time(mining_time(block("dirt"), None), 0.5 * HARVEST_FACTOR);
+        assert_time(mining_time(block("glass"), None), 0.3 * HARVEST_FACTOR);
+        assert_time(mining_time(block("oak_log"), None), 2.0 * HARVEST_FACTOR);
+    }
+
+    #[test]
+    fn each_tool_tier_mines_faster() {
+        let mut previous = f32::INFINITY;
+
+        for tier in ToolTier::ALL {
+            let time = mining_time(block("stone"), tool(ToolKind::Pickaxe, tier));
+            assert_time(time, 1.5 * HARVEST_FACTOR / tier.efficiency());
+            assert!(time.unwrap() < previous);
+            previous = time.unwrap();
+
+            let time = mining_time(block("dirt"), tool(ToolKind::Shovel, tier));
+            assert_time(time, 0.5 * HARVEST_FACTOR / tier.efficiency());
+            let time = mining_time(block("oak_log"), tool(ToolKind::Axe, tier));
+            assert_time(time, 2.0 * HARVEST_FACTOR / tier.efficiency());
+        }
+    }
+
+    #[test]
+    fn the_wrong_tool_mines_like_a_hand() {
+        let pickaxe = tool(ToolKind::Pickaxe, ToolTier::Diamond);
+
+        assert_time(mining_time(block("dirt"), pickaxe), 0.5 * HARVEST_FACTOR);
+        assert_time(mining_time(block("glass"), pickaxe), 0.3 * HARVEST_FACTOR);
+        assert!(can_harvest(block("dirt"), pickaxe));
+    }
+
+    #[test]
+    fn blocks_that_cant_be_harvested_are_slower() {
+        assert!(!can_harvest(block("stone"), None));
+        assert_time(mining_time(block("stone"), None), 1.5 * NO_HARVEST_FACTOR);
+
+        let shovel = tool(ToolKind::Shovel, ToolTier::Diamond);
+        assert!(!can_harvest(block("stone"), shovel));
+        assert_time(mining_time(block("stone"), shovel), 1.5 * NO_HARVEST_FACTOR);
+
+        // A pickaxe of a lower tier is still faster than a hand, but nothing drops
+        let stone_pickaxe = tool(ToolKind::Pickaxe, ToolTier::Stone);
+        assert!(!can_harvest(block("diamond_ore"), stone_pickaxe));
+        assert_time(
+            mining_time(block("diamond_ore"), stone_pickaxe),
+            3.0 * NO_HARVEST_FACTOR / ToolTier::Stone.efficiency(),
+        );
+
+        let iron_pickaxe = tool(ToolKind::Pickaxe, ToolTier::Iron);
+        assert!(can_harvest(block("diamond_ore"), iron_pickaxe));
+        assert_time(
+            mining_time(block("diamond_ore"), iron_pickaxe),
+            3.0 * HARVEST_FACTOR / ToolTier::Iron.efficiency(),
+        );
+        assert!(!can_harvest(block("obsidian"), iron_pickaxe));
+    }
+
+    #[test]
+    fn unbreakable_blocks_are_never_mined() {
+        assert_eq!(mining_time(block("bedrock"), None), None);
+        for tier in ToolTier::ALL {
+            for kind in ToolKind::ALL {
+                assert_eq!(mining_time(block("bedrock"), tool(kind, tier)), None);
+            }
+        }
+
+        let mut mining = BlockMining::default();
+        for _ in 0..100 {
+            assert!(!mining.update((0, 0, 0), None, 1.0));
+        }
+        assert_eq!(mining.crack_stage((0, 0, 0)), None);
+    }
+
+    #[test]
+    fn crack_stages_go_from_0_to_the_last_one() {
+        let mut mining = BlockMining::default();
+        let duration = Some(DESTROY_STAGES as f32);
+        assert_eq!(mining.crack_stage((0, 0, 0)), None);
+
+        assert!(!mining.update((0, 0, 0), duration, 0.5));
+        assert_eq!(mining.crack_stage((0, 0, 0)), Some(0));
+        assert_eq!(mining.crack_stage((1, 0, 0)), None);
+
+        for stage in 1..DESTROY_STAGES {
+            assert!(!mining.update((0, 0, 0), duration, 1.0));
+            assert_eq!(mining.crack_stage((0, 0, 0)), Some(stage));
+        }
+
+        assert!(mining.update((0, 0, 0), duration, 1.0));
+        assert_eq!(mining.crack_stage((0, 0, 0)), None);
+    }
+
+    #[test]
+    fn a_different_target_starts_over() {
+        let mut mining = BlockMining::default();
+
+        assert!(!mining.update((0, 0, 0), Some(1.0), 0.6));
+        assert!(!mining.update((1, 0, 0), Some(1.0), 0.6));
+        assert_eq!(mining.crack_stage((0, 0, 0)), None);
+        assert_eq!(mining.crack_stage((1, 0, 0)), Some(6));
+
+        // Coming back to the first block starts over too
+        assert!(!mining.update((0, 0, 0), Some(1.0), 0.6));
+        assert!(mining.update((0, 0, 0), Some(1.0), 0.6));
+    }
+
+    #[test]
+    fn stopping_starts_over() {
+        let mut mining = BlockMining::default();
+
+        assert!(!mining.update((0, 0, 0), Some(1.0), 0.6));
+        mining.stop();
+        assert_eq!(mining.crack_stage((0, 0, 0)), None);
+        assert!(!mining.update((0, 0, 0), Some(1.0), 0.6));
+    }
+
+    #[test]
+    fn the_next_block_waits_for_the_cooldown() {
+        let mut mining = BlockMining::default();
+
+        assert!(mining.update((0, 0, 0), Some(0.1), 0.1));
+        assert!(!mining.update((1, 0, 0), Some(0.1), MINING_COOLDOWN - 0.05));
+        assert_eq!(mining.crack_stage((1, 0, 0)), None);
+        assert!(mining.update((1, 0, 0), Some(0.1), 0.1));
+    }
+}
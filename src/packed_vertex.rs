@@ -0,0 +1,234 @@
+use crate::constants::{BLOCK_TEXTURE_SIZE, TEXTURE_ATLAS_SIZE};
+use crate::types::UVCoords;
+
+/*
+    Chunk vertices are packed into 2 u32s (8 bytes instead of 36 with floats)
+    voxel.vert decodes them with the same layout
+
+    Word 0 :
+        bits  0..5   x, from 0 to 16
+        bits  5..10  y, from 0 to 16
+        bits 10..15  z, from 0 to 16
+        bits 15..18  face index, same order as the ambient occlusion (right, left, top, bottom, front, back)
+        bits 18..20  ambient occlusion, from 0 to 3
+        bits 20..24  sky light, from 0 to 15
+        bits 24..28  block light, from 0 to 15
+        bits 28..32  how far the vertex is lowered, in 16ths of a block (top of the flowing fluids, slabs)
+
+    Word 1 :
+        bits  0..12  tile index in the atlas, x + y * TILES_PER_ROW
+        bits 12..17  position inside the tile along the u axis, from 0 to 16 (the tile repeats on merged quads)
+        bits 17..22  position inside the tile along the v axis, from 0 to 16
+        bits 22..26  how far the vertex is moved towards -x, in 16ths of a block
+        bits 26..30  how far the vertex is moved towards -z, in 16ths of a block
+        bit  30      the position inside the tile is in 16ths of a tile (blocks that aren't cubes)
+        bits 31..32  unused
+*/
+
+// Number of u32 per vertex
+pub const VERTEX_SIZE: usize = 2;
+
+pub const TILES_PER_ROW: u32 = TEXTURE_ATLAS_SIZE / BLOCK_TEXTURE_SIZE;
+
+const POSITION_BITS: u32 = 5;
+const FACE_BITS: u32 = 3;
+const AO_BITS: u32 = 2;
+const LIGHT_BITS: u32 = 4;
+const TILE_BITS: u32 = 12;
+const TILE_COORD_BITS: u32 = 5;
+const LOWERED_BITS: u32 = 4;
+const INSET_BITS: u32 = 4;
+
+const FACE_SHIFT: u32 = 3 * POSITION_BITS;
+const AO_SHIFT: u32 = FACE_SHIFT + FACE_BITS;
+const SKY_LIGHT_SHIFT: u32 = AO_SHIFT + AO_BITS;
+const BLOCK_LIGHT_SHIFT: u32 = SKY_LIGHT_SHIFT + LIGHT_BITS;
+const LOWERED_SHIFT: u32 = BLOCK_LIGHT_SHIFT + LIGHT_BITS;
+const TILE_COORD_SHIFT: u32 = TILE_BITS;
+const INSET_SHIFT: u32 = TILE_COORD_SHIFT + 2 * TILE_COORD_BITS;
+const FINE_TILE_COORDS_SHIFT: u32 = INSET_SHIFT + 2 * INSET_BITS;
+
+const fn mask(bits: u32) -> u32 {
+    (1 << bits) - 1
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PackedVertex {
+    pub position: [u8; 3],
+    pub face: u8,
+    pub ao: u8,
+    pub tile: u16,
+    pub tile_coords: [u8; 2],
+    pub sky_light: u8,
+    pub block_light: u8,
+    pub lowered: u8,
+    // Moves the vertex towards -x and -z, like `lowered` does for y
+    pub inset: [u8; 2],
+    pub fine_tile_coords: bool,
+}
+
+impl PackedVertex {
+    pub fn encode(&self) -> [u32; VERTEX_SIZE] {
+        debug_assert!(self.position.iter().all(|&c| c as u32 <= mask(POSITION_BITS)));
+        debug_assert!((self.face as u32) <= mask(FACE_BITS));
+        debug_assert!((self.ao as u32) <= mask(AO_BITS));
+        debug_assert!((self.tile as u32) <= mask(TILE_BITS));
+        debug_assert!(self.tile_coords.iter().all(|&c| c as u32 <= mask(TILE_COORD_BITS)));
+        debug_assert!((self.sky_light as u32) <= mask(LIGHT_BITS));
+        debug_assert!((self.block_light as u32) <= mask(LIGHT_BITS));
+        debug_assert!((self.lowered as u32) <= mask(LOWERED_BITS));
+        debug_assert!(self.inset.iter().all(|&c| c as u32 <= mask(INSET_BITS)));
+
+        let [x, y, z] = self.position.map(|c| c as u32);
+        let [u, v] = self.tile_coords.map(|c| c as u32);
+        let [inset_x, inset_z] = self.inset.map(|c| c as u32);
+
+        [
+            x | (y << POSITION_BITS)
+                | (z << (2 * POSITION_BITS))
+                | ((self.face as u32) << FACE_SHIFT)
+                | ((self.ao as u32) << AO_SHIFT)
+                | ((self.sky_light as u32) << SKY_LIGHT_SHIFT)
+                | ((self.block_light as u32) << BLOCK_LIGHT_SHIFT)
+                | ((self.lowered as u32) << LOWERED_SHIFT),
+            self.tile as u32
+                | (u << TILE_COORD_SHIFT)
+                | (v << (TILE_COORD_SHIFT + TILE_COORD_BITS))
+                | (inset_x << INSET_SHIFT)
+                | (inset_z << (INSET_SHIFT + INSET_BITS))
+                | ((self.fine_tile_coords as u32) << FINE_TILE_COORDS_SHIFT),
+        ]
+    }
+
+    pub fn decode([word0, word1]: [u32; VERTEX_SIZE]) -> Self {
+        let position_mask = mask(POSITION_BITS);
+        let tile_coord_mask = mask(TILE_COORD_BITS);
+
+        Self {
+            position: [
+                (word0 & position_mask) as u8,
+                ((word0 >> POSITION_BITS) & position_mask) as u8,
+                ((word0 >> (2 * POSITION_BITS)) & position_mask) as u8,
+            ],
+            face: ((word0 >> FACE_SHIFT) & mask(FACE_BITS)) as u8,
+            ao: ((word0 >> AO_SHIFT) & mask(AO_BITS)) as u8,
+            tile: (word1 & mask(TILE_BITS)) as u16,
+            tile_coords: [
+                ((word1 >> TILE_COORD_SHIFT) & tile_coord_mask) as u8,
+                ((word1 >> (TILE_COORD_SHIFT + TILE_COORD_BITS)) & tile_coord_mask) as u8,
+            ],
+            sky_light: ((word0 >> SKY_LIGHT_SHIFT) & mask(LIGHT_BITS)) as u8,
+            block_light: ((word0 >> BLOCK_LIGHT_SHIFT) & mask(LIGHT_BITS)) as u8,
+            lowered: ((word0 >> LOWERED_SHIFT) & mask(LOWERED_BITS)) as u8,
+            inset: [
+                ((word1 >> INSET_SHIFT) & mask(INSET_BITS)) as u8,
+                ((word1 >> (INSET_SHIFT + INSET_BITS)) & mask(INSET_BITS)) as u8,
+            ],
+            fine_tile_coords: (word1 >> FINE_TILE_COORDS_SHIFT) & 1 != 0,
+        }
+    }
+}
+
+// Index of the atlas tile starting at the bottom left corner of `uv`
+pub fn tile_index_of_uv(uv: UVCoords) -> u16 {
+    let x = (uv.0 * TILES_PER_ROW as f32).round() as u32;
+    let y = (uv.1 * TILES_PER_ROW as f32).round() as u32;
+
+    (x + y * TILES_PER_ROW) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ZERO: PackedVertex = PackedVertex {
+        position: [0, 0, 0],
+        face: 0,
+        ao: 0,
+        tile: 0,
+        tile_coords: [0, 0],
+        sky_light: 0,
+        block_light: 0,
+        lowered: 0,
+        inset: [0, 0],
+        fine_tile_coords: false,
+    };
+
+    fn assert_round_trip(vertex: PackedVertex) {
+        assert_eq!(PackedVertex::decode(vertex.encode()), vertex);
+    }
+
+    // Each field alone at its limits, the other fields must stay at 0
+    #[test]
+    fn fields_round_trip_at_their_limits() {
+        assert_round_trip(ZERO);
+
+        for c in [0, 1, 15, 16] {
+            for axis in 0..3 {
+                let mut position = [0; 3];
+                position[axis] = c;
+                assert_round_trip(PackedVertex { position, ..ZERO });
+            }
+
+            assert_round_trip(PackedVertex { tile_coords: [c, 0], ..ZERO });
+            assert_round_trip(PackedVertex { tile_coords: [0, c], ..ZERO });
+        }
+
+        for face in 0..6 {
+            assert_round_trip(PackedVertex { face, ..ZERO });
+        }
+
+        for ao in 0..=3 {
+            assert_round_trip(PackedVertex { ao, ..ZERO });
+        }
+
+        let last_tile = (TILES_PER_ROW * TILES_PER_ROW - 1) as u16;
+        for tile in [0, 1, TILES_PER_ROW as u16, last_tile] {
+            assert_round_trip(PackedVertex { tile, ..ZERO });
+        }
+
+        for light in [0, 15] {
+            assert_round_trip(PackedVertex { sky_light: light, ..ZERO });
+            assert_round_trip(PackedVertex { block_light: light, ..ZERO });
+        }
+
+        for c in [0, 1, 8, 15] {
+            assert_round_trip(PackedVertex { lowered: c, ..ZERO });
+            assert_round_trip(PackedVertex { inset: [c, 0], ..ZERO });
+            assert_round_trip(PackedVertex { inset: [0, c], ..ZERO });
+        }
+
+        assert_round_trip(PackedVertex { fine_tile_coords: true, ..ZERO });
+    }
+
+    #[test]
+    fn every_field_at_its_maximum_round_trips() {
+        let vertex = PackedVertex {
+            position: [16, 16, 16],
+            face: 5,
+            ao: 3,
+            tile: (TILES_PER_ROW * TILES_PER_ROW - 1) as u16,
+            tile_coords: [16, 16],
+            sky_light: 15,
+            block_light: 15,
+            lowered: 15,
+            inset: [15, 15],
+            fine_tile_coords: true,
+        };
+
+        assert_round_trip(vertex);
+        // The unused last bit stays clear
+        assert_eq!(vertex.encode()[1] >> 31, 0);
+    }
+
+    #[test]
+    fn every_atlas_tile_fits() {
+        assert!(TILES_PER_ROW * TILES_PER_ROW <= 1 << TILE_BITS);
+
+        let tile_size = 1.0 / TILES_PER_ROW as f32;
+        let last = TILES_PER_ROW - 1;
+        let uv = (last as f32 * tile_size, last as f32 * tile_size, 1.0, 1.0);
+        assert_eq!(tile_index_of_uv(uv) as u32, last + last * TILES_PER_ROW);
+        assert_eq!(tile_index_of_uv((0.0, 0.0, tile_size, tile_size)), 0);
+    }
+}
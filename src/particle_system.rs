@@ -168,7 +168,7 @@ impl Interpolator<ParticlePhysicsProperties> {
                 let mut colliding_block_aabb = None;
 
                 if let Some(block) = chunk_manager.get_block(containing_block.x, containing_block.y, containing_block.z) {
-                    if block.is_air() {
+                    if !block.is_solid() {
                         continue;
                     }
 
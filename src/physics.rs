@@ -1,10 +1,10 @@
 use crate::input::InputCache;
 use crate::player::{PlayerPhysicsState, PlayerProperties};
+use crate::survival::{fall_damage, GameMode, PlayerStats};
 use crate::{chunk_manager, time, GRAVITY};
-use nalgebra_glm::{vec3, Vec3};
 use std::time::{Instant};
 
-use crate::constants::PLAYER_HALF_WIDTH;
+use crate::constants::FLUID_GRAVITY_FACTOR;
 
 pub trait Interpolatable {
     fn interpolate(&self, other: &Self, alpha: f32) -> Self;
@@ -40,6 +40,12 @@ impl<T:Clone + Interpolatable> Interpolator<T> {
     pub fn get_current_state(&mut self) -> &mut T {
         &mut self.current_state
     }
+
+    // Jumps to `state` without interpolating from the previous one
+    pub fn reset(&mut self, state: T) {
+        self.previous_state = state.clone();
+        self.current_state = state;
+    }
 }
 
 impl<T: Clone + Interpolatable> Interpolator<T> {
@@ -78,46 +84,60 @@ impl Interpolator<PlayerPhysicsState> {
         input_cache: &InputCache,
         chunk_manager: &chunk_manager::ChunkManager,
         player_properties: &mut PlayerProperties,
+        player_stats: &mut PlayerStats,
     ) -> PlayerPhysicsState {
         self.step(time, &mut |player: &PlayerPhysicsState, _t: f32, dt: f32| {
             let mut player = player.clone();
-            
+
+            if player_properties.game_mode == GameMode::Spectator {
+                player_properties.update_movement_mode(input_cache);
+                player.update_eyes_height(false, dt);
+                player.apply_spectator_movement(player_properties, input_cache);
+                player.move_without_collisions(player.velocity * dt);
+                return player;
+            }
+
+            player.is_in_fluid = player.is_touching_fluid(chunk_manager);
+
+            // Fluids hold the player up, they slowly sink when not swimming
             if !player_properties.is_flying {
-                player.acceleration.y += GRAVITY;
+                player.acceleration.y += if player.is_in_fluid {
+                    GRAVITY * FLUID_GRAVITY_FACTOR
+                } else {
+                    GRAVITY
+                };
             }
 
+            player_properties.update_movement_mode(input_cache);
+            player.update_eyes_height(player_properties.is_sneaking, dt);
             player.apply_keyboard_movement(&player_properties, input_cache);
             player.velocity += player.acceleration * dt;
             player.apply_fricition(dt, player_properties.is_flying);
             player.limit_velocitiy(&player_properties);
 
-            let mut is_player_on_ground = false;
-
             if player.is_on_ground {
                 player_properties.is_flying = false;
             }
 
-            let separated_axis = &[
-                vec3(player.velocity.x, 0.0, 0.0),
-                vec3(0.0, player.velocity.y, 0.0),
-                vec3(0.0, 0.0, player.velocity.z),
-            ];
-
-            for v in separated_axis {
-                player.aabb.translate(&(v * dt));
-                let block_collided = player.get_colliding_block_coords(chunk_manager);
+            let was_on_ground = player.is_on_ground;
+            let falling_speed = -player.velocity.y;
+            let offset = player.velocity * dt;
+            player.move_and_collide(chunk_manager, offset, player_properties);
 
-                // Reaction
-                if let Some(block_collided) = block_collided {
-                    is_player_on_ground |= player.separate_from_block(&v, &block_collided);
+            if player_properties.game_mode.is_survival() {
+                // Fluids break the fall
+                if player.is_on_ground && !was_on_ground && !player.is_in_fluid {
+                    player_stats.damage(fall_damage(falling_speed));
                 }
-            }
 
-            player.position.x = player.aabb.mins.x + PLAYER_HALF_WIDTH;
-            player.position.y = player.aabb.mins.y;
-            player.position.z = player.aabb.mins.z + PLAYER_HALF_WIDTH;
-
-            player.is_on_ground = is_player_on_ground;
+                player_stats.add_movement_exhaustion(
+                    &offset,
+                    was_on_ground,
+                    player.is_in_fluid,
+                    player_properties.is_sprinting,
+                );
+                player_stats.update(&player, chunk_manager, dt);
+            }
 
             player.acceleration.x = 0.0;
             player.acceleration.y = 0.0;
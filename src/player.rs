@@ -1,11 +1,16 @@
-use crate::aabb::{get_block_aabb, AABB};
+use crate::aabb::AABB;
 use crate::chunk_manager::ChunkManager;
 use crate::constants::{
-    HORIZONTAL_ACCELERATION, IN_AIR_FRICTION, JUMP_IMPULSE, MAX_VERTICAL_VELOCITY,
-    MOUSE_SENSITIVITY_X, MOUSE_SENSITIVITY_Y, ON_GROUND_FRICTION, PLAYER_WIDTH, WALKING_SPEED,
+    HORIZONTAL_ACCELERATION, IN_AIR_FRICTION, IN_FLUID_FRICTION, JUMP_IMPULSE,
+    MAX_SPECTATOR_SPEED, MAX_VERTICAL_VELOCITY, MIN_SPECTATOR_SPEED, MOUSE_SENSITIVITY_X,
+    MOUSE_SENSITIVITY_Y, ON_GROUND_FRICTION, SNEAKING_EYES_HEIGHT, SNEAKING_SPEED,
+    SPECTATOR_SPEED, SPECTATOR_SPEED_STEP, SPRINTING_SPEED, STEP_HEIGHT, SWIMMING_SPEED,
+    SWIM_ACCELERATION, SWIM_OUT_SPEED, WALKING_SPEED,
 };
+use crate::fluids::fluid_height;
 use crate::input::InputCache;
 use crate::physics::Interpolatable;
+use crate::survival::GameMode;
 use crate::{FLYING_SPEED, PLAYER_EYES_HEIGHT, PLAYER_HALF_WIDTH, PLAYER_HEIGHT};
 use glfw::Key;
 use nalgebra::{clamp, Vector3};
@@ -17,14 +22,27 @@ use crate::util::Forward;
 
 pub struct PlayerProperties {
     pub rotation: Vec3,
+    pub game_mode: GameMode,
     pub is_flying: bool,
+    pub is_sprinting: bool,
+    // Holding Shift while not flying, the player is slower and doesn't fall off the edges
+    pub is_sneaking: bool,
+    // Highest block the player walks up without jumping, 0 to never step up
+    pub step_height: f32,
+    // Changed with the scroll wheel
+    pub spectator_speed: f32,
 }
 
 impl PlayerProperties {
     pub fn new() -> Self {
         Self {
             rotation: vec3(0.0, 0.0, 0.0),
+            game_mode: GameMode::Survival,
             is_flying: false,
+            is_sprinting: false,
+            is_sneaking: false,
+            step_height: STEP_HEIGHT,
+            spectator_speed: SPECTATOR_SPEED,
         }
     }
 
@@ -38,6 +56,28 @@ impl PlayerProperties {
             pi::<f32>() / 2.0 - 0.0001,
         );
     }
+
+    pub fn change_spectator_speed(&mut self, scroll: f32) {
+        self.spectator_speed = (self.spectator_speed * SPECTATOR_SPEED_STEP.powf(scroll))
+            .clamp(MIN_SPECTATOR_SPEED, MAX_SPECTATOR_SPEED);
+    }
+
+    // Shift makes the player go down when flying instead of sneaking
+    // Sprinting starts with Ctrl or a double tap on W (see main.rs) and lasts while going forward
+    pub fn update_movement_mode(&mut self, input_cache: &InputCache) {
+        let is_going_forward = input_cache.is_key_pressed(Key::W);
+        self.is_sneaking = !self.is_flying
+            && self.game_mode != GameMode::Spectator
+            && input_cache.is_key_pressed(Key::LeftShift);
+
+        if is_going_forward && input_cache.is_key_pressed(Key::LeftControl) {
+            self.is_sprinting = true;
+        }
+
+        if !is_going_forward || self.is_sneaking {
+            self.is_sprinting = false;
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -46,7 +86,13 @@ pub struct PlayerPhysicsState {
     pub aabb: AABB,
     pub velocity: Vec3,
     pub acceleration: Vec3,
+    // Height of the camera above the feet, lower when sneaking
+    pub eyes_height: f32,
     pub is_on_ground: bool,
+    // Touching water or lava, the player swims instead of walking
+    pub is_in_fluid: bool,
+    // Blocked by a wall along x or z during the last step, used to climb out of the water
+    pub is_against_wall: bool,
 }
 
 impl Interpolatable for PlayerPhysicsState{
@@ -63,7 +109,10 @@ impl Interpolatable for PlayerPhysicsState{
             },
             velocity: interpolate_vec3(&self.velocity, &other.velocity),
             acceleration: interpolate_vec3(&self.acceleration, &other.acceleration),
+            eyes_height: self.eyes_height.interpolate(&other.eyes_height, alpha),
             is_on_ground: other.is_on_ground,
+            is_in_fluid: other.is_in_fluid,
+            is_against_wall: other.is_against_wall,
         }
     }
 }
@@ -88,12 +137,27 @@ impl PlayerPhysicsState {
             },
             velocity: vec3(0.0, 0.0, 0.0),
             acceleration: vec3(0.0, 0.0, 0.0),
+            eyes_height: PLAYER_EYES_HEIGHT,
             is_on_ground: false,
+            is_in_fluid: false,
+            is_against_wall: false,
         }
     }
 
     pub fn get_camera_position(&self) -> Vec3 {
-        self.position + vec3(0.0, PLAYER_EYES_HEIGHT, 0.0)
+        self.position + vec3(0.0, self.eyes_height, 0.0)
+    }
+
+    // The camera goes down smoothly when sneaking
+    pub fn update_eyes_height(&mut self, is_sneaking: bool, dt: f32) {
+        let target = if is_sneaking {
+            SNEAKING_EYES_HEIGHT
+        } else {
+            PLAYER_EYES_HEIGHT
+        };
+
+        let convergence = 15.0;
+        self.eyes_height += (target - self.eyes_height) * (convergence * dt).min(1.0);
     }
 }
 
@@ -143,9 +207,16 @@ impl PlayerPhysicsState {
             }
         }
 
-        // Jump
+        // Jump, or swim up
         if input_cache.is_key_pressed(Key::Space) {
-            if self.is_on_ground {
+            if self.is_in_fluid && !player_properties.is_flying {
+                self.acceleration.y += SWIM_ACCELERATION;
+
+                // Hop out of the water onto the shore
+                if self.is_against_wall {
+                    self.velocity.y = self.velocity.y.max(SWIM_OUT_SPEED);
+                }
+            } else if self.is_on_ground {
                 self.velocity.y = *JUMP_IMPULSE;
             }
         }
@@ -176,124 +247,190 @@ impl PlayerPhysicsState {
         }
     }
 
-    pub fn get_colliding_block_coords(&self, chunk_manager: &ChunkManager) -> Option<Vec3> {
-        let player_mins = &self.aabb.mins;
-        let player_maxs = &self.aabb.maxs;
+    // Spectators move at a constant speed, without gravity or inertia
+    pub fn apply_spectator_movement(
+        &mut self,
+        player_properties: &PlayerProperties,
+        input_cache: &InputCache,
+    ) {
+        let forward = player_properties.rotation.forward();
+        let forward = vec3(forward.x, 0.0, forward.z).normalize();
+        let right = forward.cross(&Vector3::y());
+        let mut direction = vec3(0.0, 0.0, 0.0);
 
-        let block_min = vec3(
-            player_mins.x.floor() as i32,
-            player_mins.y.floor() as i32,
-            player_mins.z.floor() as i32,
-        );
-        let block_max = vec3(
-            player_maxs.x.floor() as i32,
-            player_maxs.y.floor() as i32,
-            player_maxs.z.floor() as i32,
-        );
+        if input_cache.is_key_pressed(Key::W) {
+            direction += forward;
+        }
+        if input_cache.is_key_pressed(Key::S) {
+            direction -= forward;
+        }
+        if input_cache.is_key_pressed(Key::A) {
+            direction -= right;
+        }
+        if input_cache.is_key_pressed(Key::D) {
+            direction += right;
+        }
+        if input_cache.is_key_pressed(Key::Space) {
+            direction.y += 1.0;
+        }
+        if input_cache.is_key_pressed(Key::LeftShift) {
+            direction.y -= 1.0;
+        }
 
-        let mut block_collided = None;
-
-        // Find the block that the player is colliding with
-        'outer: for y in block_min.y..=block_max.y {
-            for z in block_min.z..=block_max.z {
-                for x in block_min.x..=block_max.x {
-                    if let Some(block) = chunk_manager.get_block(x, y, z) {
-                        if block.is_air() {
-                            continue;
-                        }
-                        let block_aabb = get_block_aabb(&vec3(x as f32, y as f32, z as f32));
-
-                        if self.aabb.intersects(&block_aabb) {
-                            block_collided = Some(vec3(x as f32, y as f32, z as f32));
-                            break 'outer;
-                        }
-                    }
-                }
+        self.velocity = if direction.norm_squared() != 0.0 {
+            direction.normalize() * player_properties.spectator_speed
+        } else {
+            vec3(0.0, 0.0, 0.0)
+        };
+    }
+
+    // Spectators go through blocks and fluids
+    pub fn move_without_collisions(&mut self, offset: Vec3) {
+        self.aabb.translate(&offset);
+        self.position += offset;
+        self.is_on_ground = false;
+        self.is_in_fluid = false;
+        self.is_against_wall = false;
+    }
+
+    // Part of the horizontal `offset` that keeps the player above the blocks they stand on,
+    // so that they can't drop more than `drop`
+    fn clip_at_edges(&self, obstacles: &[AABB], mut offset: Vec3, drop: f32) -> Vec3 {
+        let edge_step = 0.05;
+        let shrink = |value: f32| {
+            if value.abs() < edge_step {
+                0.0
+            } else {
+                value - edge_step * value.signum()
             }
+        };
+        let is_supported = |dx: f32, dz: f32| {
+            let mut below = self.aabb;
+            below.translate(&vec3(dx, -drop, dz));
+            obstacles.iter().any(|obstacle| below.intersects(obstacle))
+        };
+
+        while offset.x != 0.0 && !is_supported(offset.x, 0.0) {
+            offset.x = shrink(offset.x);
         }
-        block_collided
-    }
-    pub fn separate_from_block(&mut self, v: &Vec3, block_coords: &Vec3) -> bool {
-        let mut is_player_on_ground = false;
-        let block_aabb = get_block_aabb(&block_coords);
-
-        if !v.x.is_zero() {
-            if v.x < 0.0 {
-                self.aabb = AABB::new(
-                    vec3(block_aabb.maxs.x, self.aabb.mins.y, self.aabb.mins.z),
-                    vec3(
-                        block_aabb.maxs.x + PLAYER_WIDTH,
-                        self.aabb.maxs.y,
-                        self.aabb.maxs.z,
-                    ),
-                )
-            } else {
-                self.aabb = AABB::new(
-                    vec3(
-                        block_aabb.mins.x - PLAYER_WIDTH,
-                        self.aabb.mins.y,
-                        self.aabb.mins.z,
-                    ),
-                    vec3(block_aabb.mins.x, self.aabb.maxs.y, self.aabb.maxs.z),
-                )
+
+        while offset.z != 0.0 && !is_supported(0.0, offset.z) {
+            offset.z = shrink(offset.z);
+        }
+
+        while offset.x != 0.0 && offset.z != 0.0 && !is_supported(offset.x, offset.z) {
+            offset.x = shrink(offset.x);
+            offset.z = shrink(offset.z);
+        }
+
+        offset
+    }
+
+    // Moves the player by `offset` without going through any block, even at high speeds
+    // On the ground, the player walks up the blocks that are at most `step_height` high
+    // and doesn't walk off the edges when sneaking
+    pub fn move_and_collide(
+        &mut self,
+        chunk_manager: &ChunkManager,
+        mut offset: Vec3,
+        player_properties: &PlayerProperties,
+    ) {
+        let step_height = player_properties.step_height;
+        let step_up = vec3(0.0, step_height, 0.0);
+        let obstacles =
+            chunk_manager.get_collision_boxes_in(&self.aabb.expanded_towards(&(offset + step_up)));
+
+        if player_properties.is_sneaking && self.is_on_ground && offset.y <= 0.0 {
+            // The player can still sneak down a step, like from a block onto a slab
+            let clipped = self.clip_at_edges(&obstacles, offset, step_height + 0.05);
+
+            if clipped.x != offset.x {
+                self.velocity.x = 0.0;
+            }
+            if clipped.z != offset.z {
+                self.velocity.z = 0.0;
             }
 
-            self.velocity.x = 0.0;
+            offset = clipped;
         }
 
-        if !v.y.is_zero() {
-            if v.y < 0.0 {
-                self.aabb = AABB::new(
-                    vec3(self.aabb.mins.x, block_aabb.maxs.y, self.aabb.mins.z),
-                    vec3(
-                        self.aabb.maxs.x,
-                        block_aabb.maxs.y + PLAYER_HEIGHT,
-                        self.aabb.maxs.z,
-                    ),
-                );
-                is_player_on_ground = true;
-            } else {
-                self.aabb = AABB::new(
-                    vec3(
-                        self.aabb.mins.x,
-                        block_aabb.mins.y - PLAYER_HEIGHT,
-                        self.aabb.mins.z,
-                    ),
-                    vec3(self.aabb.maxs.x, block_aabb.mins.y, self.aabb.maxs.z),
-                )
+        let mut aabb = self.aabb;
+        let mut moved = aabb.sweep(&obstacles, &offset);
+
+        let is_blocked_horizontally = moved.x != offset.x || moved.z != offset.z;
+        let is_landing = offset.y < 0.0 && moved.y != offset.y;
+
+        let can_step_up =
+            step_height > 0.0 && offset.y <= 0.0 && (self.is_on_ground || is_landing);
+
+        if can_step_up && is_blocked_horizontally {
+            // Go up, then forward, then back down onto whatever is there
+            let mut stepped_aabb = self.aabb;
+            let up = stepped_aabb.sweep(&obstacles, &step_up);
+            let forward = stepped_aabb.sweep(&obstacles, &vec3(offset.x, 0.0, offset.z));
+            let down = stepped_aabb.sweep(&obstacles, &vec3(0.0, offset.y - up.y, 0.0));
+
+            if forward.xz().norm_squared() > moved.xz().norm_squared() {
+                aabb = stepped_aabb;
+                moved = vec3(forward.x, up.y + down.y, forward.z);
             }
+        }
 
+        if moved.x != offset.x {
+            self.velocity.x = 0.0;
+        }
+        if moved.y != offset.y {
             self.velocity.y = 0.0;
         }
+        if moved.z != offset.z {
+            self.velocity.z = 0.0;
+        }
 
-        if !v.z.is_zero() {
-            if v.z < 0.0 {
-                self.aabb = AABB::new(
-                    vec3(self.aabb.mins.x, self.aabb.mins.y, block_aabb.maxs.z),
-                    vec3(
-                        self.aabb.maxs.x,
-                        self.aabb.maxs.y,
-                        block_aabb.maxs.z + PLAYER_WIDTH,
-                    ),
-                )
-            } else {
-                self.aabb = AABB::new(
-                    vec3(
-                        self.aabb.mins.x,
-                        self.aabb.mins.y,
-                        block_aabb.mins.z - PLAYER_WIDTH,
-                    ),
-                    vec3(self.aabb.maxs.x, self.aabb.maxs.y, block_aabb.mins.z),
-                )
+        self.is_on_ground = offset.y < 0.0 && moved.y != offset.y;
+        self.is_against_wall = moved.x != offset.x || moved.z != offset.z;
+
+        self.aabb = aabb;
+        self.position = vec3(
+            aabb.mins.x + PLAYER_HALF_WIDTH,
+            aabb.mins.y,
+            aabb.mins.z + PLAYER_HALF_WIDTH,
+        );
+    }
+
+    // Whether the player overlaps a fluid, flowing fluids are only as high as their level
+    pub fn is_touching_fluid(&self, chunk_manager: &ChunkManager) -> bool {
+        let mins = self.aabb.mins.map(|c| c.floor() as i32);
+        let maxs = self.aabb.maxs.map(|c| c.floor() as i32);
+
+        for y in mins.y..=maxs.y {
+            for z in mins.z..=maxs.z {
+                for x in mins.x..=maxs.x {
+                    let block = match chunk_manager.get_block(x, y, z) {
+                        Some(block) if block.is_fluid() => block,
+                        _ => continue,
+                    };
+
+                    let height = if chunk_manager.get_block(x, y + 1, z) == Some(block) {
+                        1.0
+                    } else {
+                        let level = chunk_manager.get_fluid_level(x, y, z).unwrap_or(0);
+                        fluid_height(level) as f32 / 16.0
+                    };
+
+                    if self.aabb.mins.y < y as f32 + height {
+                        return true;
+                    }
+                }
             }
-            self.velocity.z = 0.0;
         }
 
-        is_player_on_ground
+        false
     }
 
     pub fn apply_fricition(&mut self, dt: f32, is_vertically: bool) {
-        let friction = if self.is_on_ground {
+        let friction = if self.is_in_fluid {
+            IN_FLUID_FRICTION
+        } else if self.is_on_ground {
             ON_GROUND_FRICTION
         } else {
             IN_AIR_FRICTION
@@ -309,6 +446,11 @@ impl PlayerPhysicsState {
             self.velocity.z -= friction * self.velocity.z * dt;
         }
 
+        // Fluids slow down the fall and the swimming
+        if self.is_in_fluid && !is_vertically {
+            self.velocity.y -= IN_FLUID_FRICTION * self.velocity.y * dt;
+        }
+
         if is_vertically {
             if self.acceleration.y.is_zero()
                 || self.acceleration.y.signum() != self.velocity.y.signum()
@@ -327,9 +469,21 @@ impl PlayerPhysicsState {
             }
 
             self.velocity.y = clamp(self.velocity.y, -10.0, 10.0);
+        } else if self.is_in_fluid {
+            if speed > SWIMMING_SPEED {
+                horizontal = horizontal.scale(SWIMMING_SPEED / speed);
+            }
         } else {
-            if speed > WALKING_SPEED {
-                horizontal = horizontal.scale(WALKING_SPEED / speed);
+            let max_speed = if player_properties.is_sneaking {
+                SNEAKING_SPEED
+            } else if player_properties.is_sprinting {
+                SPRINTING_SPEED
+            } else {
+                WALKING_SPEED
+            };
+
+            if speed > max_speed {
+                horizontal = horizontal.scale(max_speed / speed);
             }
         }
 
@@ -343,3 +497,221 @@ impl PlayerPhysicsState {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_registry::install_test_registry;
+    use crate::chunk::{BlockID, Chunk};
+    use crate::types::UVMap;
+    use crate::world_generator::TerrainGenerator;
+    use std::sync::Arc;
+
+    // Empty chunks around the origin with a few blocks in them, nothing is drawn
+    fn world(blocks: &[((i32, i32, i32), &str)]) -> ChunkManager {
+        install_test_registry();
+
+        let mut chunk_manager =
+            ChunkManager::new(Arc::new(TerrainGenerator::new(1)), &UVMap::default());
+        for x in -1..=1 {
+            for y in 0..=1 {
+                for z in -1..=1 {
+                    chunk_manager
+                        .loaded_chunks
+                        .insert((x, y, z), Chunk::full_of_block(BlockID::AIR));
+                }
+            }
+        }
+
+        for &((x, y, z), name) in blocks {
+            chunk_manager.set_block(x, y, z, BlockID::named(name));
+        }
+        chunk_manager
+    }
+
+    // Stone under y = 11 from -4 to 4 along x and z
+    fn floor() -> Vec<((i32, i32, i32), &'static str)> {
+        (-4..=4)
+            .flat_map(|x| (-4..=4).map(move |z| ((x, 10, z), "stone")))
+            .collect()
+    }
+
+    fn standing_at(x: f32, y: f32, z: f32) -> PlayerPhysicsState {
+        let mut player = PlayerPhysicsState::new_at_position(vec3(x, y, z));
+        player.is_on_ground = true;
+        player
+    }
+
+    fn assert_position(player: &PlayerPhysicsState, expected: Vec3) {
+        assert!(
+            (player.position - expected).abs().max() < 1e-4,
+            "The player is at {:?} instead of {:?}",
+            player.position,
+            expected
+        );
+    }
+
+    #[test]
+    fn no_tunnelling_through_a_wall_at_high_speed() {
+        let chunk_manager = world(&[((2, 10, 0), "stone"), ((2, 11, 0), "stone")]);
+        let mut player = PlayerPhysicsState::new_at_position(vec3(0.5, 10.0, 0.5));
+        player.velocity = vec3(500.0, 0.0, 0.0);
+
+        player.move_and_collide(&chunk_manager, vec3(50.0, 0.0, 0.0), &PlayerProperties::new());
+
+        assert_position(&player, vec3(2.0 - PLAYER_HALF_WIDTH, 10.0, 0.5));
+        assert_eq!(player.velocity.x, 0.0);
+        assert!(player.is_against_wall);
+    }
+
+    #[test]
+    fn no_tunnelling_through_a_floor_at_high_speed() {
+        let chunk_manager = world(&[((0, 10, 0), "stone")]);
+        let mut player = PlayerPhysicsState::new_at_position(vec3(0.5, 25.0, 0.5));
+        player.velocity = vec3(0.0, -1000.0, 0.0);
+
+        player.move_and_collide(&chunk_manager, vec3(0.0, -100.0, 0.0), &PlayerProperties::new());
+
+        assert_position(&player, vec3(0.5, 11.0, 0.5));
+        assert_eq!(player.velocity.y, 0.0);
+        assert!(player.is_on_ground);
+    }
+
+    #[test]
+    fn landing_in_a_corner_against_several_blocks() {
+        let mut blocks = floor();
+        for i in 0..=2 {
+            for y in 11..=12 {
+                blocks.push(((2, y, i), "stone"));
+                blocks.push(((i, y, 2), "stone"));
+            }
+        }
+        let chunk_manager = world(&blocks);
+
+        // Falling diagonally onto the four blocks under the player, into both walls
+        let mut player = PlayerPhysicsState::new_at_position(vec3(1.0, 11.5, 1.0));
+        player.velocity = vec3(10.0, -10.0, 10.0);
+
+        player.move_and_collide(&chunk_manager, vec3(3.0, -1.0, 3.0), &PlayerProperties::new());
+
+        let corner = 2.0 - PLAYER_HALF_WIDTH;
+        assert_position(&player, vec3(corner, 11.0, corner));
+        assert_eq!(player.velocity, vec3(0.0, 0.0, 0.0));
+        assert!(player.is_on_ground && player.is_against_wall);
+    }
+
+    #[test]
+    fn sliding_along_a_wall_made_of_several_blocks() {
+        let mut blocks = floor();
+        blocks.extend((-4..=4).map(|z| ((2, 11, z), "stone")));
+        let chunk_manager = world(&blocks);
+
+        let mut player = standing_at(0.5, 11.0, 0.5);
+        player.move_and_collide(&chunk_manager, vec3(2.0, -0.1, 2.0), &PlayerProperties::new());
+
+        // Blocked along x only, the seams between the blocks don't stop the player
+        assert_position(&player, vec3(2.0 - PLAYER_HALF_WIDTH, 11.0, 2.5));
+    }
+
+    #[test]
+    fn stepping_up_a_slab() {
+        let mut blocks = floor();
+        blocks.push(((2, 11, 0), "oak_slab"));
+        let chunk_manager = world(&blocks);
+
+        let mut player = standing_at(0.5, 11.0, 0.5);
+        player.move_and_collide(&chunk_manager, vec3(1.5, -0.1, 0.0), &PlayerProperties::new());
+
+        assert_position(&player, vec3(2.0, 11.5, 0.5));
+        assert!(player.is_on_ground);
+    }
+
+    #[test]
+    fn not_stepping_up_a_full_block() {
+        let mut blocks = floor();
+        blocks.push(((2, 11, 0), "stone"));
+        let chunk_manager = world(&blocks);
+
+        let mut player = standing_at(0.5, 11.0, 0.5);
+        player.move_and_collide(&chunk_manager, vec3(1.5, -0.1, 0.0), &PlayerProperties::new());
+
+        assert_position(&player, vec3(2.0 - PLAYER_HALF_WIDTH, 11.0, 0.5));
+    }
+
+    #[test]
+    fn not_stepping_up_without_a_step_height() {
+        let mut blocks = floor();
+        blocks.push(((2, 11, 0), "oak_slab"));
+        let chunk_manager = world(&blocks);
+
+        let mut player_properties = PlayerProperties::new();
+        player_properties.step_height = 0.0;
+
+        let mut player = standing_at(0.5, 11.0, 0.5);
+        player.move_and_collide(&chunk_manager, vec3(1.5, -0.1, 0.0), &player_properties);
+
+        assert_position(&player, vec3(2.0 - PLAYER_HALF_WIDTH, 11.0, 0.5));
+    }
+
+    #[test]
+    fn sneaking_stops_at_the_edge() {
+        let chunk_manager = world(&[((0, 10, 0), "stone")]);
+        let mut player_properties = PlayerProperties::new();
+        player_properties.is_sneaking = true;
+
+        let mut player = standing_at(0.5, 11.0, 0.5);
+        player.velocity = vec3(5.0, 0.0, 0.0);
+        player.move_and_collide(&chunk_manager, vec3(2.0, -0.1, 0.0), &player_properties);
+
+        // Still standing on the block, a few hundredths from its edge
+        let edge = 1.0 + PLAYER_HALF_WIDTH;
+        assert!(player.position.x < edge && player.position.x > edge - 0.06);
+        assert_eq!(player.position.y, 11.0);
+        assert_eq!(player.velocity.x, 0.0);
+
+        // Walking off the edge when not sneaking, the player falls from there
+        let mut player = standing_at(0.5, 11.0, 0.5);
+        player.move_and_collide(&chunk_manager, vec3(2.0, -0.1, 0.0), &PlayerProperties::new());
+        assert_position(&player, vec3(2.5, 11.0, 0.5));
+        player.move_and_collide(&chunk_manager, vec3(0.0, -0.1, 0.0), &PlayerProperties::new());
+        assert_position(&player, vec3(2.5, 10.9, 0.5));
+    }
+
+    #[test]
+    fn sneaking_stops_at_a_corner() {
+        let chunk_manager = world(&[((0, 10, 0), "stone")]);
+        let mut player_properties = PlayerProperties::new();
+        player_properties.is_sneaking = true;
+
+        let mut player = standing_at(0.5, 11.0, 0.5);
+        player.move_and_collide(&chunk_manager, vec3(-2.0, -0.1, 2.0), &player_properties);
+
+        let edge = PLAYER_HALF_WIDTH;
+        assert!(player.position.x > -edge && player.position.x < -edge + 0.06);
+        assert!(player.position.z < 1.0 + edge && player.position.z > 1.0 + edge - 0.06);
+        assert_eq!(player.position.y, 11.0);
+    }
+
+    #[test]
+    fn fences_are_one_and_a_half_blocks_high() {
+        let mut blocks = floor();
+        blocks.push(((2, 11, 0), "oak_fence"));
+        let chunk_manager = world(&blocks);
+
+        // Too high to step up, the player stops at the post
+        let mut player = standing_at(0.5, 11.0, 0.5);
+        player.move_and_collide(&chunk_manager, vec3(2.0, -0.1, 0.0), &PlayerProperties::new());
+        assert_position(&player, vec3(2.0 + 6.0 / 16.0 - PLAYER_HALF_WIDTH, 11.0, 0.5));
+
+        // Falling onto the post from above
+        let mut player = PlayerPhysicsState::new_at_position(vec3(2.5, 14.0, 0.5));
+        player.move_and_collide(&chunk_manager, vec3(0.0, -3.0, 0.0), &PlayerProperties::new());
+        assert_position(&player, vec3(2.5, 12.5, 0.5));
+        assert!(player.is_on_ground);
+
+        // Standing right above the fence, nothing is in the way
+        let mut player = PlayerPhysicsState::new_at_position(vec3(0.5, 12.5, 0.5));
+        player.move_and_collide(&chunk_manager, vec3(2.0, 0.0, 0.0), &PlayerProperties::new());
+        assert_position(&player, vec3(2.5, 12.5, 0.5));
+    }
+}
@@ -1,9 +1,53 @@
+use crate::aabb::AABB;
 use nalgebra_glm::{floor, I32Vec3, IVec3, Vec3};
 use num_traits::float::FloatCore;
 
+// Distance along the ray where it enters the box and the axis of the face it goes through
+// The axis is None when the ray starts inside of the box
+fn ray_box_intersection(
+    origin: &Vec3,
+    direction: &Vec3,
+    aabb: &AABB,
+) -> Option<(f32, Option<usize>)> {
+    let mut t_enter = f32::NEG_INFINITY;
+    let mut t_exit = f32::INFINITY;
+    let mut entry_axis = None;
+
+    for axis in 0..3 {
+        if direction[axis] == 0.0 {
+            if origin[axis] < aabb.mins[axis] || origin[axis] > aabb.maxs[axis] {
+                return None;
+            }
+            continue;
+        }
+
+        let t0 = (aabb.mins[axis] - origin[axis]) / direction[axis];
+        let t1 = (aabb.maxs[axis] - origin[axis]) / direction[axis];
+        let (near, far) = if t0 < t1 { (t0, t1) } else { (t1, t0) };
+
+        if near > t_enter {
+            t_enter = near;
+            entry_axis = Some(axis);
+        }
+        t_exit = t_exit.min(far);
+    }
+
+    if t_enter > t_exit || t_exit < 0.0 {
+        return None;
+    }
+
+    if t_enter < 0.0 {
+        Some((0.0, None))
+    } else {
+        Some((t_enter, entry_axis))
+    }
+}
+
 // direction must be normalized
+// `get_boxes` gives the boxes of a block the ray can hit, in world coordinates (see `get_selection_boxes`)
+// Stops at the first box hit, fluids have none so the blocks under water can be reached
 pub fn raycast(
-    is_solid_block_at: &dyn Fn(i32, i32, i32) -> bool,
+    get_boxes: &dyn Fn(i32, i32, i32) -> Vec<AABB>,
     origin: &Vec3,
     direction: &Vec3,
     distance: f32,
@@ -33,19 +77,18 @@ pub fn raycast(
     let mut hit_pos = Vec3::new(0.0, 0.0, 0.0);
     let mut hit_norm = IVec3::new(0, 0, 0);
 
-    let mut stepped_index = -1;
     while t <= distance {
-        // exit check
-        if is_solid_block_at(i.x, i.y, i.z) {
-            hit_pos = origin.zip_map(&direction, |p, d| p + t * d);
-            if stepped_index == 0 {
-                hit_norm[0] = -step.x;
-            }
-            if stepped_index == 1 {
-                hit_norm[1] = -step.y;
-            }
-            if stepped_index == 2 {
-                hit_norm[2] = -step.z;
+        // exit check, the nearest box of the block that is within reach
+        let hit = get_boxes(i.x, i.y, i.z)
+            .iter()
+            .filter_map(|aabb| ray_box_intersection(origin, direction, aabb))
+            .filter(|&(t_hit, _)| t_hit <= distance)
+            .min_by(|(a, _), (b, _)| a.total_cmp(b));
+
+        if let Some((t_hit, entry_axis)) = hit {
+            hit_pos = origin.zip_map(&direction, |p, d| p + t_hit * d);
+            if let Some(axis) = entry_axis {
+                hit_norm[axis] = -step[axis];
             }
             return Some(((i.x, i.y, i.z), hit_norm));
         }
@@ -56,24 +99,20 @@ pub fn raycast(
                 i.x += step.x;
                 t = t_max.x;
                 t_max.x += t_delta.x;
-                stepped_index = 0;
             } else {
                 i.z += step.z;
                 t = t_max.z;
                 t_max.z += t_delta.z;
-                stepped_index = 2;
             }
         } else {
             if t_max.y < t_max.z {
                 i.y += step.y;
                 t = t_max.y;
                 t_max.y += t_delta.y;
-                stepped_index = 1;
             } else {
                 i.z += step.z;
                 t = t_max.z;
                 t_max.z += t_delta.z;
-                stepped_index = 2;
             }
         }
     }
@@ -2,6 +2,22 @@
 
 out vec4 Color;
 
+// The 10 stages of the cracks side by side
+uniform sampler2D destroy_stages;
+// Negative for the outline, otherwise the stage of the cracks drawn on the face of the block
+uniform int crack_stage;
+
+in vec2 uv;
+
 void main(){
-    Color = vec4(0.0, 0.0, 0.0, 0.35);
+    if (crack_stage < 0) {
+        Color = vec4(0.0, 0.0, 0.0, 0.35);
+        return;
+    }
+
+    Color = texture(destroy_stages, vec2((crack_stage + uv.x) / 10.0, uv.y));
+
+    if (Color.a == 0) {
+        discard;
+    }
 }
\ No newline at end of file
@@ -5,6 +5,9 @@ uniform mat4 view;
 uniform mat4 projection;
 
 layout (location = 0) in vec3 pos;
+layout (location = 1) in vec2 texture_coords;
+
+out vec2 uv;
 
 float z_offset = -0.001;
 
@@ -12,4 +15,6 @@ void main(){
     vec4 position = projection * view * model * vec4(pos, 1.0);
     position.z += z_offset;
     gl_Position = position;
+
+    uv = texture_coords;
 }
\ No newline at end of file
@@ -3,18 +3,30 @@
 out vec4 Color;
 
 uniform sampler2D atlas;
+uniform float tile_size;
+// Darkens the sky light at night, from 0 to 1
+uniform float sky_light_multiplier;
+uniform vec3 fog_color;
+uniform float fog_start;
+uniform float fog_end;
+// Pixels with an alpha at or below it are discarded, 0.5 for the leaves of the cutout pass
+uniform float alpha_cutoff;
 
 in VertexAttributes{
     vec3 frag_pos;
-    vec2 texture_coords;
+    vec2 tile_coords;
+    vec2 tile_origin;
     vec3 normal;
     float ao;
+    vec2 light;
 } attrs;
 
 void main(){
-    vec4 diffuse_frag = texture(atlas, attrs.texture_coords);
+    // Greedy meshing merges faces into bigger quads, so the tile is repeated once per block
+    vec2 texture_coords = attrs.tile_origin + fract(attrs.tile_coords) * tile_size;
+    vec4 diffuse_frag = texture(atlas, texture_coords);
 
-    if (diffuse_frag.a == 0){
+    if (diffuse_frag.a <= alpha_cutoff){
         discard;
     }
 
@@ -29,4 +41,12 @@ void main(){
     }
 
     Color.rgb *= (1.0 - attrs.ao * 0.15);
+
+    // Each light level is 20% darker than the one above it
+    float light_level = max(attrs.light.x * sky_light_multiplier, attrs.light.y);
+    Color.rgb *= pow(0.8, 15.0 * (1.0 - light_level));
+
+    // The chunks fade into the sky before the render distance
+    float fog = clamp((length(attrs.frag_pos) - fog_start) / (fog_end - fog_start), 0.0, 1.0);
+    Color.rgb = mix(Color.rgb, fog_color, fog);
 }
\ No newline at end of file
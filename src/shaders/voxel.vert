@@ -3,25 +3,69 @@
 uniform mat4 model;
 uniform mat4 view;
 uniform mat4 projection;
+uniform float tile_size;
 
-layout (location = 0) in vec3 pos;
-layout (location = 1) in vec2 texture_coords;
-layout (location = 2) in vec3 normal;
-layout (location = 3) in float ao;
+// See packed_vertex.rs for the layout
+layout (location = 0) in uvec2 packed_vertex;
 
 out VertexAttributes{
     vec3 frag_pos;
-    vec2 texture_coords;
+    vec2 tile_coords;
+    vec2 tile_origin;
     vec3 normal;
     float ao;
+    // Sky and block light, from 0 to 1
+    vec2 light;
 } attrs;
 
+// Same order as the ambient occlusion : right, left, top, bottom, front, back
+const vec3 NORMALS[6] = vec3[6](
+    vec3(1.0, 0.0, 0.0),
+    vec3(-1.0, 0.0, 0.0),
+    vec3(0.0, 1.0, 0.0),
+    vec3(0.0, -1.0, 0.0),
+    vec3(0.0, 0.0, 1.0),
+    vec3(0.0, 0.0, -1.0)
+);
+
 void main(){
+    uint word0 = packed_vertex.x;
+    uint word1 = packed_vertex.y;
+
+    vec3 pos = vec3(
+        float(word0 & 31u),
+        float((word0 >> 5u) & 31u),
+        float((word0 >> 10u) & 31u)
+    );
+    uint face = (word0 >> 15u) & 7u;
+    uint ao = (word0 >> 18u) & 3u;
+    uint sky_light = (word0 >> 20u) & 15u;
+    uint block_light = (word0 >> 24u) & 15u;
+    float lowered = float((word0 >> 28u) & 15u) / 16.0;
+    pos.y -= lowered;
+
+    pos.x -= float((word1 >> 22u) & 15u) / 16.0;
+    pos.z -= float((word1 >> 26u) & 15u) / 16.0;
+
+    uint tile = word1 & 4095u;
+    uint tiles_per_row = uint(round(1.0 / tile_size));
+    vec2 tile_coords = vec2(float((word1 >> 12u) & 31u), float((word1 >> 17u) & 31u));
+    bool fine_tile_coords = ((word1 >> 30u) & 1u) != 0u;
+
+    if (fine_tile_coords) {
+        tile_coords /= 16.0;
+    } else if (face != 2u && face != 3u) {
+        // The texture of the side faces is cut instead of squashed, their v axis goes up
+        tile_coords.y -= lowered;
+    }
+
     gl_Position = projection * view * model * vec4(pos, 1.0f);
 
     // frag 쉐이더는 위치만 필요하므로 vec3으로 정해줌
     attrs.frag_pos = vec3(view * model * vec4(pos, 1.0f));
-    attrs.texture_coords = texture_coords;
-    attrs.normal = normal;
-    attrs.ao = ao;
+    attrs.tile_coords = tile_coords;
+    attrs.tile_origin = vec2(float(tile % tiles_per_row), float(tile / tiles_per_row)) * tile_size;
+    attrs.normal = NORMALS[face];
+    attrs.ao = float(ao);
+    attrs.light = vec2(float(sky_light), float(block_light)) / 15.0;
 }
\ No newline at end of file
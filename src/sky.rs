@@ -0,0 +1,116 @@
+use crate::constants::{MOON_SIZE, SKY_DISTANCE, SUN_SIZE};
+use crate::shader::ShaderProgram;
+use crate::shapes::quad;
+use crate::texture::create_texture;
+use crate::world_clock::WorldClock;
+use nalgebra_glm::{mat3_to_mat4, mat4_to_mat3, vec3, Mat4};
+use std::f32::consts::PI;
+use std::os::raw::c_void;
+
+// Texture units of the sky, 0 to 2 are used by the atlas and the GUI
+const SUN_TEXTURE_UNIT: u32 = 3;
+const MOON_TEXTURE_UNIT: u32 = 4;
+
+// The sun and the moon are textured quads that turn around the player, on opposite sides
+pub struct Sky {
+    vao: u32,
+    sun_texture: u32,
+    moon_texture: u32,
+}
+
+impl Sky {
+    pub fn new() -> Self {
+        let mut vao = 0;
+        gl_call!(gl::CreateVertexArrays(1, &mut vao));
+
+        // Position
+        gl_call!(gl::EnableVertexArrayAttrib(vao, 0));
+        gl_call!(gl::VertexArrayAttribFormat(
+            vao,
+            0,
+            3,
+            gl::FLOAT,
+            gl::FALSE,
+            0
+        ));
+        gl_call!(gl::VertexArrayAttribBinding(vao, 0, 0));
+
+        // Texture coords
+        gl_call!(gl::EnableVertexArrayAttrib(vao, 1));
+        gl_call!(gl::VertexArrayAttribFormat(
+            vao,
+            1,
+            2,
+            gl::FLOAT,
+            gl::FALSE,
+            (3 * std::mem::size_of::<f32>()) as u32
+        ));
+        gl_call!(gl::VertexArrayAttribBinding(vao, 1, 0));
+
+        let mut vbo = 0;
+        gl_call!(gl::CreateBuffers(1, &mut vbo));
+        gl_call!(gl::VertexArrayVertexBuffer(
+            vao,
+            0,
+            vbo,
+            0,
+            (5 * std::mem::size_of::<f32>()) as i32
+        ));
+
+        // create_texture flips the images, so v goes up
+        gl_call!(gl::NamedBufferData(
+            vbo,
+            (5 * 6 * std::mem::size_of::<f32>()) as isize,
+            quad((0.0, 1.0, 1.0, 0.0)).as_ptr() as *const c_void,
+            gl::STATIC_DRAW
+        ));
+
+        Self {
+            vao,
+            sun_texture: create_texture("textures/environment/sun.png"),
+            moon_texture: create_texture("textures/environment/moon.png"),
+        }
+    }
+
+    // Draws behind everything, it must be called right after clearing the screen
+    pub fn draw(
+        &self,
+        shader: &mut ShaderProgram,
+        clock: &WorldClock,
+        view: &Mat4,
+        projection: &Mat4,
+    ) {
+        // Without the translation the sky stays at the same distance wherever the player goes
+        let view_projection = projection * mat3_to_mat4(&mat4_to_mat3(view));
+
+        gl_call!(gl::BindTextureUnit(SUN_TEXTURE_UNIT, self.sun_texture));
+        gl_call!(gl::BindTextureUnit(MOON_TEXTURE_UNIT, self.moon_texture));
+        gl_call!(gl::Disable(gl::DEPTH_TEST));
+        gl_call!(gl::Disable(gl::CULL_FACE));
+        gl_call!(gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA));
+        gl_call!(gl::BindVertexArray(self.vao));
+
+        shader.use_program();
+        shader.set_uniform_matrix4fv("projection", view_projection.as_ptr());
+
+        let bodies = [
+            (clock.sun_angle(), SUN_SIZE, SUN_TEXTURE_UNIT),
+            (clock.sun_angle() + PI, MOON_SIZE, MOON_TEXTURE_UNIT),
+        ];
+
+        for (angle, size, texture_unit) in bodies {
+            // Rises in the east (+x), goes over the player and sets in the west, always facing the player
+            let model_matrix = Mat4::new_rotation(vec3(0.0, 0.0, angle))
+                * Mat4::new_translation(&vec3(SKY_DISTANCE, 0.0, 0.0))
+                * Mat4::new_rotation(vec3(0.0, -PI / 2.0, 0.0))
+                * Mat4::new_scaling(size);
+
+            shader.set_uniform_matrix4fv("model", model_matrix.as_ptr());
+            shader.set_uniform1i("tex", texture_unit as i32);
+            gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, 6));
+        }
+
+        gl_call!(gl::Enable(gl::CULL_FACE));
+        gl_call!(gl::Enable(gl::DEPTH_TEST));
+    }
+}
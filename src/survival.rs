@@ -0,0 +1,439 @@
+use crate::chunk_manager::ChunkManager;
+use crate::constants::{GRAVITY, MAX_FOOD, MAX_HEALTH};
+use crate::inventory::item::Food;
+use crate::player::PlayerPhysicsState;
+use nalgebra_glm::Vec3;
+
+/*
+    Survival mode : the player has health and food, both counted in halves of the icons of the HUD
+
+    Health goes down when the player gets hurt :
+        falling             1 per block fallen above SAFE_FALL_DISTANCE, from the speed when landing
+        in the void         VOID_DAMAGE, below VOID_LEVEL
+        suffocating         SUFFOCATION_DAMAGE, with the head inside a full block
+        starving            1 every FOOD_INTERVAL without food, down to half a heart
+    After being hurt the player can't be hurt again for DAMAGE_COOLDOWN seconds
+
+    Sprinting, swimming, jumping, getting hurt and healing exhaust the player. Every EXHAUSTION_PER_FOOD
+    of exhaustion uses a point of saturation, or a point of food once there is no saturation left
+    With at least REGENERATION_FOOD food, the player heals 1 every FOOD_INTERVAL
+    Eating restores food and saturation, the saturation never goes above the food
+
+    At 0 health the player dies and respawns at the spawn point of the world
+*/
+
+const SAFE_FALL_DISTANCE: f32 = 3.0;
+const VOID_LEVEL: f32 = -64.0;
+const VOID_DAMAGE: u32 = 4;
+const SUFFOCATION_DAMAGE: u32 = 1;
+const DAMAGE_COOLDOWN: f32 = 0.5; // in seconds
+
+const FOOD_INTERVAL: f32 = 4.0; // in seconds
+const REGENERATION_FOOD: u32 = 18;
+const INITIAL_SATURATION: f32 = 5.0;
+
+const EXHAUSTION_PER_FOOD: f32 = 4.0;
+const SPRINT_EXHAUSTION: f32 = 0.1; // per block
+const SWIM_EXHAUSTION: f32 = 0.01; // per block
+const JUMP_EXHAUSTION: f32 = 0.05;
+const SPRINT_JUMP_EXHAUSTION: f32 = 0.2;
+const DAMAGE_EXHAUSTION: f32 = 0.1;
+const REGENERATION_EXHAUSTION: f32 = 6.0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GameMode {
+    // Can fly and never gets hurt
+    Creative,
+    Survival,
+    // Flies through the blocks without interacting with the world, see `apply_spectator_movement`
+    Spectator,
+}
+
+impl GameMode {
+    pub fn next(&self) -> GameMode {
+        match self {
+            GameMode::Creative => GameMode::Survival,
+            GameMode::Survival => GameMode::Spectator,
+            GameMode::Spectator => GameMode::Creative,
+        }
+    }
+
+    pub fn can_fly(&self) -> bool {
+        *self != GameMode::Survival
+    }
+
+    // Whether the player has health and food
+    pub fn is_survival(&self) -> bool {
+        *self == GameMode::Survival
+    }
+}
+
+// Damage of a fall that ends at `speed`, the distance fallen comes from the gravity
+// The landing speed is the one of a whole physics step, so the distance is a bit too long
+pub fn fall_damage(speed: f32) -> u32 {
+    let distance = speed * speed / (2.0 * -GRAVITY);
+    (distance - SAFE_FALL_DISTANCE).round().max(0.0) as u32
+}
+
+pub struct PlayerStats {
+    pub health: u32,
+    pub food: u32,
+    saturation: f32,
+    exhaustion: f32,
+    // Seconds left before the player can be hurt again
+    invulnerability: f32,
+    // Seconds since the last time the player healed or starved
+    food_timer: f32,
+}
+
+impl PlayerStats {
+    pub fn new() -> Self {
+        Self {
+            health: MAX_HEALTH,
+            food: MAX_FOOD,
+            saturation: INITIAL_SATURATION,
+            exhaustion: 0.0,
+            invulnerability: 0.0,
+            food_timer: 0.0,
+        }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.health == 0
+    }
+
+    pub fn damage(&mut self, amount: u32) {
+        if amount == 0 || self.invulnerability > 0.0 || self.is_dead() {
+            return;
+        }
+
+        self.health = self.health.saturating_sub(amount);
+        self.invulnerability = DAMAGE_COOLDOWN;
+        self.add_exhaustion(DAMAGE_EXHAUSTION);
+    }
+
+    pub fn add_exhaustion(&mut self, amount: f32) {
+        self.exhaustion += amount;
+
+        while self.exhaustion >= EXHAUSTION_PER_FOOD {
+            self.exhaustion -= EXHAUSTION_PER_FOOD;
+
+            if self.saturation > 0.0 {
+                self.saturation = (self.saturation - 1.0).max(0.0);
+            } else {
+                self.food = self.food.saturating_sub(1);
+            }
+        }
+    }
+
+    // Returns false when the player isn't hungry, the food isn't eaten then
+    pub fn eat(&mut self, food: Food) -> bool {
+        if self.food >= MAX_FOOD {
+            return false;
+        }
+
+        self.food = (self.food + food.food()).min(MAX_FOOD);
+        self.saturation = (self.saturation + food.saturation()).min(self.food as f32);
+        true
+    }
+
+    // `offset` is how far the player tried to move during the physics step
+    pub fn add_movement_exhaustion(
+        &mut self,
+        offset: &Vec3,
+        was_on_ground: bool,
+        is_in_fluid: bool,
+        is_sprinting: bool,
+    ) {
+        let distance = offset.xz().norm();
+
+        if is_in_fluid {
+            self.add_exhaustion(SWIM_EXHAUSTION * distance);
+        } else if is_sprinting {
+            self.add_exhaustion(SPRINT_EXHAUSTION * distance);
+        }
+
+        // Only a jump takes the player up from the ground
+        if was_on_ground && offset.y > 0.0 {
+            self.add_exhaustion(if is_sprinting {
+                SPRINT_JUMP_EXHAUSTION
+            } else {
+                JUMP_EXHAUSTION
+            });
+        }
+    }
+
+    // Damage from the surroundings of the player, healing and starving, called on every physics step
+    pub fn update(&mut self, player: &PlayerPhysicsState, chunk_manager: &ChunkManager, dt: f32) {
+        self.invulnerability = (self.invulnerability - dt).max(0.0);
+
+        if player.position.y < VOID_LEVEL {
+            self.damage(VOID_DAMAGE);
+        }
+
+        let head = player.get_camera_position().map(|c| c.floor() as i32);
+        let is_suffocating = chunk_manager
+            .get_block(head.x, head.y, head.z)
+            .map_or(false, |block| block.is_solid() && !block.is_transparent());
+
+        if is_suffocating {
+            self.damage(SUFFOCATION_DAMAGE);
+        }
+
+        let can_heal = self.food >= REGENERATION_FOOD && self.health < MAX_HEALTH;
+        let is_starving = self.food == 0 && self.health > 1;
+
+        if !can_heal && !is_starving {
+            self.food_timer = 0.0;
+            return;
+        }
+
+        self.food_timer += dt;
+        if self.food_timer >= FOOD_INTERVAL {
+            self.food_timer -= FOOD_INTERVAL;
+
+            if can_heal {
+                self.health += 1;
+                self.add_exhaustion(REGENERATION_EXHAUSTION);
+            } else {
+                self.damage(1);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_registry::install_test_registry;
+    use crate::chunk::{BlockID, Chunk};
+    use crate::types::UVMap;
+    use crate::world_generator::TerrainGenerator;
+    use nalgebra_glm::vec3;
+    use std::sync::Arc;
+
+    fn hungry(food: u32, saturation: f32) -> PlayerStats {
+        PlayerStats {
+            food,
+            saturation,
+            ..PlayerStats::new()
+        }
+    }
+
+    #[test]
+    fn eating_restores_food_and_saturation() {
+        let mut stats = hungry(10, 0.0);
+
+        assert!(stats.eat(Food::Apple));
+        assert_eq!(stats.food, 10 + Food::Apple.food());
+        assert_eq!(stats.saturation, Food::Apple.saturation());
+    }
+
+    #[test]
+    fn food_never_goes_above_the_maximum() {
+        let mut stats = hungry(MAX_FOOD - 1, 0.0);
+
+        assert!(stats.eat(Food::Apple));
+        assert_eq!(stats.food, MAX_FOOD);
+    }
+
+    #[test]
+    fn saturation_never_goes_above_the_food() {
+        let mut stats = hungry(0, 0.0);
+
+        assert!(stats.eat(Food::Apple));
+        assert_eq!(stats.saturation, 2.4);
+        assert!(stats.eat(Food::Apple));
+        assert_eq!(stats.saturation, 4.8);
+
+        let mut stats = hungry(1, 5.0);
+        assert!(stats.eat(Food::Apple));
+        assert_eq!(stats.saturation, 1.0 + Food::Apple.food() as f32);
+    }
+
+    #[test]
+    fn a_full_player_does_not_eat() {
+        let mut stats = hungry(MAX_FOOD, 0.0);
+
+        assert!(!stats.eat(Food::Apple));
+        assert_eq!(stats.food, MAX_FOOD);
+        assert_eq!(stats.saturation, 0.0);
+    }
+
+    #[test]
+    fn eating_makes_up_for_the_exhaustion() {
+        let mut stats = hungry(MAX_FOOD, 0.0);
+        stats.add_exhaustion(EXHAUSTION_PER_FOOD * 6.0);
+        assert_eq!(stats.food, MAX_FOOD - 6);
+
+        assert!(stats.eat(Food::Apple));
+        assert!(stats.eat(Food::Apple));
+        assert_eq!(stats.food, MAX_FOOD);
+
+        // The saturation is used before the food
+        stats.add_exhaustion(EXHAUSTION_PER_FOOD);
+        assert_eq!(stats.food, MAX_FOOD);
+    }
+
+    // Empty chunks around the origin with a few blocks in them, nothing is drawn
+    fn world(blocks: &[((i32, i32, i32), &str)]) -> ChunkManager {
+        install_test_registry();
+
+        let mut chunk_manager =
+            ChunkManager::new(Arc::new(TerrainGenerator::new(1)), &UVMap::default());
+        for x in -1..=1 {
+            for y in 0..=1 {
+                for z in -1..=1 {
+                    chunk_manager
+                        .loaded_chunks
+                        .insert((x, y, z), Chunk::full_of_block(BlockID::AIR));
+                }
+            }
+        }
+
+        for &((x, y, z), name) in blocks {
+            chunk_manager.set_block(x, y, z, BlockID::named(name));
+        }
+        chunk_manager
+    }
+
+    // The head of this player is in the block (0, 11, 0)
+    fn player() -> PlayerPhysicsState {
+        PlayerPhysicsState::new_at_position(vec3(0.5, 10.0, 0.5))
+    }
+
+    fn speed_after_falling(distance: f32) -> f32 {
+        (2.0 * -GRAVITY * distance).sqrt()
+    }
+
+    #[test]
+    fn short_falls_dont_hurt() {
+        assert_eq!(fall_damage(0.0), 0);
+        assert_eq!(fall_damage(speed_after_falling(1.0)), 0);
+        assert_eq!(fall_damage(speed_after_falling(SAFE_FALL_DISTANCE)), 0);
+    }
+
+    #[test]
+    fn fall_damage_grows_with_the_distance() {
+        assert_eq!(fall_damage(speed_after_falling(4.0)), 1);
+        assert_eq!(fall_damage(speed_after_falling(10.0)), 7);
+        assert_eq!(fall_damage(speed_after_falling(23.0)), 20);
+    }
+
+    #[test]
+    fn damage_makes_the_player_invulnerable_for_a_while() {
+        let mut stats = PlayerStats::new();
+
+        stats.damage(3);
+        assert_eq!(stats.health, MAX_HEALTH - 3);
+
+        stats.damage(3);
+        assert_eq!(stats.health, MAX_HEALTH - 3);
+
+        stats.update(&player(), &world(&[]), DAMAGE_COOLDOWN);
+        stats.damage(3);
+        assert_eq!(stats.health, MAX_HEALTH - 6);
+    }
+
+    #[test]
+    fn no_damage_doesnt_make_the_player_invulnerable() {
+        let mut stats = PlayerStats::new();
+
+        stats.damage(0);
+        stats.damage(2);
+        assert_eq!(stats.health, MAX_HEALTH - 2);
+    }
+
+    #[test]
+    fn health_stops_at_zero() {
+        let mut stats = PlayerStats::new();
+
+        stats.damage(MAX_HEALTH + 5);
+        assert_eq!(stats.health, 0);
+        assert!(stats.is_dead());
+    }
+
+    #[test]
+    fn the_void_hurts() {
+        let mut stats = PlayerStats::new();
+        let player = PlayerPhysicsState::new_at_position(vec3(0.5, VOID_LEVEL - 1.0, 0.5));
+
+        stats.update(&player, &world(&[]), 0.05);
+        assert_eq!(stats.health, MAX_HEALTH - VOID_DAMAGE);
+    }
+
+    #[test]
+    fn the_player_suffocates_in_solid_blocks() {
+        let mut stats = PlayerStats::new();
+
+        stats.update(&player(), &world(&[((0, 11, 0), "stone")]), 0.05);
+        assert_eq!(stats.health, MAX_HEALTH - SUFFOCATION_DAMAGE);
+    }
+
+    #[test]
+    fn the_player_doesnt_suffocate_in_air_or_glass() {
+        let mut stats = PlayerStats::new();
+
+        stats.update(&player(), &world(&[]), 0.05);
+        stats.update(&player(), &world(&[((0, 11, 0), "glass")]), 0.05);
+        assert_eq!(stats.health, MAX_HEALTH);
+    }
+
+    #[test]
+    fn suffocation_only_hurts_once_per_cooldown() {
+        let mut stats = PlayerStats::new();
+        let world = world(&[((0, 11, 0), "stone")]);
+
+        // A power of two so that the cooldown runs out exactly on the fifth step
+        let dt = DAMAGE_COOLDOWN / 4.0;
+        for _ in 0..8 {
+            stats.update(&player(), &world, dt);
+        }
+        assert_eq!(stats.health, MAX_HEALTH - 2 * SUFFOCATION_DAMAGE);
+    }
+
+    #[test]
+    fn well_fed_players_heal() {
+        let mut stats = PlayerStats {
+            health: 10,
+            ..hungry(REGENERATION_FOOD, 0.0)
+        };
+        let world = world(&[]);
+
+        stats.update(&player(), &world, FOOD_INTERVAL / 2.0);
+        assert_eq!(stats.health, 10);
+
+        stats.update(&player(), &world, FOOD_INTERVAL / 2.0);
+        assert_eq!(stats.health, 11);
+        // Healing costs food when there is no saturation left
+        assert_eq!(stats.food, REGENERATION_FOOD - 1);
+    }
+
+    #[test]
+    fn hungry_players_dont_heal() {
+        let mut stats = PlayerStats {
+            health: 10,
+            ..hungry(REGENERATION_FOOD - 1, 0.0)
+        };
+
+        stats.update(&player(), &world(&[]), FOOD_INTERVAL * 2.0);
+        assert_eq!(stats.health, 10);
+    }
+
+    #[test]
+    fn starving_stops_at_half_a_heart() {
+        let mut stats = PlayerStats {
+            health: 4,
+            ..hungry(0, 0.0)
+        };
+        let world = world(&[]);
+
+        stats.update(&player(), &world, FOOD_INTERVAL);
+        assert_eq!(stats.health, 3);
+
+        for _ in 0..10 {
+            stats.update(&player(), &world, FOOD_INTERVAL);
+        }
+        assert_eq!(stats.health, 1);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::block_registry::block_registry;
 use crate::block_texture_sides::BlockFaces;
 use crate::chunk::BlockID;
 use crate::constants::{BLOCK_TEXTURE_SIZE, TEXTURE_ATLAS_SIZE};
@@ -6,59 +7,24 @@ use image::{ColorType, DynamicImage};
 use std::collections::HashMap;
 use std::os::raw::c_void;
 
-pub fn generate_texture_atlas() -> (u32, HashMap<BlockID, BlockFaces<UVCoords>>) {
-    let mut face_images = create_face_images_map();
+pub fn generate_texture_atlas() -> Result<(u32, HashMap<BlockID, BlockFaces<UVCoords>>), String> {
+    let face_images = create_face_images_map();
     let atlas = creat_texture_atlas(TEXTURE_ATLAS_SIZE as i32);
-    let face_uvs = create_face_uvs_map(atlas, face_images);
+    let face_uvs = create_face_uvs_map(atlas, face_images)?;
 
-    (atlas, face_uvs)
+    Ok((atlas, face_uvs))
 }
 
 fn create_face_images_map() -> HashMap<BlockID, BlockFaces<&'static str>> {
     // 각 블록들의 텍스쳐 제작
-    // Generate texture atlas
-    let mut face_images: HashMap<BlockID, BlockFaces<&str>> = HashMap::new();
-    face_images.insert(BlockID::Dirt, BlockFaces::All("textures/blocks/dirt.png"));
-    face_images.insert(
-        BlockID::Cobblestone,
-        BlockFaces::All("textures/blocks/cobblestone.png"),
-    );
-    face_images.insert(
-        BlockID::Obsidian,
-        BlockFaces::All("textures/blocks/obsidian.png"),
-    );
-    face_images.insert(
-        BlockID::Grass,
-        BlockFaces::Sides {
-            sides: "textures/blocks/grass_block_side.png",
-            top: "textures/blocks/grass_block_top.png",
-            bottom: "textures/blocks/dirt.png",
-        },
-    );
-    face_images.insert(
-        BlockID::OakLog,
-        BlockFaces::Sides {
-            sides: "textures/blocks/oak_log.png",
-            top: "textures/blocks/oak_log_top.png",
-            bottom: "textures/blocks/oak_log_top.png",
-        },
-    );
-    face_images.insert(
-        BlockID::OakLeaves,
-        BlockFaces::All("textures/blocks/oak_leaves.png"),
-    );
-    face_images.insert(
-        BlockID::OakPlanks,
-        BlockFaces::All("textures/blocks/oak_planks.png"),
-    );
-    face_images.insert(BlockID::Glass, BlockFaces::All("textures/blocks/glass.png"));
-    face_images.insert(BlockID::Debug, BlockFaces::All("textures/blocks/debug.png"));
-    face_images.insert(
-        BlockID::Debug2,
-        BlockFaces::All("textures/blocks/debug2.png"),
-    );
-
-    face_images
+    // The textures of every block come from the block registry
+    block_registry()
+        .blocks()
+        .filter_map(|block| {
+            let faces = block.faces.as_ref()?;
+            Some((block.id, faces.map(|path| path.as_str())))
+        })
+        .collect()
 }
 
 fn creat_texture_atlas(atlas_size: i32) -> u32 {
@@ -88,7 +54,7 @@ fn creat_texture_atlas(atlas_size: i32) -> u32 {
 fn create_face_uvs_map(
     atlas: u32,
     face_images: HashMap<BlockID, BlockFaces<&str>>,
-) -> HashMap<BlockID, BlockFaces<UVCoords>> {
+) -> Result<HashMap<BlockID, BlockFaces<UVCoords>>, String> {
     // Load all ther images and fill the UV map for all the blocks
     let mut x = 0;
     let mut y = 0;
@@ -123,7 +89,7 @@ fn create_face_uvs_map(
     for (block, faces) in face_images {
         match faces {
             BlockFaces::All(all) => {
-                let mut img = read_image(all);
+                let mut img = read_image(all)?;
                 let uv = put_image_into_atlas(&mut img);
                 face_uvs.insert(block, BlockFaces::All(uv));
             }
@@ -131,9 +97,9 @@ fn create_face_uvs_map(
                 face_uvs.insert(
                     block,
                     BlockFaces::Sides {
-                        sides: put_image_into_atlas(&mut read_image(sides)),
-                        top: put_image_into_atlas(&mut read_image(top)),
-                        bottom: put_image_into_atlas(&mut read_image(bottom)),
+                        sides: put_image_into_atlas(&mut read_image(sides)?),
+                        top: put_image_into_atlas(&mut read_image(top)?),
+                        bottom: put_image_into_atlas(&mut read_image(bottom)?),
                     },
                 );
             }
@@ -148,34 +114,30 @@ fn create_face_uvs_map(
                 face_uvs.insert(
                     block,
                     BlockFaces::Each {
-                        top: put_image_into_atlas(&mut read_image(top)),
-                        bottom: put_image_into_atlas(&mut read_image(bottom)),
-                        front: put_image_into_atlas(&mut read_image(front)),
-                        back: put_image_into_atlas(&mut read_image(back)),
-                        left: put_image_into_atlas(&mut read_image(left)),
-                        right: put_image_into_atlas(&mut read_image(right)),
+                        top: put_image_into_atlas(&mut read_image(top)?),
+                        bottom: put_image_into_atlas(&mut read_image(bottom)?),
+                        front: put_image_into_atlas(&mut read_image(front)?),
+                        back: put_image_into_atlas(&mut read_image(back)?),
+                        left: put_image_into_atlas(&mut read_image(left)?),
+                        right: put_image_into_atlas(&mut read_image(right)?),
                     },
                 );
             }
         }
     }
 
-    (face_uvs)
+    Ok(face_uvs)
 }
 
-fn read_image(image_path: &str) -> DynamicImage {
-    let img = image::open(image_path);
-    let img = match img {
-        Ok(img) => img.flipv(),
-        Err(err) => panic!("Filename: {image_path}, error: {}", err.to_string()),
-    };
+fn read_image(image_path: &str) -> Result<DynamicImage, String> {
+    let img = image::open(image_path)
+        .map_err(|err| format!("Failed to load the texture {image_path}: {err}"))?
+        .flipv();
 
     match img.color() {
-        ColorType::Rgba8 => {}
-        _ => panic!("Texture format not supported"),
-    };
-
-    img
+        ColorType::Rgba8 => Ok(img),
+        _ => Err(format!("Texture format of {image_path} is not supported, expected RGBA8")),
+    }
 }
 
 fn blit_image_to_texture(src: &DynamicImage, dest: u32, x: i32, y: i32) {
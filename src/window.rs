@@ -40,7 +40,16 @@ pub fn create_window(
     window.set_raw_mouse_motion(true);
     window.set_mouse_button_polling(true);
     window.set_scroll_polling(true);
-    window.set_cursor_mode(CursorMode::Disabled);
+    set_cursor_captured(&mut window, true);
 
     (glfw, window, events)
 }
+
+// The hidden cursor turns the camera, the visible one is used in the inventory screen
+pub fn set_cursor_captured(window: &mut Window, captured: bool) {
+    window.set_cursor_mode(if captured {
+        CursorMode::Disabled
+    } else {
+        CursorMode::Normal
+    });
+}
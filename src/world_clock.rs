@@ -0,0 +1,191 @@
+use crate::constants::{BACKGROUND_COLOR, DAY_LENGTH, NIGHT_SKY_LIGHT};
+use nalgebra_glm::{mix, smoothstep, vec3, Vec3};
+use std::f32::consts::PI;
+use std::time::Instant;
+
+const NIGHT_SKY_COLOR: (f32, f32, f32) = (0.02, 0.02, 0.06);
+const SUNSET_SKY_COLOR: (f32, f32, f32) = (0.98, 0.56, 0.35);
+
+const DAY_FOG_COLOR: (f32, f32, f32) = (0.8, 0.88, 1.0);
+const NIGHT_FOG_COLOR: (f32, f32, f32) = (0.03, 0.03, 0.08);
+const SUNSET_FOG_COLOR: (f32, f32, f32) = (0.95, 0.65, 0.45);
+
+fn color((r, g, b): (f32, f32, f32)) -> Vec3 {
+    vec3(r, g, b)
+}
+
+// Keeps track of the time of day in the world
+// It follows the global timer so it stops when the game is paused
+pub struct WorldClock {
+    // Fraction of the day between 0 and 1 : 0.0 is sunrise, 0.25 noon, 0.5 sunset and 0.75 midnight
+    time_of_day: f32,
+    last_update: Option<Instant>,
+    // How many times faster than normal the time goes
+    pub speed: f32,
+}
+
+impl WorldClock {
+    pub fn new(time_of_day: f32) -> Self {
+        Self {
+            time_of_day: time_of_day.rem_euclid(1.0),
+            last_update: None,
+            speed: 1.0,
+        }
+    }
+
+    // `now` comes from the global timer, it doesn't move while the timer is paused
+    pub fn update(&mut self, now: Instant) {
+        if let Some(last_update) = self.last_update {
+            let elapsed = now.saturating_duration_since(last_update).as_secs_f32();
+            self.fast_forward(elapsed * self.speed);
+        }
+
+        self.last_update = Some(now);
+    }
+
+    pub fn time_of_day(&self) -> f32 {
+        self.time_of_day
+    }
+
+    pub fn set_time_of_day(&mut self, time_of_day: f32) {
+        self.time_of_day = time_of_day.rem_euclid(1.0);
+    }
+
+    // Moves the time forward by the given amount of in game seconds
+    pub fn fast_forward(&mut self, seconds: f32) {
+        self.set_time_of_day(self.time_of_day + seconds / DAY_LENGTH);
+    }
+
+    // Angle of the sun above the eastern horizon, the moon is always on the opposite side
+    pub fn sun_angle(&self) -> f32 {
+        self.time_of_day * 2.0 * PI
+    }
+
+    // From -1 when the sun is right under the player to 1 at noon
+    pub fn sun_height(&self) -> f32 {
+        self.sun_angle().sin()
+    }
+
+    // 0 at night, 1 during the day, the transition happens around sunrise and sunset
+    pub fn daylight(&self) -> f32 {
+        smoothstep(-0.15, 0.25, self.sun_height())
+    }
+
+    // Scales the sky light baked in the chunk meshes, block light is not affected
+    pub fn sky_light_multiplier(&self) -> f32 {
+        NIGHT_SKY_LIGHT + (1.0 - NIGHT_SKY_LIGHT) * self.daylight()
+    }
+
+    // How close the sun is to the horizon, 1 when it is right on it
+    fn twilight(&self) -> f32 {
+        1.0 - smoothstep(0.0, 0.3, self.sun_height().abs())
+    }
+
+    fn interpolate_colors(&self, day: Vec3, night: Vec3, sunset: Vec3) -> Vec3 {
+        let color = mix(&night, &day, self.daylight());
+        mix(&color, &sunset, self.twilight() * 0.6)
+    }
+
+    pub fn sky_color(&self) -> Vec3 {
+        let (r, g, b, _) = BACKGROUND_COLOR;
+        self.interpolate_colors(
+            vec3(r, g, b),
+            color(NIGHT_SKY_COLOR),
+            color(SUNSET_SKY_COLOR),
+        )
+    }
+
+    pub fn fog_color(&self) -> Vec3 {
+        self.interpolate_colors(
+            color(DAY_FOG_COLOR),
+            color(NIGHT_FOG_COLOR),
+            color(SUNSET_FOG_COLOR),
+        )
+    }
+
+    // Readable time for the debug output, 0.0 is 6:00 like in Minecraft
+    pub fn formatted_time(&self) -> String {
+        let minutes = ((self.time_of_day * 24.0 * 60.0) as u32 + 6 * 60) % (24 * 60);
+        format!("{:02}:{:02}", minutes / 60, minutes % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn assert_close(value: f32, expected: f32) {
+        assert!((value - expected).abs() < 1e-4, "{value} instead of {expected}");
+    }
+
+    #[test]
+    fn time_of_day_wraps_around() {
+        let mut clock = WorldClock::new(1.25);
+        assert_close(clock.time_of_day(), 0.25);
+
+        clock.set_time_of_day(2.5);
+        assert_close(clock.time_of_day(), 0.5);
+
+        clock.set_time_of_day(-0.25);
+        assert_close(clock.time_of_day(), 0.75);
+    }
+
+    #[test]
+    fn a_day_lasts_day_length_seconds() {
+        let mut clock = WorldClock::new(0.25);
+
+        clock.fast_forward(DAY_LENGTH / 4.0);
+        assert_close(clock.time_of_day(), 0.5);
+
+        clock.fast_forward(DAY_LENGTH);
+        assert_close(clock.time_of_day(), 0.5);
+
+        clock.fast_forward(DAY_LENGTH * 3.0 + DAY_LENGTH / 2.0);
+        assert_close(clock.time_of_day(), 0.0);
+    }
+
+    #[test]
+    fn update_follows_the_timer() {
+        let mut clock = WorldClock::new(0.0);
+        let start = Instant::now();
+
+        // The first update only starts the clock
+        clock.update(start);
+        assert_close(clock.time_of_day(), 0.0);
+
+        clock.update(start + Duration::from_secs_f32(DAY_LENGTH / 4.0));
+        assert_close(clock.time_of_day(), 0.25);
+    }
+
+    #[test]
+    fn time_stands_still_while_the_timer_is_paused() {
+        let mut clock = WorldClock::new(0.0);
+        let now = Instant::now();
+
+        clock.update(now);
+        clock.update(now);
+        clock.update(now);
+        assert_close(clock.time_of_day(), 0.0);
+    }
+
+    #[test]
+    fn speed_scales_the_elapsed_time() {
+        let mut clock = WorldClock::new(0.0);
+        clock.speed = 2.0;
+        let start = Instant::now();
+
+        clock.update(start);
+        clock.update(start + Duration::from_secs_f32(DAY_LENGTH / 8.0));
+        assert_close(clock.time_of_day(), 0.25);
+    }
+
+    #[test]
+    fn sky_light_is_full_at_noon_and_lowest_at_midnight() {
+        assert_close(WorldClock::new(0.25).sky_light_multiplier(), 1.0);
+        assert_close(WorldClock::new(0.75).sky_light_multiplier(), NIGHT_SKY_LIGHT);
+
+        let sunset = WorldClock::new(0.5).sky_light_multiplier();
+        assert!(sunset > NIGHT_SKY_LIGHT && sunset < 1.0, "{sunset} at sunset");
+    }
+}
@@ -0,0 +1,559 @@
+use crate::block_registry::{known_blocks, KnownBlocks};
+use crate::chunk::{BlockArray, BlockID, Chunk};
+use crate::chunk_manager::{CHUNK_SIZE, CHUNK_VOLUME};
+use crate::constants::{SEA_LEVEL, SNOW_LINE};
+use noise::{NoiseFn, SuperSimplex};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/*
+    Terrain generation
+
+    Every column gets its height and biome from a few 2D noise maps that only depend on the seed :
+        continentalness : low frequency, oceans where it is low
+        mountainness    : low frequency, mountains where it is high
+        temperature     : picks deserts where it is hot and dry
+        humidity        : picks forests where it is wet
+        detail          : fractal noise (several octaves) for the hills inside a biome
+
+    The height is blended continuously between the biomes so there are no cliffs at the borders,
+    the biome itself only picks the surface blocks and the decorations
+
+    Underground is stone down to a bedrock floor, carved by :
+        tunnels  : where two 3D noise fields are both close to 0 (spaghetti caves), they can reach the surface
+        caverns  : where a third 3D noise field is high, only deep underground
+        ravines  : narrow and deep cuts along the lines where a 2D noise field crosses 0
+    Ore veins are random walks through the stone, they are kept inside their chunk
+    Decorations use a hash of the seed and the column instead of a random generator, so a chunk is
+    always generated the same way whatever the order chunks are generated in
+*/
+
+const TREE_HEIGHT: i32 = 5;
+// Leaves reach 2 blocks away from the trunk, so trees rooted in a neighbouring chunk can spill into this one
+const TREE_RADIUS: i32 = 2;
+
+// Octaves of the fractal noise used for the hills
+const DETAIL_OCTAVES: u32 = 5;
+const RIDGE_OCTAVES: u32 = 4;
+const CLIMATE_OCTAVES: u32 = 2;
+
+// Bedrock is a full layer at y = 0 with a few more blocks above it
+const BEDROCK_LAYERS: i32 = 4;
+// The lower, the thinner the tunnels
+const TUNNEL_THICKNESS: f64 = 0.01;
+const CAVERN_THRESHOLD: f64 = 0.6;
+// Distance to the noise line, in noise units, where ravines are cut
+const RAVINE_WIDTH: f64 = 0.025;
+const RAVINE_DEPTH: f64 = 35.0;
+// Caves don't come closer to the floor of the oceans and beaches so the water stays where it is
+const UNDERWATER_CAVE_ROOF: i32 = 6;
+
+// Veins of an ore in the stone
+struct OreVein {
+    block: BlockID,
+    // Height range where the veins start
+    min_y: i32,
+    max_y: i32,
+    // For a chunk that is entirely in the height range
+    veins_per_chunk: u32,
+    // Number of steps of the random walk
+    vein_size: u32,
+}
+
+// Makes the chunk generation pluggable, the chunk workers only need this
+pub trait WorldGenerator: Send + Sync {
+    fn seed(&self) -> u32;
+
+    // Height of the highest solid block of the column
+    fn surface_height(&self, x: i32, z: i32) -> i32;
+
+    // Must only depend on the seed and the coordinates, chunks are generated in any order and on any thread
+    fn generate_chunk(&self, coords: (i32, i32, i32)) -> Box<BlockArray>;
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Biome {
+    Plains,
+    Forest,
+    Desert,
+    Mountains,
+    Ocean,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Column {
+    pub height: i32,
+    pub biome: Biome,
+}
+
+// Sum of several octaves of noise, each one with twice the frequency and half the amplitude of the previous
+// The result stays between -1 and 1
+fn fractal_noise(noise: &SuperSimplex, x: f64, z: f64, octaves: u32) -> f64 {
+    let (mut sum, mut amplitude, mut frequency, mut total_amplitude) = (0.0, 1.0, 1.0, 0.0);
+
+    for _ in 0..octaves {
+        sum += noise.get([x * frequency, z * frequency]) * amplitude;
+        total_amplitude += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+
+    sum / total_amplitude
+}
+
+fn smoothstep(edge0: f64, edge1: f64, x: f64) -> f64 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// Default generator, see the comment at the top of the file
+pub struct TerrainGenerator {
+    seed: u32,
+    continentalness: SuperSimplex,
+    mountainness: SuperSimplex,
+    temperature: SuperSimplex,
+    humidity: SuperSimplex,
+    detail: SuperSimplex,
+    ridges: SuperSimplex,
+    tunnels: [SuperSimplex; 2],
+    caverns: SuperSimplex,
+    ravines: SuperSimplex,
+    ravine_regions: SuperSimplex,
+    blocks: KnownBlocks,
+    ores: [OreVein; 4],
+}
+
+impl TerrainGenerator {
+    pub fn new(seed: u32) -> Self {
+        // Each noise map gets its own seed so they don't line up
+        let noise = |i: u32| SuperSimplex::new(seed.wrapping_add(i.wrapping_mul(0x9e37_79b9)));
+
+        Self {
+            seed,
+            continentalness: noise(0),
+            mountainness: noise(1),
+            temperature: noise(2),
+            humidity: noise(3),
+            detail: noise(4),
+            ridges: noise(5),
+            tunnels: [noise(6), noise(7)],
+            caverns: noise(8),
+            ravines: noise(9),
+            ravine_regions: noise(10),
+            blocks: *known_blocks(),
+            ores: [
+                OreVein {
+                    block: known_blocks().coal_ore,
+                    min_y: 5,
+                    max_y: 128,
+                    veins_per_chunk: 10,
+                    vein_size: 10,
+                },
+                OreVein {
+                    block: known_blocks().iron_ore,
+                    min_y: 5,
+                    max_y: 64,
+                    veins_per_chunk: 6,
+                    vein_size: 7,
+                },
+                OreVein {
+                    block: known_blocks().gold_ore,
+                    min_y: 5,
+                    max_y: 32,
+                    veins_per_chunk: 2,
+                    vein_size: 6,
+                },
+                OreVein {
+                    block: known_blocks().diamond_ore,
+                    min_y: 1,
+                    max_y: 16,
+                    veins_per_chunk: 1,
+                    vein_size: 5,
+                },
+            ],
+        }
+    }
+
+    pub fn column(&self, x: i32, z: i32) -> Column {
+        let (x, z) = (x as f64, z as f64);
+
+        let continentalness =
+            fractal_noise(&self.continentalness, x / 700.0, z / 700.0, CLIMATE_OCTAVES);
+        let mountainness = fractal_noise(&self.mountainness, x / 500.0, z / 500.0, CLIMATE_OCTAVES);
+        let temperature = fractal_noise(&self.temperature, x / 450.0, z / 450.0, CLIMATE_OCTAVES);
+        let humidity = fractal_noise(&self.humidity, x / 350.0, z / 350.0, CLIMATE_OCTAVES);
+        let detail = fractal_noise(&self.detail, x / 96.0, z / 96.0, DETAIL_OCTAVES);
+
+        let sea_level = SEA_LEVEL as f64;
+        let mut height = sea_level + 4.0 + detail * 6.0;
+
+        // Ridged noise gives sharp peaks, only computed where it is used
+        let mountain_factor = smoothstep(0.15, 0.45, mountainness);
+        if mountain_factor > 0.0 {
+            let ridges =
+                1.0 - fractal_noise(&self.ridges, x / 160.0, z / 160.0, RIDGE_OCTAVES).abs();
+            height += mountain_factor * (12.0 + ridges * ridges * 60.0);
+        }
+
+        let ocean_factor = smoothstep(-0.2, -0.4, continentalness);
+        let ocean_floor = sea_level - 14.0 + detail * 4.0;
+        let height = (height + (ocean_floor - height) * ocean_factor).floor() as i32;
+
+        let biome = if height < SEA_LEVEL {
+            Biome::Ocean
+        } else if mountain_factor > 0.5 {
+            Biome::Mountains
+        } else if temperature > 0.2 && humidity < 0.1 {
+            Biome::Desert
+        } else if humidity > 0.15 {
+            Biome::Forest
+        } else {
+            Biome::Plains
+        };
+
+        Column { height, biome }
+    }
+
+    // Cheap integer hash so that decorations only depend on the seed and the column
+    fn column_hash(&self, x: i32, z: i32) -> u32 {
+        let mut h = (x as u32).wrapping_mul(0x8da6_b343)
+            ^ (z as u32).wrapping_mul(0xd816_3841)
+            ^ self.seed.wrapping_mul(0xcb1a_b31f);
+        h ^= h >> 16;
+        h = h.wrapping_mul(0x7feb_352d);
+        h ^= h >> 15;
+        h = h.wrapping_mul(0x846c_a68b);
+        h ^ (h >> 16)
+    }
+
+    fn is_bedrock(&self, x: i32, y: i32, z: i32) -> bool {
+        let hash = self.column_hash(x, z) ^ (y as u32).wrapping_mul(0x2c1b_3c6d);
+        y == 0 || y < BEDROCK_LAYERS && hash % (y as u32 + 1) == 0
+    }
+
+    fn is_cave(&self, x: i32, y: i32, z: i32, column: &Column) -> bool {
+        let roof = if column.height <= SEA_LEVEL + 1 {
+            column.height - UNDERWATER_CAVE_ROOF
+        } else {
+            column.height
+        };
+
+        if y < BEDROCK_LAYERS || y > roof {
+            return false;
+        }
+
+        let (x, y, z) = (x as f64, y as f64, z as f64);
+        let tunnel_position = [x / 48.0, y / 32.0, z / 48.0];
+        let [a, b] = self
+            .tunnels
+            .each_ref()
+            .map(|noise| noise.get(tunnel_position));
+
+        if a * a + b * b < TUNNEL_THICKNESS {
+            return true;
+        }
+
+        y < (roof - 12) as f64
+            && self.caverns.get([x / 64.0, y / 40.0, z / 64.0]) > CAVERN_THRESHOLD
+    }
+
+    // Number of blocks cut from the top of the column
+    fn ravine_depth(&self, x: i32, z: i32, column: &Column) -> i32 {
+        if column.height <= SEA_LEVEL + 1 {
+            return 0;
+        }
+
+        let (x, z) = (x as f64, z as f64);
+
+        // Ravines only appear in some regions, otherwise they would cross the whole world
+        let region = smoothstep(0.3, 0.45, self.ravine_regions.get([x / 300.0, z / 300.0]));
+        if region == 0.0 {
+            return 0;
+        }
+
+        let distance = self.ravines.get([x / 220.0, z / 220.0]).abs();
+        if distance >= RAVINE_WIDTH {
+            return 0;
+        }
+
+        // Deepest at the middle of the ravine
+        let t = 1.0 - distance / RAVINE_WIDTH;
+        (t.sqrt() * RAVINE_DEPTH * region) as i32
+    }
+
+    // Decorations aren't placed over a cave entrance or a ravine
+    fn is_surface_carved(&self, x: i32, z: i32, column: &Column) -> bool {
+        self.ravine_depth(x, z, column) > 0 || self.is_cave(x, column.height, z, column)
+    }
+
+    // Veins are random walks that stay inside the chunk, so they only depend on the seed and the chunk
+    fn place_ores(&self, blocks: &mut BlockArray, (cx, cy, cz): (i32, i32, i32)) {
+        let size = CHUNK_SIZE as i32;
+        let (min_y, max_y) = (cy * size, cy * size + size - 1);
+        let chunk_hash = self.column_hash(cx, cz) ^ (cy as u32).wrapping_mul(0x9e37_79b9);
+        let mut rng = StdRng::seed_from_u64(((chunk_hash as u64) << 32) | self.seed as u64);
+
+        for ore in &self.ores {
+            let (low, high) = (ore.min_y.max(min_y), ore.max_y.min(max_y));
+            if low > high {
+                continue;
+            }
+
+            // Chunks that are only partly in the range get fewer veins
+            let layers = (high - low + 1) as u32;
+            let veins = (ore.veins_per_chunk * layers + rng.gen_range(0..CHUNK_SIZE)) / CHUNK_SIZE;
+
+            for _ in 0..veins {
+                let mut position = [
+                    rng.gen_range(0..size),
+                    rng.gen_range(low..=high) - min_y,
+                    rng.gen_range(0..size),
+                ];
+
+                for _ in 0..ore.vein_size {
+                    let [x, y, z] = position.map(|c| c as u32);
+                    let block = &mut blocks[Chunk::coords_to_index(x, y, z)];
+                    if *block == self.blocks.stone {
+                        *block = ore.block;
+                    }
+
+                    let axis = rng.gen_range(0..3);
+                    let step = if rng.gen() { 1 } else { -1 };
+                    position[axis] = (position[axis] + step).clamp(0, size - 1);
+                }
+            }
+        }
+    }
+
+    // Blocks of the column from the top : the surface block, the 3 blocks under it and the rest
+    fn column_layers(&self, column: &Column) -> (BlockID, BlockID, BlockID) {
+        let b = &self.blocks;
+
+        match column.biome {
+            // Beaches around the oceans
+            _ if column.height <= SEA_LEVEL + 1 && column.biome != Biome::Mountains => {
+                (b.sand, b.sand, b.stone)
+            }
+            Biome::Desert => (b.sand, b.sand, b.stone),
+            Biome::Mountains if column.height >= SNOW_LINE => (b.snow, b.stone, b.stone),
+            Biome::Mountains => (b.stone, b.stone, b.stone),
+            Biome::Plains | Biome::Forest | Biome::Ocean => (b.grass, b.dirt, b.stone),
+        }
+    }
+
+    fn has_tree(&self, x: i32, z: i32, column: &Column) -> bool {
+        let chance = match column.biome {
+            Biome::Forest => 20,
+            Biome::Plains => 150,
+            _ => return false,
+        };
+
+        column.height > SEA_LEVEL + 1 && self.column_hash(x, z) % chance == 0
+    }
+
+    fn has_cactus(&self, x: i32, z: i32, column: &Column) -> bool {
+        column.biome == Biome::Desert
+            && column.height > SEA_LEVEL + 1
+            && self.column_hash(x, z) % 120 == 0
+    }
+}
+
+impl WorldGenerator for TerrainGenerator {
+    fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    fn surface_height(&self, x: i32, z: i32) -> i32 {
+        self.column(x, z).height
+    }
+
+    // Only touches plain data so it can run on a worker thread
+    fn generate_chunk(&self, (cx, cy, cz): (i32, i32, i32)) -> Box<BlockArray> {
+        let size = CHUNK_SIZE as i32;
+        let b = &self.blocks;
+        let (min_x, min_y, min_z) = (cx * size, cy * size, cz * size);
+        let mut blocks = Box::new([BlockID::AIR; CHUNK_VOLUME as usize]);
+
+        // Only writes the block if it falls inside this chunk
+        let mut set_block = |x: i32, y: i32, z: i32, block: BlockID| {
+            let (lx, ly, lz) = (x - min_x, y - min_y, z - min_z);
+
+            if (0..size).contains(&lx) && (0..size).contains(&ly) && (0..size).contains(&lz) {
+                blocks[Chunk::coords_to_index(lx as u32, ly as u32, lz as u32)] = block;
+            }
+        };
+
+        // Columns of the chunk and of the border where trees can grow from
+        let border_size = size + 2 * TREE_RADIUS;
+        let columns: Vec<Column> = (0..border_size * border_size)
+            .map(|i| {
+                let (x, z) = (i % border_size, i / border_size);
+                self.column(min_x - TREE_RADIUS + x, min_z - TREE_RADIUS + z)
+            })
+            .collect();
+        let column_at = |x: i32, z: i32| {
+            let (bx, bz) = (x - min_x + TREE_RADIUS, z - min_z + TREE_RADIUS);
+            &columns[(bx + bz * border_size) as usize]
+        };
+
+        for x in min_x..min_x + size {
+            for z in min_z..min_z + size {
+                let column = column_at(x, z);
+                let (surface, subsurface, underground) = self.column_layers(column);
+                let height = column.height;
+                let ravine_depth = self.ravine_depth(x, z, column);
+
+                for y in min_y.max(0)..(min_y + size).min(height + 1) {
+                    let block = if y < BEDROCK_LAYERS && self.is_bedrock(x, y, z) {
+                        b.bedrock
+                    } else if height - y < ravine_depth || self.is_cave(x, y, z, column) {
+                        continue;
+                    } else {
+                        match height - y {
+                            0 => surface,
+                            1..=3 => subsurface,
+                            _ => underground,
+                        }
+                    };
+                    set_block(x, y, z, block);
+                }
+
+                for y in (height + 1).max(min_y)..=SEA_LEVEL.min(min_y + size - 1) {
+                    set_block(x, y, z, b.water);
+                }
+            }
+        }
+
+        // Trees are placed in global column order so overlapping trees look the same from every chunk
+        for x in min_x - TREE_RADIUS..min_x + size + TREE_RADIUS {
+            for z in min_z - TREE_RADIUS..min_z + size + TREE_RADIUS {
+                let column = column_at(x, z);
+                let y = column.height;
+
+                if !(min_y - TREE_HEIGHT - 3..min_y + size).contains(&y)
+                    || self.is_surface_carved(x, z, column)
+                {
+                    continue;
+                }
+
+                if self.has_cactus(x, z, column) {
+                    let h = 1 + (self.column_hash(x, z) >> 8) as i32 % 3;
+                    for i in y + 1..y + 1 + h {
+                        set_block(x, i, z, b.cactus);
+                    }
+                }
+
+                if !self.has_tree(x, z, column) {
+                    continue;
+                }
+
+                let h = TREE_HEIGHT + (self.column_hash(x, z) >> 8) as i32 % 2;
+
+                for i in y + 1..y + 1 + h {
+                    set_block(x, i, z, b.oak_log)
+                }
+
+                for yy in y + h - 2..=y + h - 1 {
+                    for xx in x - 2..=x + 2 {
+                        for zz in z - 2..=z + 2 {
+                            if xx != x || zz != z {
+                                set_block(xx, yy, zz, b.oak_leaves);
+                            }
+                        }
+                    }
+                }
+
+                for xx in x - 1..=x + 1 {
+                    for zz in z - 1..=z + 1 {
+                        if xx != x || zz != z {
+                            set_block(xx, y + h, zz, b.oak_leaves);
+                        }
+                    }
+                }
+
+                set_block(x, y + h + 1, z, b.oak_leaves);
+                set_block(x + 1, y + h + 1, z, b.oak_leaves);
+                set_block(x - 1, y + h + 1, z, b.oak_leaves);
+                set_block(x, y + h + 1, z + 1, b.oak_leaves);
+                set_block(x, y + h + 1, z - 1, b.oak_leaves);
+            }
+        }
+
+        self.place_ores(&mut blocks, (cx, cy, cz));
+
+        blocks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_registry::install_test_registry;
+    use std::thread;
+
+    // Underground chunks with ores, surface chunks and chunks at negative coordinates
+    const COORDS: [(i32, i32, i32); 6] =
+        [(0, 0, 0), (3, 1, -2), (0, 2, 0), (-5, 3, 7), (-1, 2, -1), (12, 4, -9)];
+
+    fn chunk_bytes(generator: &TerrainGenerator, coords: (i32, i32, i32)) -> Vec<u8> {
+        Chunk::from_blocks(&generator.generate_chunk(coords)).to_bytes()
+    }
+
+    #[test]
+    fn same_seed_generates_identical_chunks() {
+        install_test_registry();
+        let first = TerrainGenerator::new(42);
+        let second = TerrainGenerator::new(42);
+
+        for coords in COORDS {
+            assert_eq!(chunk_bytes(&first, coords), chunk_bytes(&second, coords), "{coords:?}");
+        }
+    }
+
+    #[test]
+    fn generation_does_not_depend_on_the_order_or_the_thread() {
+        install_test_registry();
+        let expected: Vec<Vec<u8>> = {
+            let generator = TerrainGenerator::new(42);
+            COORDS.iter().map(|&coords| chunk_bytes(&generator, coords)).collect()
+        };
+
+        // Like the chunk workers, one generator shared by several threads in any order
+        let generator = &TerrainGenerator::new(42);
+        thread::scope(|scope| {
+            let handles: Vec<_> = COORDS
+                .iter()
+                .rev()
+                .map(|&coords| scope.spawn(move || chunk_bytes(generator, coords)))
+                .collect();
+
+            for (handle, expected) in handles.into_iter().zip(expected.iter().rev()) {
+                assert_eq!(&handle.join().unwrap(), expected);
+            }
+        });
+    }
+
+    #[test]
+    fn ores_are_generated_the_same_way() {
+        install_test_registry();
+        let coal_ore = BlockID::named("coal_ore");
+        let generator = TerrainGenerator::new(42);
+
+        // The ores are the only random part, check they are actually there
+        let blocks = generator.generate_chunk((3, 1, -2));
+        assert!(blocks.iter().any(|&block| block == coal_ore));
+        assert_eq!(blocks, TerrainGenerator::new(42).generate_chunk((3, 1, -2)));
+    }
+
+    #[test]
+    fn different_seeds_generate_different_chunks() {
+        install_test_registry();
+        let first = TerrainGenerator::new(42);
+        let second = TerrainGenerator::new(43);
+
+        assert!(COORDS
+            .iter()
+            .any(|&coords| chunk_bytes(&first, coords) != chunk_bytes(&second, coords)));
+        assert!((0..16).any(|x| first.surface_height(x, 0) != second.surface_height(x, 0)));
+    }
+}
@@ -0,0 +1,532 @@
+use crate::chunk_manager::ChunkManager;
+use crate::constants::START_TIME_OF_DAY;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use nalgebra_glm::{vec3, Vec3};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+/*
+    World directory layout
+
+    <world>/level.dat                 : metadata (seed, spawn point, player position and rotation, time of day) as "key = value" lines
+    <world>/regions/r.X.Y.Z.region    : one file per REGION_SIZE^3 chunks
+
+    Region file layout (all integers are little endian)
+
+    magic   : b"MCRG"
+    version : u32
+    count   : u32, number of chunks stored in the region
+    header  : count entries of (index in the region: u32, offset: u32, length: u32)
+    data    : zlib compressed chunk data, referenced by the header
+*/
+
+pub const REGION_SIZE: i32 = 32;
+const REGION_VOLUME: usize = (REGION_SIZE * REGION_SIZE * REGION_SIZE) as usize;
+const REGION_MAGIC: &[u8; 4] = b"MCRG";
+const REGION_VERSION: u32 = 1;
+// Only the saved chunks have an entry, most regions hold a handful of modified chunks and a table
+// of the whole region would take hundreds of KiB
+const REGION_HEADER_SIZE: usize = 4 + 4 + 4;
+const REGION_ENTRY_SIZE: usize = 4 + 4 + 4;
+
+type ChunkCoords = (i32, i32, i32);
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn region_file_name((x, y, z): ChunkCoords) -> String {
+    format!("r.{x}.{y}.{z}.region")
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WorldMetadata {
+    pub seed: u32,
+    // Where the player respawns after dying
+    pub spawn_point: Vec3,
+    pub player_position: Vec3,
+    pub player_rotation: Vec3,
+    // See WorldClock
+    pub time_of_day: f32,
+}
+
+impl WorldMetadata {
+    pub fn new(seed: u32, spawn_point: Vec3) -> Self {
+        Self {
+            seed,
+            spawn_point,
+            player_position: spawn_point,
+            player_rotation: vec3(0.0, 0.0, 0.0),
+            time_of_day: START_TIME_OF_DAY,
+        }
+    }
+
+    fn serialize(&self) -> String {
+        let (s, p, r) = (&self.spawn_point, &self.player_position, &self.player_rotation);
+
+        format!(
+            "seed = {}\nspawn_point = {} {} {}\nplayer_position = {} {} {}\nplayer_rotation = {} {} {}\ntime_of_day = {}\n",
+            self.seed, s.x, s.y, s.z, p.x, p.y, p.z, r.x, r.y, r.z, self.time_of_day
+        )
+    }
+
+    fn deserialize(text: &str) -> io::Result<Self> {
+        let mut values = HashMap::new();
+
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| invalid_data(format!("Malformed metadata line: {line}")))?;
+            values.insert(key.trim(), value.trim());
+        }
+
+        let get = |key: &str| {
+            values
+                .get(key)
+                .copied()
+                .ok_or_else(|| invalid_data(format!("Missing metadata key: {key}")))
+        };
+
+        let parse_vec3 = |key: &str| -> io::Result<Vec3> {
+            let components = get(key)?
+                .split_whitespace()
+                .map(|c| c.parse::<f32>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|err| invalid_data(format!("Invalid value for {key}: {err}")))?;
+
+            match components.as_slice() {
+                &[x, y, z] => Ok(vec3(x, y, z)),
+                _ => Err(invalid_data(format!("Expected 3 components for {key}"))),
+            }
+        };
+
+        let player_position = parse_vec3("player_position")?;
+
+        Ok(Self {
+            seed: get("seed")?
+                .parse()
+                .map_err(|err| invalid_data(format!("Invalid seed: {err}")))?,
+            // Worlds saved before survival mode respawn where the player was
+            spawn_point: match values.get("spawn_point") {
+                Some(_) => parse_vec3("spawn_point")?,
+                None => player_position,
+            },
+            player_position,
+            player_rotation: parse_vec3("player_rotation")?,
+            // Worlds saved before the day/night cycle don't have it
+            time_of_day: match values.get("time_of_day") {
+                Some(value) => value
+                    .parse()
+                    .map_err(|err| invalid_data(format!("Invalid time_of_day: {err}")))?,
+                None => START_TIME_OF_DAY,
+            },
+        })
+    }
+}
+
+// In-memory copy of a region file, chunks are kept compressed
+#[derive(Default)]
+struct RegionFile {
+    chunks: HashMap<usize, Vec<u8>>,
+    dirty: bool,
+}
+
+impl RegionFile {
+    fn read(path: &Path) -> io::Result<RegionFile> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(RegionFile::default()),
+            Err(err) => return Err(err),
+        };
+
+        if bytes.len() < REGION_HEADER_SIZE || &bytes[0..4] != REGION_MAGIC {
+            return Err(invalid_data(format!("{} is not a region file", path.display())));
+        }
+
+        let read_u32 = |at: usize| {
+            bytes
+                .get(at..at + 4)
+                .map(|word| u32::from_le_bytes(word.try_into().unwrap()) as usize)
+                .ok_or_else(|| invalid_data(format!("{} is truncated", path.display())))
+        };
+
+        let version = read_u32(4)? as u32;
+        if version != REGION_VERSION {
+            return Err(invalid_data(format!(
+                "Unsupported region version {version} in {}",
+                path.display()
+            )));
+        }
+
+        // (index, offset, length) of the stored chunks
+        let entries = (0..read_u32(8)?)
+            .map(|i| {
+                let entry = REGION_HEADER_SIZE + i * REGION_ENTRY_SIZE;
+                Ok((read_u32(entry)?, read_u32(entry + 4)?, read_u32(entry + 8)?))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let mut chunks = HashMap::new();
+
+        for (index, offset, length) in entries {
+            if index >= REGION_VOLUME {
+                return Err(invalid_data(format!(
+                    "Chunk index {index} is out of the region in {}",
+                    path.display()
+                )));
+            }
+
+            let data = bytes.get(offset..offset + length).ok_or_else(|| {
+                invalid_data(format!("Chunk {index} is out of bounds in {}", path.display()))
+            })?;
+            chunks.insert(index, data.to_vec());
+        }
+
+        Ok(RegionFile {
+            chunks,
+            dirty: false,
+        })
+    }
+
+    fn write(&self, path: &Path) -> io::Result<()> {
+        let header_size = REGION_HEADER_SIZE + self.chunks.len() * REGION_ENTRY_SIZE;
+        let mut header = Vec::with_capacity(header_size);
+        let mut data = Vec::new();
+
+        header.extend_from_slice(REGION_MAGIC);
+        header.extend_from_slice(&REGION_VERSION.to_le_bytes());
+        header.extend_from_slice(&(self.chunks.len() as u32).to_le_bytes());
+
+        // In the order of the region so that saving the same chunks gives the same file
+        let mut indices: Vec<usize> = self.chunks.keys().copied().collect();
+        indices.sort_unstable();
+
+        for index in indices {
+            let chunk = &self.chunks[&index];
+            let offset = header_size + data.len();
+            data.extend_from_slice(chunk);
+
+            header.extend_from_slice(&(index as u32).to_le_bytes());
+            header.extend_from_slice(&(offset as u32).to_le_bytes());
+            header.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+        }
+
+        // Write to a temporary file first so a crash never leaves a half written region behind
+        let tmp_path = path.with_extension("tmp");
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(&header)?;
+        file.write_all(&data)?;
+        file.sync_all()?;
+        fs::rename(tmp_path, path)
+    }
+}
+
+pub struct WorldSave {
+    directory: PathBuf,
+    regions: HashMap<ChunkCoords, RegionFile>,
+}
+
+impl WorldSave {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            regions: HashMap::new(),
+        }
+    }
+
+    fn metadata_path(&self) -> PathBuf {
+        self.directory.join("level.dat")
+    }
+
+    fn regions_directory(&self) -> PathBuf {
+        self.directory.join("regions")
+    }
+
+    fn region_path(&self, coords: ChunkCoords) -> PathBuf {
+        self.regions_directory().join(region_file_name(coords))
+    }
+
+    // Region coordinates and the index of the chunk inside its region
+    fn locate_chunk((x, y, z): ChunkCoords) -> (ChunkCoords, usize) {
+        let region = (
+            x.div_euclid(REGION_SIZE),
+            y.div_euclid(REGION_SIZE),
+            z.div_euclid(REGION_SIZE),
+        );
+        let (lx, ly, lz) = (
+            x.rem_euclid(REGION_SIZE),
+            y.rem_euclid(REGION_SIZE),
+            z.rem_euclid(REGION_SIZE),
+        );
+
+        (region, (ly * REGION_SIZE * REGION_SIZE + lz * REGION_SIZE + lx) as usize)
+    }
+
+    // A region that can't be read is moved aside to r.X.Y.Z.corrupt and starts over empty, so the
+    // chunks modified afterwards can still be saved. The error is only returned the first time
+    fn region(&mut self, coords: ChunkCoords) -> io::Result<&mut RegionFile> {
+        if !self.regions.contains_key(&coords) {
+            let path = self.region_path(coords);
+
+            match RegionFile::read(&path) {
+                Ok(region) => {
+                    self.regions.insert(coords, region);
+                }
+                Err(err) => {
+                    fs::rename(&path, path.with_extension("corrupt"))?;
+                    self.regions.insert(coords, RegionFile::default());
+                    return Err(err);
+                }
+            }
+        }
+
+        Ok(self.regions.get_mut(&coords).unwrap())
+    }
+
+    pub fn exists(&self) -> bool {
+        self.metadata_path().is_file()
+    }
+
+    pub fn load_metadata(&self) -> io::Result<WorldMetadata> {
+        WorldMetadata::deserialize(&fs::read_to_string(self.metadata_path())?)
+    }
+
+    pub fn save_metadata(&self, metadata: &WorldMetadata) -> io::Result<()> {
+        fs::create_dir_all(&self.directory)?;
+        fs::write(self.metadata_path(), metadata.serialize())
+    }
+
+    // Returns the uncompressed chunk data, or None if the chunk was never saved
+    pub fn load_chunk(&mut self, coords: ChunkCoords) -> io::Result<Option<Vec<u8>>> {
+        let (region_coords, index) = WorldSave::locate_chunk(coords);
+        let region = self.region(region_coords)?;
+
+        match region.chunks.get(&index) {
+            Some(compressed) => {
+                let mut data = Vec::new();
+                ZlibDecoder::new(compressed.as_slice()).read_to_end(&mut data)?;
+                Ok(Some(data))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // Stores the chunk in memory, call `flush` to write the modified regions to disk
+    pub fn store_chunk(&mut self, coords: ChunkCoords, data: &[u8]) -> io::Result<()> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data)?;
+        let compressed = encoder.finish()?;
+
+        let (region_coords, index) = WorldSave::locate_chunk(coords);
+        let region = self.region(region_coords)?;
+        region.chunks.insert(index, compressed);
+        region.dirty = true;
+
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        let regions_directory = self.regions_directory();
+        fs::create_dir_all(&regions_directory)?;
+
+        for (&coords, region) in self.regions.iter_mut() {
+            if region.dirty {
+                region.write(&regions_directory.join(region_file_name(coords)))?;
+                region.dirty = false;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ChunkManager {
+    // Chunks that were never modified are not saved, the generator recreates them identically
+    pub fn save_world(&self, world_save: &mut WorldSave) -> io::Result<()> {
+        for (&coords, chunk) in self.loaded_chunks.iter() {
+            if chunk.modified {
+                world_save.store_chunk(coords, &chunk.to_bytes())?;
+            }
+        }
+
+        world_save.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_registry::install_test_registry;
+    use crate::block_state::{BlockState, Property};
+    use crate::chunk::{BlockID, Chunk};
+    use crate::types::UVMap;
+    use crate::world_generator::TerrainGenerator;
+    use std::sync::Arc;
+
+    // Empty directory of its own for each test
+    fn test_directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir()
+            .join(format!("minecraft-world-save-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        directory
+    }
+
+    fn modified_chunk(block: &str) -> Chunk {
+        let mut chunk = Chunk::full_of_block(BlockID::named("stone"));
+        chunk.set_block(1, 2, 3, BlockID::named(block));
+        chunk.set_block_state(
+            15,
+            15,
+            15,
+            BlockState::new(BlockID::named("oak_log")).with(Property::Axis, 2),
+        );
+        chunk
+    }
+
+    // The only chunk of a region that was written to disk
+    fn saved_region(directory: &Path) -> PathBuf {
+        let mut world_save = WorldSave::new(directory);
+        world_save
+            .store_chunk((0, 0, 0), &modified_chunk("dirt").to_bytes())
+            .unwrap();
+        world_save.flush().unwrap();
+
+        world_save.region_path((0, 0, 0))
+    }
+
+    #[test]
+    fn modified_world_round_trip() {
+        install_test_registry();
+        let directory = test_directory("round-trip");
+
+        let mut chunk_manager =
+            ChunkManager::new(Arc::new(TerrainGenerator::new(1)), &UVMap::default());
+        // Two regions, one of them at negative coordinates
+        let modified = [((0, 0, 0), "dirt"), ((31, 5, 2), "sand"), ((-1, 3, -40), "glass")];
+        for (coords, block) in modified {
+            chunk_manager
+                .loaded_chunks
+                .insert(coords, modified_chunk(block));
+        }
+        chunk_manager
+            .loaded_chunks
+            .insert((1, 0, 0), Chunk::full_of_block(BlockID::named("stone")));
+
+        let metadata = WorldMetadata {
+            seed: 1234,
+            spawn_point: vec3(0.5, 70.0, 0.5),
+            player_position: vec3(-12.25, 64.0, 1.0 / 3.0),
+            player_rotation: vec3(0.1, -2.7, 0.0),
+            time_of_day: 0.8125,
+        };
+
+        let mut world_save = WorldSave::new(&directory);
+        chunk_manager.save_world(&mut world_save).unwrap();
+        world_save.save_metadata(&metadata).unwrap();
+
+        let mut reopened = WorldSave::new(&directory);
+        assert!(reopened.exists());
+        assert_eq!(reopened.load_metadata().unwrap(), metadata);
+
+        for (coords, _) in modified {
+            let saved = reopened.load_chunk(coords).unwrap();
+            assert_eq!(saved, Some(chunk_manager.loaded_chunks[&coords].to_bytes()));
+
+            let mut chunk = Chunk::empty();
+            chunk.load_bytes(&saved.unwrap()).unwrap();
+            assert_eq!(chunk.to_bytes(), chunk_manager.loaded_chunks[&coords].to_bytes());
+        }
+
+        // The generator recreates the chunks that were never modified
+        assert_eq!(reopened.load_chunk((1, 0, 0)).unwrap(), None);
+        assert_eq!(reopened.load_chunk((0, 1, 0)).unwrap(), None);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn region_header_only_lists_saved_chunks() {
+        install_test_registry();
+        let directory = test_directory("header");
+
+        let region_path = saved_region(&directory);
+        let bytes = fs::read(&region_path).unwrap();
+        let count = u32::from_le_bytes(bytes[8..12].try_into().unwrap());
+        assert_eq!(count, 1);
+
+        // The entry of the chunk, then its data right after the header
+        let entry = |at: usize| {
+            let at = REGION_HEADER_SIZE + at;
+            u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap()) as usize
+        };
+        let (index, offset, length) = (entry(0), entry(4), entry(8));
+        assert_eq!(index, 0);
+        assert_eq!(offset, REGION_HEADER_SIZE + REGION_ENTRY_SIZE);
+        assert_eq!(bytes.len(), REGION_HEADER_SIZE + REGION_ENTRY_SIZE + length);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn missing_region_has_no_chunks() {
+        let directory = test_directory("missing");
+
+        let mut world_save = WorldSave::new(&directory);
+        assert!(!world_save.exists());
+        assert_eq!(world_save.load_chunk((3, 4, -5)).unwrap(), None);
+        assert!(world_save.load_metadata().is_err());
+    }
+
+    #[test]
+    fn truncated_region_is_an_error() {
+        install_test_registry();
+        let directory = test_directory("truncated");
+        let region_path = saved_region(&directory);
+        let bytes = fs::read(&region_path).unwrap();
+
+        // In the middle of the header, then in the middle of the chunk data
+        for length in [6, REGION_HEADER_SIZE + 5, bytes.len() - 10] {
+            fs::write(&region_path, &bytes[..length]).unwrap();
+
+            let mut world_save = WorldSave::new(&directory);
+            assert!(world_save.load_chunk((0, 0, 0)).is_err(), "{length} bytes");
+        }
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn corrupt_region_is_an_error() {
+        install_test_registry();
+        let directory = test_directory("corrupt");
+        let region_path = saved_region(&directory);
+        let bytes = fs::read(&region_path).unwrap();
+        let data_start = REGION_HEADER_SIZE + REGION_ENTRY_SIZE;
+
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+
+        let mut bad_version = bytes.clone();
+        bad_version[4..8].copy_from_slice(&7u32.to_le_bytes());
+
+        let mut bad_index = bytes.clone();
+        bad_index[REGION_HEADER_SIZE..REGION_HEADER_SIZE + 4]
+            .copy_from_slice(&(REGION_VOLUME as u32).to_le_bytes());
+
+        let mut bad_data = bytes.clone();
+        bad_data[data_start..].fill(0xAA);
+
+        for corrupt in [bad_magic, bad_version, bad_index, bad_data] {
+            fs::write(&region_path, corrupt).unwrap();
+
+            let mut world_save = WorldSave::new(&directory);
+            assert!(world_save.load_chunk((0, 0, 0)).is_err());
+        }
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}